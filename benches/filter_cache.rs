@@ -0,0 +1,77 @@
+// Copyright 2024 CratesLand Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compare evaluating the filters of a [`Logger`] per record against caching their results per
+//! (target, level) pair with [`Logger::cache_filters`].
+
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::BenchmarkId;
+use criterion::Criterion;
+use log::LevelFilter;
+use log::Log;
+use log::Metadata;
+use logforth::append::Null;
+use logforth::filter::TargetLevelFilter;
+use logforth::Dispatch;
+use logforth::Logger;
+
+const DISPATCHES: [usize; 3] = [1, 8, 32];
+
+const TARGETS: [&str; 4] = ["app::db", "app::http", "hyper::proto", "tokio::runtime"];
+
+/// A logger with `n` dispatches, each with a per-target level filter.
+fn logger(n: usize) -> Logger {
+    (0..n).fold(Logger::new(), |logger, _| {
+        let levels = (0..64)
+            .map(|i| (format!("crate_{i}"), LevelFilter::Trace))
+            .chain([("app".to_string(), LevelFilter::Debug)]);
+        logger.dispatch(
+            Dispatch::new()
+                .filter(TargetLevelFilter::new(levels, LevelFilter::Warn))
+                .append(Null::default()),
+        )
+    })
+}
+
+fn enabled(logger: &Logger) -> usize {
+    TARGETS
+        .iter()
+        .filter(|target| {
+            logger.enabled(
+                &Metadata::builder()
+                    .target(target)
+                    .level(log::Level::Info)
+                    .build(),
+            )
+        })
+        .count()
+}
+
+fn bench_filter_cache(c: &mut Criterion) {
+    let mut group = c.benchmark_group("enabled");
+    for n in DISPATCHES {
+        group.bench_with_input(BenchmarkId::new("uncached", n), &logger(n), |b, logger| {
+            b.iter(|| enabled(logger))
+        });
+        let cached = logger(n).cache_filters(1024);
+        group.bench_with_input(BenchmarkId::new("cached", n), &cached, |b, logger| {
+            b.iter(|| enabled(logger))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_filter_cache);
+criterion_main!(benches);
@@ -0,0 +1,72 @@
+// Copyright 2024 CratesLand Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compare formatting a record once for N sinks with a [`Tee`] against formatting it per sink.
+
+use std::io;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::BenchmarkId;
+use criterion::Criterion;
+use log::Log;
+use log::Record;
+use logforth::append::Tee;
+use logforth::append::Writer;
+use logforth::layout::TextLayout;
+use logforth::Dispatch;
+use logforth::Logger;
+
+const SINKS: [usize; 3] = [1, 4, 16];
+
+fn sink() -> Writer {
+    Writer::new(Arc::new(Mutex::new(io::sink())))
+}
+
+fn log(logger: &Logger) {
+    logger.log(
+        &Record::builder()
+            .level(log::Level::Info)
+            .target("bench")
+            .args(format_args!("request {} took {}ms", "GET /users/42", 17))
+            .build(),
+    );
+}
+
+fn bench_tee(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sinks");
+    for n in SINKS {
+        let mut dispatch = Dispatch::new().layout(TextLayout::default()).append(sink());
+        for _ in 1..n {
+            dispatch = dispatch.append(sink());
+        }
+        let logger = Logger::new().dispatch(dispatch);
+        group.bench_with_input(BenchmarkId::new("per_sink", n), &logger, |b, logger| {
+            b.iter(|| log(logger))
+        });
+
+        let tee = (0..n).fold(Tee::new(), |tee, _| tee.append(sink()));
+        let logger =
+            Logger::new().dispatch(Dispatch::new().layout(TextLayout::default()).append(tee));
+        group.bench_with_input(BenchmarkId::new("tee", n), &logger, |b, logger| {
+            b.iter(|| log(logger))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_tee);
+criterion_main!(benches);
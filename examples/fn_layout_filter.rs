@@ -34,7 +34,7 @@ fn main() {
                 .layout(CustomLayout::new(|record, f| {
                     f(format_args!("[system alert] {}", record.args()))
                 }))
-                .append(append::Stdout),
+                .append(append::Stdout::default()),
         )
         .apply()
         .unwrap();
@@ -24,7 +24,7 @@ fn main() {
             Dispatch::new()
                 .filter(LevelFilter::Trace)
                 .layout(JsonLayout::default())
-                .append(append::Stdout),
+                .append(append::Stdout::default()),
         )
         .apply()
         .unwrap();
@@ -41,7 +41,11 @@ fn main() {
                 .layout(JsonLayout::default())
                 .append(RollingFile::new(writer)),
         )
-        .dispatch(Dispatch::new().layout(TextLayout::default()).append(Stdout))
+        .dispatch(
+            Dispatch::new()
+                .layout(TextLayout::default())
+                .append(Stdout::default()),
+        )
         .apply()
         .unwrap();
 
@@ -24,7 +24,7 @@ fn main() {
             Dispatch::new()
                 .filter(LevelFilter::Trace)
                 .layout(TextLayout::default())
-                .append(append::Stdout),
+                .append(append::Stdout::default()),
         )
         .apply()
         .unwrap();
@@ -0,0 +1,128 @@
+// Copyright 2024 CratesLand Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use log::Record;
+
+/// The character encoding of the bytes that an appender writes, e.g., for a legacy sink that
+/// can't display UTF-8.
+///
+/// The legacy encodings are transcoded by [`encoding_rs`] with the `encoding` feature flag on.
+#[non_exhaustive]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// UTF-8, which is what layouts render, so the output is written as is.
+    #[default]
+    Utf8,
+    /// ISO-8859-1, which the [Encoding Standard](https://encoding.spec.whatwg.org/) defines as
+    /// windows-1252, where the characters it can't represent are replaced with `?`.
+    #[cfg(feature = "encoding")]
+    Latin1,
+    /// Any encoding of the [Encoding Standard](https://encoding.spec.whatwg.org/), e.g.,
+    /// `encoding_rs::SHIFT_JIS`, where the characters it can't represent are replaced with `?`.
+    #[cfg(feature = "encoding")]
+    Legacy(&'static encoding_rs::Encoding),
+}
+
+/// The bytes written after each record by default.
+pub(crate) const DEFAULT_RECORD_DELIMITER: &[u8] = b"\n";
+
+impl Encoding {
+    /// The encoding of a label of the Encoding Standard, e.g., `shift_jis` or `latin1`, if it's
+    /// known.
+    #[cfg(feature = "encoding")]
+    pub fn for_label(label: &str) -> Option<Encoding> {
+        encoding_rs::Encoding::for_label(label.as_bytes()).map(Encoding::Legacy)
+    }
+
+    /// Encodes the rendered record followed by the delimiter, which is written as is.
+    pub(crate) fn encode_record(self, record: &Record, delimiter: &[u8]) -> Vec<u8> {
+        let mut bytes = self.encode(record.args().to_string());
+        bytes.extend_from_slice(delimiter);
+        bytes
+    }
+
+    pub(crate) fn encode(self, text: String) -> Vec<u8> {
+        match self {
+            Encoding::Utf8 => text.into_bytes(),
+            #[cfg(feature = "encoding")]
+            Encoding::Latin1 => encode_legacy(encoding_rs::WINDOWS_1252, text),
+            #[cfg(feature = "encoding")]
+            Encoding::Legacy(encoding) => encode_legacy(encoding, text),
+        }
+    }
+}
+
+#[cfg(feature = "encoding")]
+fn encode_legacy(encoding: &'static encoding_rs::Encoding, text: String) -> Vec<u8> {
+    use encoding_rs::EncoderResult;
+
+    // UTF-16 is encoded as UTF-8 by the Encoding Standard
+    if encoding.output_encoding() == encoding_rs::UTF_8 {
+        return text.into_bytes();
+    }
+
+    let mut encoder = encoding.new_encoder();
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut text = text.as_str();
+    loop {
+        let max = encoder
+            .max_buffer_length_from_utf8_without_replacement(text.len())
+            .unwrap_or(text.len());
+        bytes.reserve(max);
+        let (result, read) =
+            encoder.encode_from_utf8_to_vec_without_replacement(text, &mut bytes, true);
+        text = &text[read..];
+        match result {
+            EncoderResult::InputEmpty => return bytes,
+            EncoderResult::OutputFull => {}
+            EncoderResult::Unmappable(_) => bytes.push(b'?'),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf8() {
+        let text = "café – 日志 ÿ".to_string();
+        assert_eq!(Encoding::Utf8.encode(text.clone()), text.as_bytes());
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn test_legacy() {
+        let text = "café – 日志 ÿ".to_string();
+        assert_eq!(
+            Encoding::Latin1.encode(text.clone()),
+            b"caf\xe9 \x96 ?? \xff"
+        );
+        assert_eq!(
+            Encoding::Legacy(encoding_rs::SHIFT_JIS).encode(text.clone()),
+            b"caf? ? \x93\xfa\x8e\x75 ?"
+        );
+        assert_eq!(
+            Encoding::for_label("latin1"),
+            Some(Encoding::Legacy(encoding_rs::WINDOWS_1252))
+        );
+        assert_eq!(
+            Encoding::for_label("utf-16le")
+                .unwrap()
+                .encode(text.clone()),
+            text.as_bytes()
+        );
+        assert_eq!(Encoding::for_label("klingon"), None);
+    }
+}
@@ -16,29 +16,57 @@
 
 use std::fmt;
 
+pub use self::encoding::Encoding;
 #[cfg(feature = "fastrace")]
 pub use self::fastrace::FastraceEvent;
+pub use self::null::Null;
 #[cfg(feature = "opentelemetry")]
 pub use self::opentelemetry::OpentelemetryLog;
+pub use self::retry::Retry;
 #[cfg(feature = "rolling_file")]
 pub use self::rolling_file::RollingFile;
+pub use self::stats::AppendStats;
+pub use self::stats::StatsHandle;
 pub use self::stdio::Stderr;
 pub use self::stdio::Stdout;
+pub use self::tee::Tee;
+#[cfg(feature = "tracing")]
+pub use self::tracing::TracingBridge;
+#[cfg(all(unix, feature = "unix_socket"))]
+pub use self::unix_socket::UnixSocket;
+pub use self::writer::Writer;
 use crate::layout::IdenticalLayout;
 use crate::layout::Layout;
 
+mod encoding;
 #[cfg(feature = "fastrace")]
 mod fastrace;
+mod null;
 #[cfg(feature = "opentelemetry")]
 pub mod opentelemetry;
+mod retry;
 #[cfg(feature = "rolling_file")]
 pub mod rolling_file;
+mod stats;
 mod stdio;
+mod tee;
+#[cfg(feature = "tracing")]
+mod tracing;
+#[cfg(all(unix, feature = "unix_socket"))]
+mod unix_socket;
+mod writer;
 
 pub trait Append: fmt::Debug + Send + Sync + 'static {
     /// Dispatches a log record to the append target.
     fn append(&self, record: &log::Record) -> anyhow::Result<()>;
 
+    /// Whether the append target accepts log records of the given metadata.
+    ///
+    /// Records are neither formatted nor dispatched to the append target if this returns `false`.
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
     /// Flushes any buffered records.
     fn flush(&self) {}
 
@@ -0,0 +1,80 @@
+// Copyright 2024 CratesLand Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use log::Metadata;
+use log::Record;
+
+use crate::append::Append;
+
+/// An appender that discards all log records.
+///
+/// By default, the appender reports itself as disabled so that records are neither formatted nor
+/// dispatched to it. Set `enabled` to `true` to keep filtering and formatting records before they
+/// are discarded, e.g., for benchmarking the logging pipeline without I/O.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct Null {
+    pub enabled: bool,
+}
+
+impl Append for Null {
+    fn append(&self, _record: &Record) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        self.enabled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+
+    use log::Log;
+
+    use super::*;
+    use crate::layout::CustomLayout;
+    use crate::Dispatch;
+    use crate::Logger;
+
+    fn count_formats(append: Null) -> usize {
+        let formats = Arc::new(AtomicUsize::new(0));
+        let counter = formats.clone();
+        let logger = Logger::new().dispatch(
+            Dispatch::new()
+                .layout(CustomLayout::new(move |record, f| {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                    f(*record.args())
+                }))
+                .append(append),
+        );
+
+        let record = Record::builder().args(format_args!("hello")).build();
+        assert_eq!(logger.enabled(record.metadata()), append.enabled);
+        logger.log(&record);
+        formats.load(Ordering::Relaxed)
+    }
+
+    #[test]
+    fn test_null_append() {
+        let record = Record::builder().args(format_args!("hello")).build();
+        assert!(!Null::default().enabled(record.metadata()));
+        assert!(Null::default().append(&record).is_ok());
+
+        assert_eq!(count_formats(Null::default()), 0);
+        assert_eq!(count_formats(Null { enabled: true }), 1);
+    }
+}
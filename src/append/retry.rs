@@ -0,0 +1,172 @@
+// Copyright 2024 CratesLand Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+use std::time::Instant;
+
+use log::Metadata;
+use log::Record;
+
+use crate::append::Append;
+use crate::layout::Layout;
+
+/// An appender that retries a failed write of its child appender, e.g., to ride out transient
+/// network failures.
+///
+/// A failed write is retried up to `max_retries` times. The first retry waits for `backoff`, and
+/// each following retry waits twice as long as the previous one. Once the total `deadline` since
+/// the first attempt elapses, or the retries are exhausted, the last error is returned.
+///
+/// Retries block the logging thread, so keep the backoff and the deadline short.
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// use logforth::append::Retry;
+/// use logforth::append::Stdout;
+///
+/// let append = Retry::new(Stdout::default())
+///     .max_retries(3)
+///     .backoff(Duration::from_millis(10))
+///     .deadline(Duration::from_millis(200));
+/// ```
+#[derive(Debug)]
+pub struct Retry<A> {
+    append: A,
+    max_retries: usize,
+    backoff: Duration,
+    deadline: Duration,
+}
+
+impl<A: Append> Retry<A> {
+    pub fn new(append: A) -> Self {
+        Self {
+            append,
+            max_retries: 3,
+            backoff: Duration::from_millis(10),
+            deadline: Duration::from_secs(1),
+        }
+    }
+
+    /// Sets the maximum number of retries after the first attempt.
+    pub fn max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets the wait before the first retry, which doubles for each following retry.
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Sets the total time since the first attempt after which no more retries are made.
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = deadline;
+        self
+    }
+}
+
+impl<A: Append> Append for Retry<A> {
+    fn append(&self, record: &Record) -> anyhow::Result<()> {
+        let start = Instant::now();
+        let mut backoff = self.backoff;
+        let mut retries = 0;
+        loop {
+            let err = match self.append.append(record) {
+                Ok(()) => return Ok(()),
+                Err(err) => err,
+            };
+            let remaining = self.deadline.saturating_sub(start.elapsed());
+            if retries >= self.max_retries || remaining.is_zero() {
+                return Err(err.context(format!("failed after {retries} retries")));
+            }
+            std::thread::sleep(backoff.min(remaining));
+            backoff = backoff.saturating_mul(2);
+            retries += 1;
+        }
+    }
+
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.append.enabled(metadata)
+    }
+
+    fn flush(&self) {
+        self.append.flush();
+    }
+
+    fn default_layout(&self) -> Layout {
+        self.append.default_layout()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// An appender that fails the first `failures` attempts.
+    #[derive(Debug, Default, Clone)]
+    struct Flaky {
+        failures: usize,
+        attempts: Arc<AtomicUsize>,
+        written: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Append for Flaky {
+        fn append(&self, record: &Record) -> anyhow::Result<()> {
+            if self.attempts.fetch_add(1, Ordering::SeqCst) < self.failures {
+                anyhow::bail!("connection reset");
+            }
+            self.written.lock().unwrap().push(record.args().to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_retry_until_written() {
+        let flaky = Flaky {
+            failures: 2,
+            ..Default::default()
+        };
+        let append = Retry::new(flaky.clone()).backoff(Duration::from_millis(1));
+
+        append
+            .append(&Record::builder().args(format_args!("hello")).build())
+            .unwrap();
+        assert_eq!(flaky.attempts.load(Ordering::SeqCst), 3);
+        assert_eq!(*flaky.written.lock().unwrap(), ["hello"]);
+    }
+
+    #[test]
+    fn test_retries_exhausted() {
+        let flaky = Flaky {
+            failures: usize::MAX,
+            ..Default::default()
+        };
+        let append = Retry::new(flaky.clone())
+            .max_retries(2)
+            .backoff(Duration::ZERO);
+
+        let err = append
+            .append(&Record::builder().args(format_args!("hello")).build())
+            .unwrap_err();
+        assert_eq!(err.root_cause().to_string(), "connection reset");
+        assert_eq!(flaky.attempts.load(Ordering::SeqCst), 3);
+    }
+}
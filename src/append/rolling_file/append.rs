@@ -12,28 +12,418 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use log::Level;
 use log::Record;
 
+use crate::append::encoding::DEFAULT_RECORD_DELIMITER;
 use crate::append::rolling_file::non_blocking::NonBlocking;
 use crate::append::Append;
+use crate::append::Encoding;
+use crate::append::StatsHandle;
+use crate::logger::dispatch_time;
 
 /// An appender that writes log records to a file that rolls over when it reaches a certain date
 /// time.
 #[derive(Debug)]
 pub struct RollingFile {
     writer: NonBlocking,
+    flush_on_level: Option<Level>,
+    encoding: Encoding,
+    record_delimiter: Vec<u8>,
+    sync_flush: bool,
 }
 
 impl RollingFile {
     pub fn new(writer: NonBlocking) -> Self {
-        Self { writer }
+        Self {
+            writer,
+            flush_on_level: None,
+            encoding: Encoding::default(),
+            record_delimiter: DEFAULT_RECORD_DELIMITER.to_vec(),
+            sync_flush: false,
+        }
+    }
+
+    /// Sets the encoding of the bytes written to the file, UTF-8 by default.
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Sets the bytes written after each record, a newline by default, e.g., a NUL byte for a
+    /// consumer that frames records on it.
+    ///
+    /// The delimiter is written as is, regardless of the encoding.
+    pub fn record_delimiter(mut self, delimiter: impl Into<Vec<u8>>) -> Self {
+        self.record_delimiter = delimiter.into();
+        self
+    }
+
+    /// Flushes the file right after writing a record at or above the given level.
+    ///
+    /// Records below the level stay buffered until the writer flushes them as usual. This keeps a
+    /// high throughput for verbose records while errors show up in the file immediately.
+    pub fn flush_on_level(mut self, level: Level) -> Self {
+        self.flush_on_level = Some(level);
+        self
+    }
+
+    /// Makes [`Append::flush`] wait until the records appended before are written and flushed,
+    /// for at most the shutdown timeout of the writer, rather than only asking the worker to
+    /// flush them.
+    ///
+    /// The flush in the panic hook of [`Logger::capture_panics`] always waits, so that the records
+    /// queued before the panic get a chance to be written before an abort.
+    ///
+    /// [`Logger::capture_panics`]: crate::Logger::capture_panics
+    pub fn sync_flush(mut self, sync_flush: bool) -> Self {
+        self.sync_flush = sync_flush;
+        self
+    }
+
+    /// Rolls over to a new log file right away, once the records appended before are written, see
+    /// [`RollingFileWriter::rotate_now`].
+    ///
+    /// This blocks until the rollover is done, for at most the shutdown timeout of the writer, and
+    /// fails right away if the writer isn't a [`RollingFileWriter`].
+    ///
+    /// [`RollingFileWriter`]: crate::append::rolling_file::RollingFileWriter
+    /// [`RollingFileWriter::rotate_now`]: crate::append::rolling_file::RollingFileWriter::rotate_now
+    pub fn rotate_now(&self) -> anyhow::Result<()> {
+        self.writer.rotate()
+    }
+
+    /// Returns a handle to how many records and bytes the worker has written to the file.
+    pub fn stats(&self) -> StatsHandle {
+        self.writer.stats()
     }
 }
 
 impl Append for RollingFile {
+    /// Sends the record to the worker, or, while the thread is panicking, e.g., for the panic
+    /// record of [`Logger::capture_panics`], writes and flushes it on the calling thread, so that
+    /// it survives when the panic is followed by an abort, even if the worker is backed up.
+    ///
+    /// [`Logger::capture_panics`]: crate::Logger::capture_panics
     fn append(&self, record: &Record) -> anyhow::Result<()> {
-        let bytes = format!("{}\n", record.args()).into_bytes();
-        self.writer.send(bytes)?;
-        Ok(())
+        let bytes = self.encoding.encode_record(record, &self.record_delimiter);
+        if std::thread::panicking() {
+            self.writer.write_sync(bytes, dispatch_time())?;
+        } else {
+            self.writer.send(bytes, dispatch_time())?;
+        }
+        if self
+            .flush_on_level
+            .is_some_and(|level| record.level() <= level)
+        {
+            self.writer.request_flush()?;
+        }
+        // the records are written off-thread, so a failure surfaces with a later record
+        match self.writer.take_error() {
+            Some(err) => Err(anyhow::Error::new(err).context("rolling file writer failed")),
+            None => Ok(()),
+        }
+    }
+
+    /// Asks the worker to flush the records appended before. This blocks until they're flushed
+    /// with [`RollingFile::sync_flush`] or while the thread is panicking, e.g., in the panic hook
+    /// of [`Logger::capture_panics`].
+    ///
+    /// [`Logger::capture_panics`]: crate::Logger::capture_panics
+    fn flush(&self) {
+        let result = if self.sync_flush || std::thread::panicking() {
+            self.writer.flush()
+        } else {
+            self.writer.request_flush()
+        };
+        if let Err(err) = result {
+            eprintln!("failed to flush rolling file: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use log::Log;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::append::rolling_file::worker::tests::BufferedWriter;
+    use crate::append::rolling_file::NonBlockingBuilder;
+    use crate::append::rolling_file::RollingFileWriter;
+    use crate::append::rolling_file::Rotation;
+    use crate::append::AppendStats;
+    use crate::logger::log_panic;
+    use crate::Dispatch;
+    use crate::Logger;
+
+    #[test]
+    fn test_stats() {
+        let dir = TempDir::new().unwrap();
+        let rolling = RollingFileWriter::builder().build(&dir).unwrap();
+        let (writer, _guard) = NonBlockingBuilder::default().finish(rolling);
+        let append = RollingFile::new(writer).sync_flush(true);
+        let stats = append.stats();
+        let logger = Logger::new().dispatch(Dispatch::new().append(append));
+
+        for message in ["hello", "world!", ""] {
+            logger.log(&Record::builder().args(format_args!("{message}")).build());
+        }
+        logger.flush();
+        assert_eq!(
+            stats.get(),
+            AppendStats {
+                records: 3,
+                bytes: 14,
+            }
+        );
+    }
+
+    #[test]
+    fn test_record_delimiter() {
+        let dir = TempDir::new().unwrap();
+        let rolling = RollingFileWriter::builder()
+            .filename_prefix("app")
+            .build(&dir)
+            .unwrap();
+        let (writer, guard) = NonBlockingBuilder::default().finish(rolling);
+        let append = RollingFile::new(writer).record_delimiter(*b"\0");
+
+        for message in ["hello", "multi\nline"] {
+            append
+                .append(&Record::builder().args(format_args!("{message}")).build())
+                .unwrap();
+        }
+        // the worker writes the pending records before it stops
+        drop(guard);
+        assert_eq!(
+            fs::read(dir.path().join("app.0")).unwrap(),
+            b"hello\0multi\nline\0"
+        );
+    }
+
+    #[test]
+    fn test_best_effort_errors_reach_error_handler() {
+        use std::sync::Arc;
+        use std::sync::Mutex;
+
+        use log::Log;
+
+        let dir = TempDir::new().unwrap();
+        // a file in place of the log directory can't be written
+        let blocker = dir.path().join("blocker");
+        fs::write(&blocker, "").unwrap();
+        let rolling = RollingFileWriter::builder()
+            .best_effort(true)
+            .build(blocker.join("logs"))
+            .unwrap();
+        let (writer, _guard) = NonBlockingBuilder::default().finish(rolling);
+
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let logger = Logger::new()
+            .dispatch(Dispatch::new().append(RollingFile::new(writer)))
+            .error_handler({
+                let errors = errors.clone();
+                move |err| errors.lock().unwrap().push(format!("{err:#}"))
+            });
+
+        let start = Instant::now();
+        while errors.lock().unwrap().is_empty() {
+            assert!(
+                start.elapsed() < Duration::from_secs(1),
+                "the error isn't reported"
+            );
+            logger.log(&Record::builder().args(format_args!("hello")).build());
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        let errors = errors.lock().unwrap();
+        assert!(
+            errors[0].contains("failed to create log directory"),
+            "{errors:?}"
+        );
+    }
+
+    #[test]
+    fn test_flush_on_level() {
+        let writer = BufferedWriter::default();
+        let (non_blocking, _guard) = NonBlockingBuilder::default()
+            .flush_interval(Duration::from_secs(3600))
+            .finish(writer.clone());
+        let append = RollingFile::new(non_blocking).flush_on_level(Level::Error);
+
+        append
+            .append(
+                &Record::builder()
+                    .level(Level::Debug)
+                    .args(format_args!("debug"))
+                    .build(),
+            )
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(writer.flushed().is_empty());
+
+        let start = Instant::now();
+        append
+            .append(
+                &Record::builder()
+                    .level(Level::Error)
+                    .args(format_args!("error"))
+                    .build(),
+            )
+            .unwrap();
+        while writer.flushed().is_empty() {
+            assert!(
+                start.elapsed() < Duration::from_secs(1),
+                "error record is not flushed"
+            );
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(writer.flushed(), b"debug\nerror\n");
+    }
+
+    #[test]
+    fn test_rotate_now() {
+        let dir = TempDir::new().unwrap();
+        let rolling = RollingFileWriter::builder()
+            .rotation(Rotation::Never)
+            .filename_prefix("job")
+            .build(&dir)
+            .unwrap();
+        let (writer, _guard) = NonBlockingBuilder::default().finish(rolling);
+        let append = RollingFile::new(writer);
+
+        append
+            .append(&Record::builder().args(format_args!("done")).build())
+            .unwrap();
+        append.rotate_now().unwrap();
+
+        let read = |name| fs::read_to_string(dir.path().join(name)).unwrap();
+        assert_eq!(read("job.0"), "done\n");
+        assert_eq!(read("job.1"), "");
+
+        // other writers can't roll over
+        let (writer, _guard) = NonBlockingBuilder::default().finish(BufferedWriter::default());
+        let err = RollingFile::new(writer).rotate_now().unwrap_err();
+        assert!(
+            err.to_string().contains("doesn't support rotation"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_panic_record_flushed_synchronously() {
+        /// Logs a panic while unwinding, like the panic hook does.
+        struct LogPanic<'a>(&'a Logger);
+
+        impl Drop for LogPanic<'_> {
+            fn drop(&mut self) {
+                log_panic(self.0, &"boom", None);
+            }
+        }
+
+        /// Takes 10ms per write, so that the worker falls behind.
+        #[derive(Clone, Default)]
+        struct SlowWriter(BufferedWriter);
+
+        impl std::io::Write for SlowWriter {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                std::thread::sleep(Duration::from_millis(10));
+                self.0.write(buf)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                self.0.flush()
+            }
+        }
+
+        let writer = SlowWriter::default();
+        let (non_blocking, _guard) = NonBlockingBuilder::default()
+            .flush_interval(Duration::from_secs(3600))
+            .finish(writer.clone());
+        let logger = Logger::new().dispatch(Dispatch::new().append(RollingFile::new(non_blocking)));
+
+        // the worker needs seconds to write the backlog, longer than the shutdown timeout
+        for _ in 0..500 {
+            logger.log(&Record::builder().args(format_args!("queued")).build());
+        }
+
+        // with `panic = "abort"`, the process aborts once the panic hook returns, so the record
+        // must be flushed by then
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _log = LogPanic(&logger);
+            panic!("boom");
+        }));
+        assert!(result.is_err());
+        let flushed = String::from_utf8(writer.0.flushed()).unwrap();
+        assert!(flushed.contains("panicked: boom"), "{flushed:?}");
+    }
+
+    #[test]
+    fn test_sync_flush() {
+        let writer = BufferedWriter::default();
+        let (non_blocking, _guard) = NonBlockingBuilder::default()
+            .flush_interval(Duration::from_secs(3600))
+            .finish(writer.clone());
+        let append = RollingFile::new(non_blocking).sync_flush(true);
+
+        append
+            .append(&Record::builder().args(format_args!("hello")).build())
+            .unwrap();
+        append.flush();
+        assert_eq!(writer.flushed(), b"hello\n");
+    }
+
+    #[test]
+    fn test_flush_on_worker_thread() {
+        use std::io;
+        use std::sync::Arc;
+        use std::sync::OnceLock;
+
+        /// Flushes the appender from the worker thread when written to.
+        #[derive(Clone, Default)]
+        struct Reentrant {
+            append: Arc<OnceLock<RollingFile>>,
+            flushed_in: Arc<OnceLock<Duration>>,
+        }
+
+        impl io::Write for Reentrant {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if let Some(append) = self.append.get() {
+                    let start = Instant::now();
+                    append.flush();
+                    let _ = self.flushed_in.set(start.elapsed());
+                }
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let writer = Reentrant::default();
+        let (non_blocking, _guard) = NonBlockingBuilder::default()
+            .shutdown_timeout(Duration::from_secs(10))
+            .finish(writer.clone());
+        let append = RollingFile::new(non_blocking.clone()).sync_flush(true);
+        writer
+            .append
+            .set(RollingFile::new(non_blocking).sync_flush(true))
+            .unwrap();
+
+        append
+            .append(&Record::builder().args(format_args!("hello")).build())
+            .unwrap();
+        let start = Instant::now();
+        while writer.flushed_in.get().is_none() {
+            assert!(start.elapsed() < Duration::from_secs(5), "not written");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        // the worker doesn't wait for itself until the timeout
+        assert!(*writer.flushed_in.get().unwrap() < Duration::from_secs(1));
     }
 }
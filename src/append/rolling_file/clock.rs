@@ -12,9 +12,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+#[cfg(test)]
+use std::sync::Arc;
+#[cfg(test)]
+use std::sync::Mutex;
+
 use jiff::Zoned;
 
-#[derive(Debug)]
+/// The clock of a rolling file writer, shared with the worker writing to it, so that its flush
+/// schedule follows the same time as the rotation.
+#[derive(Debug, Clone)]
 pub enum Clock {
     DefaultClock,
     #[cfg(test)]
@@ -31,32 +38,34 @@ impl Clock {
     }
 
     #[cfg(test)]
-    pub fn set_now(&mut self, now: Zoned) {
+    pub fn set_now(&self, now: Zoned) {
         if let Clock::ManualClock(clock) = self {
             clock.set_now(now);
         }
     }
 }
 
-/// The time could be reset.
-#[derive(Debug)]
+/// The time could be reset, also through the clones of the clock.
+#[derive(Debug, Clone)]
 #[cfg(test)]
 pub struct ManualClock {
-    now: Zoned,
+    now: Arc<Mutex<Zoned>>,
 }
 
 #[cfg(test)]
 impl ManualClock {
     pub fn new(now: Zoned) -> ManualClock {
-        ManualClock { now }
+        ManualClock {
+            now: Arc::new(Mutex::new(now)),
+        }
     }
 
     fn now(&self) -> Zoned {
-        self.now.clone()
+        self.now.lock().unwrap().clone()
     }
 
-    pub fn set_now(&mut self, now: Zoned) {
-        self.now = now;
+    pub fn set_now(&self, now: Zoned) {
+        *self.now.lock().unwrap() = now;
     }
 }
 
@@ -69,11 +78,11 @@ mod tests {
     #[test]
     fn test_manual_clock_adjusting() {
         let now = Zoned::from_str("2024-08-10T17:12:52+08[+08]").unwrap();
-        let mut clock = ManualClock { now: now.clone() };
+        let clock = ManualClock::new(now.clone());
         assert_eq!(clock.now(), now);
 
         let now = Zoned::from_str("2024-01-01T12:00:00+08[+08]").unwrap();
-        clock.set_now(now.clone());
+        clock.clone().set_now(now.clone());
         assert_eq!(clock.now(), now);
     }
 }
@@ -16,6 +16,7 @@ pub use append::RollingFile;
 pub use non_blocking::NonBlocking;
 pub use non_blocking::NonBlockingBuilder;
 pub use non_blocking::WorkerGuard;
+pub use rolling::ExistingFilePolicy;
 pub use rolling::RollingFileWriter;
 pub use rolling::RollingFileWriterBuilder;
 pub use rotation::Rotation;
@@ -27,8 +28,16 @@ mod rolling;
 mod rotation;
 mod worker;
 
+/// The last error of the worker writing the records, reported by the appender on its next record,
+/// since the worker can't reach the error handler of the logger.
+type ErrorSlot = std::sync::Arc<std::sync::Mutex<Option<std::io::Error>>>;
+
 #[derive(Debug)]
 enum Message {
-    Record(Vec<u8>),
+    // the time the record is dispatched at, if known, which a rolling file writer rolls over by
+    Record(Vec<u8>, Option<jiff::Zoned>),
+    // the sender, if any, is notified once the writer is flushed
+    Flush(Option<crossbeam_channel::Sender<()>>),
+    Rotate(crossbeam_channel::Sender<anyhow::Result<()>>),
     Shutdown,
 }
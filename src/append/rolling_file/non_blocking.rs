@@ -13,7 +13,10 @@
 // limitations under the License.
 
 use std::io::Write;
+use std::sync::Arc;
+use std::sync::PoisonError;
 use std::thread::JoinHandle;
+use std::thread::ThreadId;
 use std::time::Duration;
 
 use anyhow::Context;
@@ -21,9 +24,14 @@ use crossbeam_channel::bounded;
 use crossbeam_channel::unbounded;
 use crossbeam_channel::SendTimeoutError;
 use crossbeam_channel::Sender;
+use jiff::Zoned;
 
+use crate::append::rolling_file::clock::Clock;
+use crate::append::rolling_file::worker::SyncWrite;
 use crate::append::rolling_file::worker::Worker;
+use crate::append::rolling_file::ErrorSlot;
 use crate::append::rolling_file::Message;
+use crate::append::StatsHandle;
 
 /// A guard that flushes log records associated to a [`NonBlocking`] on a drop.
 ///
@@ -89,39 +97,149 @@ impl Drop for WorkerGuard {
 #[derive(Clone, Debug)]
 pub struct NonBlocking {
     sender: Sender<Message>,
+    flush_timeout: Duration,
+    errors: ErrorSlot,
+    worker: ThreadId,
+    rotatable: bool,
+    stats: StatsHandle,
+    sync_writer: Arc<dyn SyncWrite>,
 }
 
 impl NonBlocking {
     fn create<T: Write + Send + 'static>(
         writer: T,
-        thread_name: String,
-        buffered_lines_limit: Option<usize>,
-        shutdown_timeout: Option<Duration>,
+        builder: NonBlockingBuilder,
     ) -> (NonBlocking, WorkerGuard) {
+        let NonBlockingBuilder {
+            thread_name,
+            buffered_lines_limit,
+            shutdown_timeout,
+            flush_interval,
+            flush_on_buffer_bytes,
+            flush_on_record_count,
+            flush_aligned,
+            clock,
+        } = builder;
         let (sender, receiver) = match buffered_lines_limit {
             Some(cap) => bounded(cap),
             None => unbounded(),
         };
 
         let (shutdown_sender, shutdown_receiver) = bounded(0);
+        let errors = ErrorSlot::default();
+        let stats = StatsHandle::default();
 
-        let worker = Worker::new(writer, receiver, shutdown_receiver);
-        let worker_guard = WorkerGuard::new(
-            worker.make_thread(thread_name),
-            sender.clone(),
-            shutdown_sender,
-            shutdown_timeout,
-        );
+        let mut worker = Worker::new(writer, receiver, shutdown_receiver, flush_interval);
+        if let Some(clock) = clock {
+            worker = worker.clock(clock);
+        }
+        let worker = worker
+            .error_slot(errors.clone())
+            .stats(stats.clone())
+            .flush_on_buffer_bytes(flush_on_buffer_bytes)
+            .flush_on_record_count(flush_on_record_count)
+            .flush_aligned(flush_aligned);
+        let rotatable = worker.rotatable();
+        let sync_writer = worker.sync_writer();
+        let handle = worker.make_thread(thread_name);
+        let worker = handle.thread().id();
+        let worker_guard =
+            WorkerGuard::new(handle, sender.clone(), shutdown_sender, shutdown_timeout);
+        let flush_timeout = worker_guard.shutdown_timeout;
 
-        (Self { sender }, worker_guard)
+        (
+            Self {
+                sender,
+                flush_timeout,
+                errors,
+                worker,
+                rotatable,
+                stats,
+                sync_writer,
+            },
+            worker_guard,
+        )
     }
 
-    pub(super) fn send(&self, record: Vec<u8>) -> anyhow::Result<()> {
+    pub(super) fn send(&self, record: Vec<u8>, time: Option<Zoned>) -> anyhow::Result<()> {
         // TODO(tisonkun): consider drop the message if the channel is full
         self.sender
-            .send(Message::Record(record))
+            .send(Message::Record(record, time))
             .context("failed to send log message")
     }
+
+    /// Writes and flushes a record on the calling thread rather than sending it to the worker, so
+    /// that it's in the underlying output once this returns, e.g., for the panic record before an
+    /// abort.
+    ///
+    /// The record may land before the records sent earlier that the worker hasn't written yet. On
+    /// the worker thread itself, the record is sent to the worker as usual.
+    pub(super) fn write_sync(&self, record: Vec<u8>, time: Option<Zoned>) -> anyhow::Result<()> {
+        if std::thread::current().id() == self.worker {
+            return self.send(record, time);
+        }
+        self.sync_writer
+            .write_sync(&record, time.as_ref(), self.flush_timeout)
+            .context("failed to write log message")?;
+        self.stats.record(record.len());
+        Ok(())
+    }
+
+    /// The counters of the records the worker has written.
+    pub(super) fn stats(&self) -> StatsHandle {
+        self.stats.clone()
+    }
+
+    /// Takes the last error of the worker writing the records sent before, if any.
+    pub(super) fn take_error(&self) -> Option<std::io::Error> {
+        self.errors
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+    }
+
+    /// Asks the worker to flush the underlying writer once all records sent before are written.
+    pub(super) fn request_flush(&self) -> anyhow::Result<()> {
+        self.sender
+            .send(Message::Flush(None))
+            .context("failed to send flush request")
+    }
+
+    /// Waits until the worker has written and flushed all records sent before, for at most the
+    /// shutdown timeout, so that they survive if the process aborts right after.
+    ///
+    /// On the worker thread itself, e.g., when the underlying writer logs, the worker can't answer
+    /// while it's waiting, so this only asks for the flush.
+    pub(super) fn flush(&self) -> anyhow::Result<()> {
+        if std::thread::current().id() == self.worker {
+            return self.request_flush();
+        }
+        let (done, flushed) = bounded(1);
+        self.sender
+            .send_timeout(Message::Flush(Some(done)), self.flush_timeout)
+            .context("failed to send flush request")?;
+        flushed
+            .recv_timeout(self.flush_timeout)
+            .context("failed to wait for the flush")
+    }
+
+    /// Asks the worker to roll over to a new log file once all records sent before are written,
+    /// and waits until it's done, for at most the shutdown timeout.
+    pub(super) fn rotate(&self) -> anyhow::Result<()> {
+        if !self.rotatable {
+            anyhow::bail!("the writer doesn't support rotation");
+        }
+        if std::thread::current().id() == self.worker {
+            anyhow::bail!("can't wait for the rotation on the worker thread");
+        }
+        let (done, result) = bounded(1);
+        self.sender
+            .send_timeout(Message::Rotate(done), self.flush_timeout)
+            .context("failed to send rotation request")?;
+        result
+            .recv_timeout(self.flush_timeout)
+            .context("failed to receive rotation result")?
+    }
 }
 
 /// A builder for [`NonBlocking`].
@@ -130,6 +248,11 @@ pub struct NonBlockingBuilder {
     thread_name: String,
     buffered_lines_limit: Option<usize>,
     shutdown_timeout: Option<Duration>,
+    flush_interval: Option<Duration>,
+    flush_on_buffer_bytes: Option<usize>,
+    flush_on_record_count: Option<usize>,
+    flush_aligned: Option<Duration>,
+    clock: Option<Clock>,
 }
 
 impl NonBlockingBuilder {
@@ -145,6 +268,55 @@ impl NonBlockingBuilder {
         self
     }
 
+    /// Sets the interval to flush the underlying writer.
+    ///
+    /// By default, the writer is flushed after each batch of received records. With a flush
+    /// interval, the writer is flushed at most once per interval, and pending records are flushed
+    /// once the interval elapses even if no more records arrive.
+    pub fn flush_interval(mut self, flush_interval: Duration) -> NonBlockingBuilder {
+        self.flush_interval = Some(flush_interval);
+        self
+    }
+
+    /// Sets the number of written but unflushed bytes that triggers a flush right away, to bound
+    /// the memory buffered by the underlying writer for bursty loads.
+    ///
+    /// This is useful along with a flush interval, which otherwise lets the buffered bytes grow
+    /// until the interval elapses.
+    pub fn flush_on_buffer_bytes(mut self, bytes: usize) -> NonBlockingBuilder {
+        self.flush_on_buffer_bytes = Some(bytes);
+        self
+    }
+
+    /// Sets the number of written but unflushed records that triggers a flush right away, so that
+    /// the lines show up predictably even if they're small.
+    ///
+    /// This composes with the byte count, the flush interval, and the aligned flushes; the writer
+    /// is flushed whichever comes first.
+    pub fn flush_on_record_count(mut self, count: usize) -> NonBlockingBuilder {
+        self.flush_on_record_count = Some(count);
+        self
+    }
+
+    /// Flushes the pending records at each wall-clock multiple of the period in the local time
+    /// zone, e.g., at the top of each minute for a period of 60 seconds, so that the freshness of
+    /// the log aligns with a monitoring scrape.
+    ///
+    /// Like a flush interval, the writer is then no longer flushed after each batch of received
+    /// records. Both can be set, and the writer is flushed whichever comes first.
+    pub fn flush_aligned(mut self, period: Duration) -> NonBlockingBuilder {
+        self.flush_aligned = Some(period);
+        self
+    }
+
+    /// Sets the clock driving the flush schedule, the clock of the writer if it's a
+    /// [`RollingFileWriter`](crate::append::rolling_file::RollingFileWriter) by default.
+    #[cfg(test)]
+    pub(super) fn clock(mut self, clock: Clock) -> NonBlockingBuilder {
+        self.clock = Some(clock);
+        self
+    }
+
     /// Override the worker thread's name.
     ///
     /// The default worker thread name is "tracing-appender".
@@ -155,12 +327,7 @@ impl NonBlockingBuilder {
 
     /// Completes the builder, returning the configured `NonBlocking`.
     pub fn finish<T: Write + Send + 'static>(self, writer: T) -> (NonBlocking, WorkerGuard) {
-        NonBlocking::create(
-            writer,
-            self.thread_name,
-            self.buffered_lines_limit,
-            self.shutdown_timeout,
-        )
+        NonBlocking::create(writer, self)
     }
 }
 
@@ -170,6 +337,81 @@ impl Default for NonBlockingBuilder {
             thread_name: "logforth-rolling-file".to_string(),
             buffered_lines_limit: None,
             shutdown_timeout: None,
+            flush_interval: None,
+            flush_on_buffer_bytes: None,
+            flush_on_record_count: None,
+            flush_aligned: None,
+            clock: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::str::FromStr;
+
+    use crossbeam_channel::Receiver;
+
+    use super::*;
+    use crate::append::rolling_file::clock::ManualClock;
+    use crate::append::rolling_file::worker::tests::BufferedWriter;
+
+    /// A writer that reports each write and flush of the records it buffers.
+    struct NotifyingWriter {
+        writer: BufferedWriter,
+        written: Sender<()>,
+        flushed: Sender<()>,
+    }
+
+    impl Write for NotifyingWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let n = self.writer.write(buf)?;
+            let _ = self.written.send(());
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.writer.flush()?;
+            let _ = self.flushed.send(());
+            Ok(())
         }
     }
+
+    fn notifying_writer() -> (NotifyingWriter, Receiver<()>, Receiver<()>) {
+        let (written, on_written) = unbounded();
+        let (flushed, on_flushed) = unbounded();
+        let writer = NotifyingWriter {
+            writer: BufferedWriter::default(),
+            written,
+            flushed,
+        };
+        (writer, on_written, on_flushed)
+    }
+
+    #[test]
+    fn test_bounded_flush_latency() {
+        let start = Zoned::from_str("2024-08-10T00:00:00+08:00[+08:00]").unwrap();
+        let clock = Clock::ManualClock(ManualClock::new(start.clone()));
+        let (writer, written, flushed) = notifying_writer();
+        let buffered = writer.writer.clone();
+        let due = start.checked_add(jiff::Span::new().hours(1)).unwrap();
+        let (non_blocking, _guard) = NonBlockingBuilder::default()
+            .flush_interval(Duration::from_secs(3600))
+            .clock(clock.clone())
+            .finish(writer);
+
+        // the interval is nearly over by the clock, so that the idle worker sleeps only briefly
+        let almost = due.checked_sub(jiff::Span::new().milliseconds(10)).unwrap();
+        clock.set_now(almost);
+        non_blocking.send(b"hello\n".to_vec(), None).unwrap();
+        written.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert!(buffered.flushed().is_empty());
+
+        // no more records arrive, but the worker wakes up once the interval elapses by the clock,
+        // long before it would by the wall clock
+        clock.set_now(due);
+        flushed.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(buffered.flushed(), b"hello\n");
+    }
 }
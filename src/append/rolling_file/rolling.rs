@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::fmt;
 use std::fs;
 use std::fs::File;
 use std::fs::OpenOptions;
@@ -19,19 +20,24 @@ use std::io;
 use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::PoisonError;
+use std::time::Duration;
 
 use anyhow::Context;
+use jiff::Timestamp;
 use jiff::Zoned;
 use parking_lot::RwLock;
 
 use crate::append::rolling_file::clock::Clock;
+use crate::append::rolling_file::ErrorSlot;
 use crate::append::rolling_file::Rotation;
 
 /// A file writer with the ability to rotate log files at a fixed schedule.
 #[derive(Debug)]
 pub struct RollingFileWriter {
     state: State,
-    writer: RwLock<File>,
+    // not opened yet if the writer is built in the best-effort mode and failed to open it
+    writer: RwLock<Option<File>>,
 }
 
 impl RollingFileWriter {
@@ -39,19 +45,90 @@ impl RollingFileWriter {
     pub fn builder() -> RollingFileWriterBuilder {
         RollingFileWriterBuilder::new()
     }
-}
 
-impl Write for RollingFileWriter {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    /// Rolls over to a new log file right away, regardless of the rotation and the file size,
+    /// e.g., at the end of a job.
+    ///
+    /// The current file is flushed and kept as is, and the next file is opened with the next
+    /// index, the same way as a rollover on the file size.
+    pub fn rotate_now(&mut self) -> anyhow::Result<()> {
         let now = self.state.clock.now();
         let writer = self.writer.get_mut();
-        if self.state.should_rollover_on_date(&now) {
-            self.state.advance_date(&now);
-            self.state.refresh_writer(&now, 0, writer);
+        if let Some(writer) = writer {
+            writer.flush().context("failed to flush log file")?;
+            if self.state.durable {
+                writer.sync_all().context("failed to sync log file")?;
+            }
+            self.state.advance_cnt();
+            self.state.archive_active_file()?;
+        }
+        *writer = Some(self.state.create_log_writer(&now)?);
+        Ok(())
+    }
+}
+
+impl RollingFileWriter {
+    /// Reports the errors that don't fail a write, e.g., failing to delete the oldest logs, to the
+    /// slot rather than stderr, so that the appender passes them to the error handler.
+    /// The clock the writer rolls over by.
+    pub(super) fn clock(&self) -> Clock {
+        self.state.clock.clone()
+    }
+
+    pub(super) fn report_errors_to(&mut self, errors: ErrorSlot) {
+        self.state.errors = Some(errors);
+    }
+
+    /// Writes the whole buffer of a record dispatched at `now`, rolling over by `now` rather than
+    /// by the clock, so that the record lands in the file of the time it's stamped with even if
+    /// it's written after the rollover boundary.
+    pub(super) fn write_at(&mut self, mut buf: &[u8], now: &Zoned) -> io::Result<()> {
+        while !buf.is_empty() {
+            match self.write_with(buf, Some(now)) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => buf = &buf[n..],
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    fn write_with(&mut self, buf: &[u8], now: Option<&Zoned>) -> io::Result<usize> {
+        let writer = match self.writer.get_mut() {
+            Some(writer) => writer,
+            // retry opening the file failed to open in the best-effort mode
+            writer @ None => {
+                let now = self.state.clock.now();
+                let file = self
+                    .state
+                    .create_log_writer(&now)
+                    .map_err(|err| io::Error::new(io::ErrorKind::Other, format!("{err:#}")))?;
+                writer.insert(file)
+            }
+        };
+        let check_date = self.state.should_check_date();
+        if check_date || self.state.should_rollover_on_size() {
+            let now = match now {
+                Some(now) => now.clone(),
+                None => self.state.clock.now(),
+            };
+            if check_date && self.state.should_rollover_on_date(&now) && self.state.dwelled(&now) {
+                self.state.advance_date(&now);
+                self.state.refresh_writer(&now, writer);
+            }
+            if self.state.should_rollover_on_size() {
+                self.state.advance_cnt();
+                self.state.refresh_writer(&now, writer);
+            }
         }
-        if self.state.should_rollover_on_size() {
-            let cnt = self.state.advance_cnt();
-            self.state.refresh_writer(&now, cnt, writer);
+
+        if self.state.durable {
+            writer.write_all(buf)?;
+            writer.sync_data()?;
+            self.state.current_filesize += buf.len();
+            self.state.syncs += 1;
+            return Ok(buf.len());
         }
 
         writer.write(buf).map(|n| {
@@ -59,12 +136,34 @@ impl Write for RollingFileWriter {
             n
         })
     }
+}
+
+impl Write for RollingFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_with(buf, None)
+    }
 
     fn flush(&mut self) -> io::Result<()> {
-        self.writer.get_mut().flush()
+        match self.writer.get_mut() {
+            Some(writer) => writer.flush(),
+            None => Ok(()),
+        }
     }
 }
 
+/// Defines what to do when the log file to open already exists, e.g., when the process restarts
+/// within the same rotation period.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub enum ExistingFilePolicy {
+    /// Append to the existing file.
+    #[default]
+    Append,
+    /// Skip to the next unused file index, leaving the existing files untouched.
+    NextIndex,
+    /// Fail to open the file.
+    Error,
+}
+
 /// A builder for [`RollingFileWriter`].
 #[derive(Debug)]
 pub struct RollingFileWriterBuilder {
@@ -73,6 +172,16 @@ pub struct RollingFileWriterBuilder {
     suffix: Option<String>,
     max_size: usize,
     max_files: Option<usize>,
+    existing_file: ExistingFilePolicy,
+    rotation_check_interval: usize,
+    min_dwell: Option<Duration>,
+    file_header: Option<Vec<u8>>,
+    #[cfg(unix)]
+    symlink: Option<String>,
+    durable: bool,
+    remove_unused_files: bool,
+    active_filename: Option<String>,
+    best_effort: bool,
     clock: Clock,
 }
 
@@ -91,6 +200,16 @@ impl RollingFileWriterBuilder {
             suffix: None,
             max_size: usize::MAX,
             max_files: None,
+            existing_file: ExistingFilePolicy::Append,
+            rotation_check_interval: 1,
+            min_dwell: None,
+            file_header: None,
+            #[cfg(unix)]
+            symlink: None,
+            durable: false,
+            remove_unused_files: false,
+            active_filename: None,
+            best_effort: false,
             clock: Clock::DefaultClock,
         }
     }
@@ -136,25 +255,142 @@ impl RollingFileWriterBuilder {
         self
     }
 
+    /// Sets what to do when the log file to open already exists.
+    ///
+    /// Defaults to [`ExistingFilePolicy::Append`].
+    #[must_use]
+    pub fn existing_file(mut self, policy: ExistingFilePolicy) -> Self {
+        self.existing_file = policy;
+        self
+    }
+
+    /// Sets how many writes may pass between two checks of the time rotation boundary.
+    ///
+    /// Checking on every write requires reading the clock per record. For very hot loggers, a
+    /// larger interval trades a latency of at most `n - 1` writes after the boundary for
+    /// throughput. The file size is still checked on every write. Defaults to 1.
+    #[must_use]
+    pub fn rotation_check_interval(mut self, n: usize) -> Self {
+        self.rotation_check_interval = n.max(1);
+        self
+    }
+
+    /// Sets the minimum duration a log file stays active before it can be rolled by time, so that
+    /// a clock that barely advances across rotation boundaries doesn't thrash the log files.
+    ///
+    /// A time rotation within this duration is deferred until it has elapsed, and a warning is
+    /// printed once. The file size is still limited on every write. By default, a file is rolled
+    /// as soon as the time rotation is due.
+    #[must_use]
+    pub fn min_dwell(mut self, duration: Duration) -> Self {
+        self.min_dwell = Some(duration);
+        self
+    }
+
+    /// Sets the bytes written at the top of each new log file, e.g., a CSV header row or a UTF-8
+    /// BOM.
+    ///
+    /// The header is not written to an existing file that already has content.
+    #[must_use]
+    pub fn file_header(mut self, header: impl Into<Vec<u8>>) -> Self {
+        self.file_header = Some(header.into());
+        self
+    }
+
+    /// Maintains a symlink with the given name in the log directory, e.g., `app.log`, that always
+    /// points at the active log file.
+    ///
+    /// The symlink is replaced atomically whenever a new log file is opened.
+    #[cfg(unix)]
+    #[must_use]
+    pub fn symlink(mut self, name: impl Into<String>) -> Self {
+        self.symlink = Some(name.into());
+        self
+    }
+
+    /// Syncs each write to the disk before returning, so that a written record survives a crash.
+    ///
+    /// The previous log file is synced before rotating to a new one, and on Unix, the log
+    /// directory is synced once a new log file is created. This costs an `fsync` per record and
+    /// only suits low-throughput logs, e.g., a small audit log.
+    ///
+    /// Note that writing through a [`NonBlocking`] returns once the record is queued. To only
+    /// return once the record is on the disk, write through a [`Writer`] appender instead.
+    ///
+    /// [`NonBlocking`]: crate::append::rolling_file::NonBlocking
+    /// [`Writer`]: crate::append::Writer
+    #[must_use]
+    pub fn durable(mut self, durable: bool) -> Self {
+        self.durable = durable;
+        self
+    }
+
+    /// Removes a log file that nothing was written to once the writer rolls over to the next one,
+    /// e.g., when no records arrive for a whole rotation period.
+    ///
+    /// Files are rolled over on writes only, so an idle period doesn't produce files by itself,
+    /// but the file opened before it would be left empty. Only files created empty are removed;
+    /// a file with the header only counts as empty, and an existing file with content is kept.
+    #[must_use]
+    pub fn remove_unused_files(mut self, remove: bool) -> Self {
+        self.remove_unused_files = remove;
+        self
+    }
+
+    /// Writes to a log file of the given fixed name, e.g., `current.log`, and renames it to the
+    /// dated name once the writer rolls over, so that tailing tools can follow a stable name
+    /// without a symlink.
+    ///
+    /// The active file is appended to if it exists, regardless of the [`ExistingFilePolicy`].
+    /// An existing archive is never overwritten; the file is archived with the next unused index
+    /// instead. The archive is named after the time the active file was opened; for an active file
+    /// continued from an earlier run, that's the time it was last modified, so that it rolls over
+    /// on the first write if it's from an earlier period.
+    #[must_use]
+    pub fn active_filename(mut self, name: impl Into<String>) -> Self {
+        self.active_filename = Some(name.into());
+        self
+    }
+
+    /// Builds the writer even if the log file can't be opened, e.g., because the directory isn't
+    /// mounted yet, and retries opening it on each write instead.
+    ///
+    /// By default, [`RollingFileWriterBuilder::build`] fails fast with the error, e.g., a bad path
+    /// or permission denied, so that it's surfaced before the application starts logging. In the
+    /// best-effort mode, the records written before the file opens fail to write with the error,
+    /// which a [`RollingFile`] returns with its next record, so that it reaches the error handler
+    /// of the logger.
+    ///
+    /// [`RollingFile`]: crate::append::rolling_file::RollingFile
+    #[must_use]
+    pub fn best_effort(mut self, best_effort: bool) -> Self {
+        self.best_effort = best_effort;
+        self
+    }
+
+    /// The name of the log file that the configured writer opens for the given time and index,
+    /// e.g., `app.2024-08-10.0.log`, so that external tools can match the log files exactly.
+    ///
+    /// The index starts from 0 for each rotation period, and increases whenever the file rolls
+    /// over within the period, e.g., on the file size.
+    pub fn log_filename(&self, time: &Zoned, index: usize) -> String {
+        join_date(
+            &self.rotation,
+            self.prefix.as_deref(),
+            self.suffix.as_deref(),
+            time,
+            index,
+        )
+    }
+
     #[cfg(test)]
-    fn clock(mut self, clock: Clock) -> Self {
+    pub(super) fn clock(mut self, clock: Clock) -> Self {
         self.clock = clock;
         self
     }
 
     pub fn build(self, dir: impl AsRef<Path>) -> anyhow::Result<RollingFileWriter> {
-        let Self {
-            rotation,
-            prefix,
-            suffix,
-            max_size,
-            max_files,
-            clock,
-        } = self;
-        let directory = dir.as_ref().to_path_buf();
-        let (state, writer) = State::new(
-            rotation, directory, prefix, suffix, max_size, max_files, clock,
-        )?;
+        let (state, writer) = State::new(self, dir)?;
         Ok(RollingFileWriter { state, writer })
     }
 }
@@ -171,19 +407,52 @@ struct State {
     next_date_timestamp: Option<usize>,
     max_size: usize,
     max_files: Option<usize>,
+    existing_file: ExistingFilePolicy,
+    rotation_check_interval: usize,
+    writes_since_check: usize,
+    min_dwell: Option<Duration>,
+    file_header: Option<Vec<u8>>,
+    opened_at: Option<Zoned>,
+    dwell_warned: bool,
+    #[cfg(unix)]
+    symlink: Option<String>,
+    durable: bool,
+    syncs: usize,
+    remove_unused_files: bool,
+    // the path of the current file, and its size after creation if it was created empty
+    current_path: PathBuf,
+    empty_file_size: Option<usize>,
+    // the fixed name of the active file, and the index of the archive it's renamed to
+    active_filename: Option<String>,
+    opened_count: usize,
     clock: Clock,
+    // where to report the errors that don't fail a write, stderr if unset
+    errors: Option<ErrorSlot>,
 }
 
 impl State {
     fn new(
-        rotation: Rotation,
+        builder: RollingFileWriterBuilder,
         dir: impl AsRef<Path>,
-        log_filename_prefix: Option<String>,
-        log_filename_suffix: Option<String>,
-        max_size: usize,
-        max_files: Option<usize>,
-        clock: Clock,
-    ) -> anyhow::Result<(Self, RwLock<File>)> {
+    ) -> anyhow::Result<(Self, RwLock<Option<File>>)> {
+        let RollingFileWriterBuilder {
+            rotation,
+            prefix: log_filename_prefix,
+            suffix: log_filename_suffix,
+            max_size,
+            max_files,
+            existing_file,
+            rotation_check_interval,
+            min_dwell,
+            file_header,
+            #[cfg(unix)]
+            symlink,
+            durable,
+            remove_unused_files,
+            active_filename,
+            best_effort,
+            clock,
+        } = builder;
         let log_dir = dir.as_ref().to_path_buf();
         let date_format = rotation.date_format();
         let now = clock.now();
@@ -192,7 +461,7 @@ impl State {
         let current_count = 0;
         let current_filesize = 0;
 
-        let state = State {
+        let mut state = State {
             log_dir,
             log_filename_prefix,
             log_filename_suffix,
@@ -203,46 +472,163 @@ impl State {
             rotation,
             max_size,
             max_files,
+            existing_file,
+            rotation_check_interval,
+            writes_since_check: 0,
+            min_dwell,
+            file_header,
+            opened_at: None,
+            dwell_warned: false,
+            #[cfg(unix)]
+            symlink,
+            durable,
+            syncs: 0,
+            remove_unused_files,
+            current_path: PathBuf::new(),
+            empty_file_size: None,
+            active_filename,
+            opened_count: 0,
             clock,
+            errors: None,
         };
 
-        let file = state.create_log_writer(&now, 0)?;
+        let file = match state.create_log_writer(&now) {
+            Ok(file) => {
+                // an active file continued from an earlier period rolls over on the next write
+                if let Some(opened_at) = &state.opened_at {
+                    state.next_date_timestamp = state.rotation.next_date_timestamp(opened_at);
+                }
+                Some(file)
+            }
+            // the next write retries, and reports the error if it fails again
+            Err(_) if best_effort => None,
+            Err(err) => return Err(err),
+        };
         let writer = RwLock::new(file);
         Ok((state, writer))
     }
 
     fn join_date(&self, date: &Zoned, cnt: usize) -> String {
-        let date = date.strftime(self.date_format);
-        match (
+        join_date(
             &self.rotation,
-            &self.log_filename_prefix,
-            &self.log_filename_suffix,
-        ) {
-            (&Rotation::Never, Some(filename), None) => format!("{filename}.{cnt}"),
-            (&Rotation::Never, Some(filename), Some(suffix)) => {
-                format!("{filename}.{cnt}.{suffix}")
-            }
-            (&Rotation::Never, None, Some(suffix)) => format!("{cnt}.{suffix}"),
-            (_, Some(filename), Some(suffix)) => format!("{filename}.{date}.{cnt}.{suffix}"),
-            (_, Some(filename), None) => format!("{filename}.{date}.{cnt}"),
-            (_, None, Some(suffix)) => format!("{date}.{cnt}.{suffix}"),
-            (_, None, None) => format!("{date}.{cnt}"),
-        }
+            self.log_filename_prefix.as_deref(),
+            self.log_filename_suffix.as_deref(),
+            date,
+            cnt,
+        )
     }
 
-    fn create_log_writer(&self, now: &Zoned, cnt: usize) -> anyhow::Result<File> {
+    /// Creates the log file of the current count, which may be advanced according to the
+    /// [`ExistingFilePolicy`].
+    fn create_log_writer(&mut self, now: &Zoned) -> anyhow::Result<File> {
         fs::create_dir_all(&self.log_dir).context("failed to create log directory")?;
-        let filename = self.join_date(now, cnt);
         if let Some(max_files) = self.max_files {
             if let Err(err) = self.delete_oldest_logs(max_files) {
-                eprintln!("failed to delete oldest logs: {err}");
+                self.report(format_args!("failed to delete oldest logs: {err}"));
+            }
+        }
+
+        loop {
+            let filename = match &self.active_filename {
+                Some(filename) => filename.clone(),
+                None => self.join_date(now, self.current_count),
+            };
+            let path = self.log_dir.join(&filename);
+            let mut options = OpenOptions::new();
+            match self.existing_file {
+                // the active file of a fixed name is reused regardless of the policy
+                _ if self.active_filename.is_some() => options.append(true).create(true),
+                ExistingFilePolicy::Append => options.append(true).create(true),
+                ExistingFilePolicy::NextIndex | ExistingFilePolicy::Error => {
+                    options.append(true).create_new(true)
+                }
+            };
+
+            match options.open(&path) {
+                Ok(mut file) => {
+                    // account for the existing content, so that size rotation stays correct
+                    // across restarts
+                    self.current_filesize = file.metadata().map_or(0, |m| m.len() as usize);
+                    let created_empty = self.current_filesize == 0;
+                    if let Some(header) = self.file_header.as_deref() {
+                        if self.current_filesize == 0 {
+                            file.write_all(header).with_context(|| {
+                                format!("failed to write log file header: {}", path.display())
+                            })?;
+                            self.current_filesize = header.len();
+                        }
+                    }
+                    // the active file continued across a restart belongs to the period it was last
+                    // written in by the earlier process
+                    self.opened_at = match &self.active_filename {
+                        Some(_) if !created_empty => Some(modified_at(&file, now)),
+                        _ => Some(now.clone()),
+                    };
+                    self.opened_count = self.current_count;
+                    self.empty_file_size = created_empty.then_some(self.current_filesize);
+                    self.current_path = path;
+                    // persist the directory entry of the new file, so that it survives a crash
+                    #[cfg(unix)]
+                    if self.durable {
+                        if let Err(err) = File::open(&self.log_dir).and_then(|dir| dir.sync_all()) {
+                            self.report(format_args!("failed to sync log directory: {err}"));
+                        }
+                    }
+                    #[cfg(unix)]
+                    if let Err(err) = self.update_symlink(&filename) {
+                        self.report(format_args!(
+                            "failed to update symlink to {filename}: {err}"
+                        ));
+                    }
+                    return Ok(file);
+                }
+                Err(err)
+                    if err.kind() == io::ErrorKind::AlreadyExists
+                        && self.existing_file == ExistingFilePolicy::NextIndex =>
+                {
+                    self.current_count += 1;
+                }
+                Err(err) => {
+                    return Err(err)
+                        .with_context(|| format!("failed to create log file: {}", path.display()))
+                }
+            }
+        }
+    }
+
+    /// Renames the active file of a fixed name to the dated name of the period it was opened in,
+    /// with the first unused index.
+    fn archive_active_file(&mut self) -> anyhow::Result<()> {
+        if self.active_filename.is_none() {
+            return Ok(());
+        }
+        let opened_at = self.opened_at.clone().unwrap_or_else(|| self.clock.now());
+        let mut cnt = self.opened_count;
+        let archive = loop {
+            let archive = self.log_dir.join(self.join_date(&opened_at, cnt));
+            if !archive.exists() {
+                break archive;
             }
+            cnt += 1;
+        };
+        fs::rename(&self.current_path, &archive)
+            .with_context(|| format!("failed to archive log file to {}", archive.display()))
+    }
+
+    /// Points the symlink at `filename` by renaming a fresh symlink over it, so that readers never
+    /// observe a missing symlink.
+    #[cfg(unix)]
+    fn update_symlink(&self, filename: &str) -> io::Result<()> {
+        let Some(symlink) = &self.symlink else {
+            return Ok(());
+        };
+        let tmp = self.log_dir.join(format!(".{symlink}.tmp"));
+        match fs::remove_file(&tmp) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => {}
         }
-        OpenOptions::new()
-            .append(true)
-            .create(true)
-            .open(self.log_dir.join(filename))
-            .context("failed to create log file")
+        std::os::unix::fs::symlink(filename, &tmp)?;
+        fs::rename(&tmp, self.log_dir.join(symlink))
     }
 
     fn delete_oldest_logs(&self, max_files: usize) -> anyhow::Result<()> {
@@ -263,6 +649,9 @@ impl State {
                 let filename = entry.file_name();
                 // if the filename is not a UTF-8 string, skip it.
                 let filename = filename.to_str()?;
+                if self.active_filename.as_deref() == Some(filename) {
+                    return None;
+                }
                 if let Some(prefix) = &self.log_filename_prefix {
                     if !filename.starts_with(prefix) {
                         return None;
@@ -275,11 +664,15 @@ impl State {
                     }
                 }
 
-                if self.log_filename_prefix.is_none()
-                    && self.log_filename_suffix.is_none()
-                    && jiff::civil::DateTime::strptime(self.date_format, filename).is_err()
-                {
-                    return None;
+                if self.log_filename_prefix.is_none() && self.log_filename_suffix.is_none() {
+                    let matched = match self.rotation {
+                        // files are named by their index only
+                        Rotation::Never => filename.parse::<usize>().is_ok(),
+                        _ => jiff::civil::DateTime::strptime(self.date_format, filename).is_ok(),
+                    };
+                    if !matched {
+                        return None;
+                    }
                 }
 
                 let created = metadata.created().ok()?;
@@ -304,18 +697,82 @@ impl State {
         Ok(())
     }
 
-    fn refresh_writer(&self, now: &Zoned, cnt: usize, file: &mut File) {
-        match self.create_log_writer(now, cnt) {
+    fn refresh_writer(&mut self, now: &Zoned, file: &mut File) {
+        let mut unused_path = (self.remove_unused_files
+            && self.empty_file_size == Some(self.current_filesize))
+        .then(|| self.current_path.clone());
+        if self.active_filename.is_some() {
+            // an unused active file is kept as the next one rather than archived
+            if unused_path.take().is_none() {
+                if let Err(err) = self.archive_active_file() {
+                    self.report(format_args!("failed to archive log file: {err}"));
+                }
+            }
+        }
+        match self.create_log_writer(now) {
             Ok(new_file) => {
                 if let Err(err) = file.flush() {
-                    eprintln!("failed to flush previous writer: {err}");
+                    self.report(format_args!("failed to flush previous writer: {err}"));
+                }
+                if self.durable {
+                    if let Err(err) = file.sync_all() {
+                        self.report(format_args!("failed to sync previous writer: {err}"));
+                    }
                 }
                 *file = new_file;
+                if let Some(path) = unused_path {
+                    if let Err(err) = fs::remove_file(&path) {
+                        let path = path.display();
+                        self.report(format_args!(
+                            "failed to remove unused log file {path}: {err}"
+                        ));
+                    }
+                }
             }
-            Err(err) => eprintln!("failed to create writer for logs: {err}"),
+            Err(err) => self.report(format_args!("failed to create writer for logs: {err}")),
         }
     }
 
+    fn report(&self, err: fmt::Arguments) {
+        match &self.errors {
+            Some(errors) => {
+                let err = io::Error::new(io::ErrorKind::Other, err.to_string());
+                *errors.lock().unwrap_or_else(PoisonError::into_inner) = Some(err);
+            }
+            None => eprintln!("{err}"),
+        }
+    }
+
+    fn should_check_date(&mut self) -> bool {
+        if self.next_date_timestamp.is_none() {
+            return false;
+        }
+        self.writes_since_check += 1;
+        if self.writes_since_check < self.rotation_check_interval {
+            return false;
+        }
+        self.writes_since_check = 0;
+        true
+    }
+
+    /// Whether the active log file has stayed for the minimum dwell duration.
+    fn dwelled(&mut self, now: &Zoned) -> bool {
+        let (Some(opened_at), Some(min_dwell)) = (&self.opened_at, self.min_dwell) else {
+            return true;
+        };
+        let dwell = now.timestamp().as_millisecond() - opened_at.timestamp().as_millisecond();
+        if dwell >= min_dwell.as_millis() as i64 {
+            return true;
+        }
+        if !self.dwell_warned {
+            self.dwell_warned = true;
+            eprintln!(
+                "deferring log file rotation {dwell}ms after opening the file, minimum: {min_dwell:?}"
+            );
+        }
+        false
+    }
+
     fn should_rollover_on_date(&self, date: &Zoned) -> bool {
         self.next_date_timestamp
             .is_some_and(|ts| date.timestamp().as_millisecond() as usize >= ts)
@@ -325,10 +782,9 @@ impl State {
         self.current_filesize >= self.max_size
     }
 
-    fn advance_cnt(&mut self) -> usize {
+    fn advance_cnt(&mut self) {
         self.current_count += 1;
         self.current_filesize = 0;
-        self.current_count
     }
 
     fn advance_date(&mut self, now: &Zoned) {
@@ -338,6 +794,35 @@ impl State {
     }
 }
 
+/// The time the file was last modified at, in the time zone of `now`, or `now` if unknown.
+fn modified_at(file: &File, now: &Zoned) -> Zoned {
+    file.metadata()
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| Timestamp::try_from(modified).ok())
+        .map_or_else(|| now.clone(), |ts| ts.to_zoned(now.time_zone().clone()))
+}
+
+fn join_date(
+    rotation: &Rotation,
+    prefix: Option<&str>,
+    suffix: Option<&str>,
+    date: &Zoned,
+    cnt: usize,
+) -> String {
+    let date = date.strftime(rotation.date_format());
+    match (rotation, prefix, suffix) {
+        (Rotation::Never, Some(filename), None) => format!("{filename}.{cnt}"),
+        (Rotation::Never, Some(filename), Some(suffix)) => format!("{filename}.{cnt}.{suffix}"),
+        (Rotation::Never, None, Some(suffix)) => format!("{cnt}.{suffix}"),
+        (Rotation::Never, None, None) => format!("{cnt}"),
+        (_, Some(filename), Some(suffix)) => format!("{filename}.{date}.{cnt}.{suffix}"),
+        (_, Some(filename), None) => format!("{filename}.{date}.{cnt}"),
+        (_, None, Some(suffix)) => format!("{date}.{cnt}.{suffix}"),
+        (_, None, None) => format!("{date}.{cnt}"),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::cmp::min;
@@ -345,6 +830,7 @@ mod tests {
     use std::io::Write;
     use std::ops::Add;
     use std::str::FromStr;
+    use std::time::Duration;
 
     use jiff::Span;
     use jiff::Zoned;
@@ -354,6 +840,7 @@ mod tests {
 
     use crate::append::rolling_file::clock::Clock;
     use crate::append::rolling_file::clock::ManualClock;
+    use crate::append::rolling_file::ExistingFilePolicy;
     use crate::append::rolling_file::RollingFileWriterBuilder;
     use crate::append::rolling_file::Rotation;
 
@@ -539,6 +1026,494 @@ mod tests {
         assert!(time_rotation_trigger);
     }
 
+    #[test]
+    fn test_size_rotation_without_time_rotation() {
+        let temp_dir = TempDir::new().expect("failed to create a temporary directory");
+        let mut writer = RollingFileWriterBuilder::new()
+            .rotation(Rotation::Never)
+            .max_file_size(4)
+            .max_log_files(2)
+            .build(&temp_dir)
+            .unwrap();
+
+        for line in ["one\n", "two\n", "six\n"] {
+            writer.write_all(line.as_bytes()).unwrap();
+        }
+        writer.flush().unwrap();
+
+        let mut files = fs::read_dir(&temp_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(files, ["1", "2"]);
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("2")).unwrap(),
+            "six\n"
+        );
+    }
+
+    #[test]
+    fn test_min_dwell() {
+        let temp_dir = TempDir::new().expect("failed to create a temporary directory");
+        let start_time = Zoned::from_str("2024-08-10T00:00:59.999[UTC]").unwrap();
+        let mut writer = RollingFileWriterBuilder::new()
+            .rotation(Rotation::Minutely)
+            .filename_prefix("test_prefix")
+            .min_dwell(Duration::from_secs(1))
+            .clock(Clock::ManualClock(ManualClock::new(start_time.clone())))
+            .build(&temp_dir)
+            .unwrap();
+
+        // the clock crosses the rotation boundary only milliseconds after the file is opened
+        for millis in [1, 500, 999] {
+            let now = start_time.add(Span::new().milliseconds(millis));
+            writer.state.clock.set_now(now);
+            writer.write_all(b"old\n").unwrap();
+        }
+        assert_eq!(fs::read_dir(&temp_dir).unwrap().count(), 1);
+
+        writer
+            .state
+            .clock
+            .set_now(start_time.add(Span::new().seconds(1)));
+        writer.write_all(b"new\n").unwrap();
+        writer.flush().unwrap();
+        assert_eq!(fs::read_dir(&temp_dir).unwrap().count(), 2);
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("test_prefix.2024-08-10-00-01.0")).unwrap(),
+            "new\n"
+        );
+
+        // without a minimum, the file is rolled as soon as the rotation is due
+        let temp_dir = TempDir::new().expect("failed to create a temporary directory");
+        let mut writer = RollingFileWriterBuilder::new()
+            .rotation(Rotation::Minutely)
+            .filename_prefix("test_prefix")
+            .clock(Clock::ManualClock(ManualClock::new(start_time.clone())))
+            .build(&temp_dir)
+            .unwrap();
+        writer
+            .state
+            .clock
+            .set_now(start_time.add(Span::new().milliseconds(1)));
+        writer.write_all(b"new\n").unwrap();
+        assert_eq!(fs::read_dir(&temp_dir).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_remove_unused_files() {
+        let temp_dir = TempDir::new().expect("failed to create a temporary directory");
+        let start_time = Zoned::from_str("2024-08-10T00:00:00[UTC]").unwrap();
+        let mut writer = RollingFileWriterBuilder::new()
+            .rotation(Rotation::Minutely)
+            .filename_prefix("test_prefix")
+            .remove_unused_files(true)
+            .clock(Clock::ManualClock(ManualClock::new(start_time.clone())))
+            .build(&temp_dir)
+            .unwrap();
+
+        // no records arrive for two minutes
+        writer
+            .state
+            .clock
+            .set_now(start_time.add(Span::new().minutes(2)));
+        writer.write_all(b"new\n").unwrap();
+        writer.flush().unwrap();
+
+        let files = fs::read_dir(&temp_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(files, ["test_prefix.2024-08-10-00-02.0"]);
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join(&files[0])).unwrap(),
+            "new\n"
+        );
+    }
+
+    #[test]
+    fn test_file_header() {
+        let temp_dir = TempDir::new().expect("failed to create a temporary directory");
+        let existing = temp_dir.path().join("test_prefix.0.csv");
+        fs::write(&existing, "time,level\nold\n").unwrap();
+
+        let mut writer = RollingFileWriterBuilder::new()
+            .rotation(Rotation::Never)
+            .filename_prefix("test_prefix")
+            .filename_suffix("csv")
+            .max_file_size(18)
+            .file_header("time,level\n")
+            .build(&temp_dir)
+            .unwrap();
+        for line in ["first\n", "second\n", "third\n"] {
+            writer.write_all(line.as_bytes()).unwrap();
+        }
+        writer.flush().unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&existing).unwrap(),
+            "time,level\nold\nfirst\n"
+        );
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("test_prefix.1.csv")).unwrap(),
+            "time,level\nsecond\n"
+        );
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("test_prefix.2.csv")).unwrap(),
+            "time,level\nthird\n"
+        );
+    }
+
+    #[test]
+    fn test_rotation_check_interval() {
+        let temp_dir = TempDir::new().expect("failed to create a temporary directory");
+        let start_time = Zoned::from_str("2024-08-10T00:00:00[UTC]").unwrap();
+        let mut writer = RollingFileWriterBuilder::new()
+            .rotation(Rotation::Minutely)
+            .filename_prefix("test_prefix")
+            .rotation_check_interval(3)
+            .clock(Clock::ManualClock(ManualClock::new(start_time.clone())))
+            .build(&temp_dir)
+            .unwrap();
+
+        for _ in 0..3 {
+            writer.write_all(b"old\n").unwrap();
+        }
+        writer
+            .state
+            .clock
+            .set_now(start_time.add(Span::new().minutes(1)));
+        for _ in 0..3 {
+            writer.write_all(b"new\n").unwrap();
+        }
+        writer.flush().unwrap();
+
+        let read = |date| {
+            fs::read_to_string(temp_dir.path().join(format!("test_prefix.{date}.0"))).unwrap()
+        };
+        // the boundary is checked on the third write after the previous check
+        assert_eq!(read("2024-08-10-00-00"), "old\nold\nold\nnew\nnew\n");
+        assert_eq!(read("2024-08-10-00-01"), "new\n");
+    }
+
+    #[test]
+    fn test_log_filename() {
+        let temp_dir = TempDir::new().expect("failed to create a temporary directory");
+        let start_time = Zoned::from_str("2024-08-10T17:12:52+08[+08]").unwrap();
+        let builder = || {
+            RollingFileWriterBuilder::new()
+                .rotation(Rotation::Daily)
+                .filename_prefix("app")
+                .filename_suffix("log")
+                .max_file_size(4)
+                .clock(Clock::ManualClock(ManualClock::new(start_time.clone())))
+        };
+        let mut writer = builder().build(&temp_dir).unwrap();
+        writer.write_all(b"one\n").unwrap();
+        writer.write_all(b"two\n").unwrap();
+        writer.flush().unwrap();
+
+        let builder = builder();
+        assert_eq!(builder.log_filename(&start_time, 0), "app.2024-08-10.0.log");
+        for (index, content) in [(0, "one\n"), (1, "two\n")] {
+            let path = temp_dir
+                .path()
+                .join(builder.log_filename(&start_time, index));
+            assert_eq!(fs::read_to_string(path).unwrap(), content);
+        }
+    }
+
+    #[test]
+    fn test_durable() {
+        let temp_dir = TempDir::new().expect("failed to create a temporary directory");
+        let mut writer = RollingFileWriterBuilder::new()
+            .rotation(Rotation::Never)
+            .filename_prefix("audit")
+            .max_file_size(12)
+            .durable(true)
+            .build(&temp_dir)
+            .unwrap();
+
+        let read = |name| fs::read_to_string(temp_dir.path().join(name)).unwrap();
+        writer.write_all(b"login\n").unwrap();
+        assert_eq!(writer.state.syncs, 1);
+        assert_eq!(read("audit.0"), "login\n");
+
+        writer.write_all(b"logout\n").unwrap();
+        assert_eq!(writer.state.syncs, 2);
+        assert_eq!(read("audit.0"), "login\nlogout\n");
+
+        // the record is synced to the rotated file
+        writer.write_all(b"login\n").unwrap();
+        assert_eq!(writer.state.syncs, 3);
+        assert_eq!(read("audit.1"), "login\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink() {
+        let temp_dir = TempDir::new().expect("failed to create a temporary directory");
+        let mut writer = RollingFileWriterBuilder::new()
+            .rotation(Rotation::Never)
+            .filename_prefix("app")
+            .filename_suffix("log")
+            .max_file_size(4)
+            .max_log_files(2)
+            .symlink("app.log")
+            .build(&temp_dir)
+            .unwrap();
+
+        let symlink = temp_dir.path().join("app.log");
+        assert_eq!(fs::read_link(&symlink).unwrap().to_str(), Some("app.0.log"));
+
+        writer.write_all(b"old\n").unwrap();
+        writer.write_all(b"new\n").unwrap();
+        writer.flush().unwrap();
+        assert_eq!(fs::read_link(&symlink).unwrap().to_str(), Some("app.1.log"));
+        assert_eq!(fs::read_to_string(&symlink).unwrap(), "new\n");
+
+        // the symlink doesn't count as a log file
+        writer.write_all(b"end\n").unwrap();
+        writer.flush().unwrap();
+        assert!(temp_dir.path().join("app.1.log").exists());
+        assert_eq!(fs::read_to_string(&symlink).unwrap(), "end\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_report_errors() {
+        let temp_dir = TempDir::new().expect("failed to create a temporary directory");
+        let mut writer = RollingFileWriterBuilder::new()
+            .rotation(Rotation::Never)
+            .filename_prefix("app")
+            .filename_suffix("log")
+            .max_file_size(4)
+            .symlink("app.log")
+            .build(&temp_dir)
+            .unwrap();
+        let errors = crate::append::rolling_file::ErrorSlot::default();
+        writer.report_errors_to(errors.clone());
+
+        // a non-empty directory in place of the symlink can't be replaced
+        let symlink = temp_dir.path().join("app.log");
+        fs::remove_file(&symlink).unwrap();
+        fs::create_dir(&symlink).unwrap();
+        fs::write(symlink.join("keep"), "").unwrap();
+
+        writer.write_all(b"old\n").unwrap();
+        writer.write_all(b"new\n").unwrap();
+        let err = errors.lock().unwrap().take().unwrap();
+        assert!(
+            err.to_string()
+                .contains("failed to update symlink to app.1.log"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_active_filename_restart() {
+        let temp_dir = TempDir::new().expect("failed to create a temporary directory");
+        let active = temp_dir.path().join("current.log");
+        fs::write(&active, "before restart\n").unwrap();
+        let modified = fs::metadata(&active).unwrap().modified().unwrap();
+        let modified = jiff::Timestamp::try_from(modified)
+            .unwrap()
+            .to_zoned(jiff::tz::TimeZone::UTC);
+
+        // restarted a day after the active file was last written
+        let mut writer = RollingFileWriterBuilder::new()
+            .rotation(Rotation::Daily)
+            .filename_prefix("app")
+            .filename_suffix("log")
+            .active_filename("current.log")
+            .clock(Clock::ManualClock(ManualClock::new(
+                modified.add(Span::new().days(1)),
+            )))
+            .build(&temp_dir)
+            .unwrap();
+        writer.write_all(b"after restart\n").unwrap();
+        writer.flush().unwrap();
+
+        let archive = format!("app.{}.0.log", modified.strftime("%Y-%m-%d"));
+        let read = |filename: &str| fs::read_to_string(temp_dir.path().join(filename)).unwrap();
+        assert_eq!(read(&archive), "before restart\n");
+        assert_eq!(read("current.log"), "after restart\n");
+    }
+
+    #[test]
+    fn test_write_at_dispatch_time() {
+        let temp_dir = TempDir::new().expect("failed to create a temporary directory");
+        let before_midnight = Zoned::from_str("2024-08-10T23:59:59.999[UTC]").unwrap();
+        let mut writer = RollingFileWriterBuilder::new()
+            .rotation(Rotation::Daily)
+            .filename_prefix("app")
+            .filename_suffix("log")
+            .clock(Clock::ManualClock(ManualClock::new(
+                before_midnight.clone(),
+            )))
+            .build(&temp_dir)
+            .unwrap();
+        let read = |filename: &str| fs::read_to_string(temp_dir.path().join(filename)).unwrap();
+
+        // the record dispatched before midnight is written after it
+        let after_midnight = Zoned::from_str("2024-08-11T00:00:00.001[UTC]").unwrap();
+        writer.state.clock.set_now(after_midnight.clone());
+        writer.write_at(b"late\n", &before_midnight).unwrap();
+        writer.write_at(b"next\n", &after_midnight).unwrap();
+        writer.flush().unwrap();
+
+        assert_eq!(read("app.2024-08-10.0.log"), "late\n");
+        assert_eq!(read("app.2024-08-11.0.log"), "next\n");
+    }
+
+    #[test]
+    fn test_active_filename() {
+        let temp_dir = TempDir::new().expect("failed to create a temporary directory");
+        let start_time = Zoned::from_str("2024-08-10T00:00:00[UTC]").unwrap();
+        let mut writer = RollingFileWriterBuilder::new()
+            .rotation(Rotation::Minutely)
+            .filename_prefix("app")
+            .filename_suffix("log")
+            .max_file_size(8)
+            .active_filename("current.log")
+            .clock(Clock::ManualClock(ManualClock::new(start_time.clone())))
+            .build(&temp_dir)
+            .unwrap();
+        let read = |filename: &str| fs::read_to_string(temp_dir.path().join(filename)).unwrap();
+        let files = || {
+            let mut files = fs::read_dir(&temp_dir)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+                .collect::<Vec<_>>();
+            files.sort();
+            files
+        };
+
+        writer.write_all(b"first\n").unwrap();
+        assert_eq!(files(), ["current.log"]);
+
+        // rolls over on the file size
+        writer.write_all(b"second\n").unwrap();
+        writer.write_all(b"third\n").unwrap();
+        assert_eq!(files(), ["app.2024-08-10-00-00.0.log", "current.log"]);
+        assert_eq!(read("app.2024-08-10-00-00.0.log"), "first\nsecond\n");
+        assert_eq!(read("current.log"), "third\n");
+
+        // rolls over on the time
+        writer
+            .state
+            .clock
+            .set_now(start_time.add(Span::new().minutes(1)));
+        writer.write_all(b"fourth\n").unwrap();
+        writer.flush().unwrap();
+        assert_eq!(
+            files(),
+            [
+                "app.2024-08-10-00-00.0.log",
+                "app.2024-08-10-00-00.1.log",
+                "current.log"
+            ]
+        );
+        assert_eq!(read("app.2024-08-10-00-00.1.log"), "third\n");
+        assert_eq!(read("current.log"), "fourth\n");
+    }
+
+    #[test]
+    fn test_existing_file_size() {
+        let temp_dir = TempDir::new().expect("failed to create a temporary directory");
+        let existing = temp_dir.path().join("test_prefix.0.log");
+        fs::write(&existing, "0123456789").unwrap();
+
+        let mut writer = RollingFileWriterBuilder::new()
+            .rotation(Rotation::Never)
+            .filename_prefix("test_prefix")
+            .filename_suffix("log")
+            .max_file_size(12)
+            .build(&temp_dir)
+            .unwrap();
+        assert_eq!(writer.state.current_filesize, 10);
+
+        writer.write_all(b"ab\n").unwrap();
+        writer.write_all(b"cd\n").unwrap();
+        writer.flush().unwrap();
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "0123456789ab\n");
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("test_prefix.1.log")).unwrap(),
+            "cd\n"
+        );
+    }
+
+    #[test]
+    fn test_existing_file_policy() {
+        let temp_dir = TempDir::new().expect("failed to create a temporary directory");
+        let existing = temp_dir.path().join("test_prefix.0.log");
+        fs::write(&existing, "old\n").unwrap();
+
+        let builder = || {
+            RollingFileWriterBuilder::new()
+                .rotation(Rotation::Never)
+                .filename_prefix("test_prefix")
+                .filename_suffix("log")
+        };
+
+        let err = builder()
+            .existing_file(ExistingFilePolicy::Error)
+            .build(&temp_dir)
+            .unwrap_err();
+        assert!(err.to_string().contains("test_prefix.0.log"), "{err}");
+
+        let mut writer = builder()
+            .existing_file(ExistingFilePolicy::NextIndex)
+            .build(&temp_dir)
+            .unwrap();
+        assert_eq!(writer.state.current_count, 1);
+        writer.write_all(b"new\n").unwrap();
+        writer.flush().unwrap();
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "old\n");
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("test_prefix.1.log")).unwrap(),
+            "new\n"
+        );
+
+        let mut writer = builder().build(&temp_dir).unwrap();
+        writer.write_all(b"appended\n").unwrap();
+        writer.flush().unwrap();
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "old\nappended\n");
+    }
+
+    #[test]
+    fn test_best_effort() {
+        let temp_dir = TempDir::new().expect("failed to create a temporary directory");
+        // a file in place of a parent directory is unwritable, even for root
+        let blocker = temp_dir.path().join("blocker");
+        fs::write(&blocker, "").unwrap();
+        let log_dir = blocker.join("logs");
+        let builder = || {
+            RollingFileWriterBuilder::new()
+                .filename_prefix("test_prefix")
+                .filename_suffix("log")
+        };
+
+        let err = builder().build(&log_dir).unwrap_err();
+        assert!(
+            err.to_string().contains("failed to create log directory"),
+            "{err}"
+        );
+
+        let mut writer = builder().best_effort(true).build(&log_dir).unwrap();
+        assert!(writer.write_all(b"lost\n").is_err());
+        writer.flush().unwrap();
+
+        fs::remove_file(&blocker).unwrap();
+        writer.write_all(b"written\n").unwrap();
+        writer.flush().unwrap();
+        assert_eq!(
+            fs::read_to_string(log_dir.join("test_prefix.0.log")).unwrap(),
+            "written\n"
+        );
+    }
+
     fn generate_random_string() -> String {
         let mut rng = rand::thread_rng();
         let len = rng.gen_range(50..=100);
@@ -12,19 +12,132 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::any::Any;
+use std::fmt;
 use std::io;
 use std::io::Write;
+use std::sync::Arc;
+use std::sync::PoisonError;
+use std::time::Duration;
 
 use crossbeam_channel::Receiver;
 use crossbeam_channel::RecvError;
+use crossbeam_channel::RecvTimeoutError;
 use crossbeam_channel::TryRecvError;
+use jiff::Zoned;
+use parking_lot::Mutex;
 
+use crate::append::rolling_file::clock::Clock;
+use crate::append::rolling_file::ErrorSlot;
 use crate::append::rolling_file::Message;
+use crate::append::rolling_file::RollingFileWriter;
+use crate::append::StatsHandle;
+
+/// The operations of a writer that rolls over, resolved once the worker is built, so that a
+/// writer without them is told apart before any record is sent.
+struct Rolling<T> {
+    /// Writes a record at the time it's dispatched at, see [`RollingFileWriter::write_at`].
+    write_at: fn(&mut T, &[u8], &Zoned) -> io::Result<()>,
+    /// See [`RollingFileWriter::rotate_now`].
+    rotate_now: fn(&mut T) -> anyhow::Result<()>,
+    /// See [`RollingFileWriter::report_errors_to`].
+    report_errors_to: fn(&mut T, ErrorSlot),
+}
+
+impl<T> Clone for Rolling<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Rolling<T> {}
+
+impl<T: Any> Rolling<T> {
+    fn resolve(writer: &T) -> Option<Rolling<T>> {
+        let writer: &dyn Any = writer;
+        writer.is::<RollingFileWriter>().then_some(Rolling {
+            write_at: |writer, buf, now| rolling_file(writer).write_at(buf, now),
+            rotate_now: |writer| rolling_file(writer).rotate_now(),
+            report_errors_to: |writer, errors| rolling_file(writer).report_errors_to(errors),
+        })
+    }
+}
+
+fn rolling_file<T: Any>(writer: &mut T) -> &mut RollingFileWriter {
+    let writer: &mut dyn Any = writer;
+    match writer.downcast_mut() {
+        Some(writer) => writer,
+        None => unreachable!("the writer is checked to be a rolling file writer"),
+    }
+}
+
+/// Writes a record at the time it's dispatched at, if the writer rolls over and the time is known.
+fn write_record<T>(
+    writer: &mut T,
+    rolling: Option<Rolling<T>>,
+    record: &[u8],
+    time: Option<&Zoned>,
+) -> io::Result<()>
+where
+    T: Write,
+{
+    match (rolling, time) {
+        (Some(rolling), Some(time)) => (rolling.write_at)(writer, record, time),
+        _ => writer.write_all(record),
+    }
+}
+
+/// Writes records to the writer of a worker on the calling thread, bypassing the worker's queue.
+pub(super) trait SyncWrite: fmt::Debug + Send + Sync {
+    /// Writes and flushes a record, waiting at most the timeout for the worker to release the
+    /// writer.
+    fn write_sync(&self, record: &[u8], time: Option<&Zoned>, timeout: Duration) -> io::Result<()>;
+}
+
+struct SharedWriter<T> {
+    writer: Arc<Mutex<T>>,
+    rolling: Option<Rolling<T>>,
+}
+
+impl<T> fmt::Debug for SharedWriter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedWriter").finish_non_exhaustive()
+    }
+}
+
+impl<T: Write + Send + 'static> SyncWrite for SharedWriter<T> {
+    fn write_sync(&self, record: &[u8], time: Option<&Zoned>, timeout: Duration) -> io::Result<()> {
+        let Some(mut writer) = self.writer.try_lock_for(timeout) else {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "timed out waiting for the worker to release the writer",
+            ));
+        };
+        write_record(&mut *writer, self.rolling, record, time)?;
+        writer.flush()
+    }
+}
 
 pub(super) struct Worker<T: Write + Send + 'static> {
-    writer: T,
+    // shared with the handles writing records synchronously, see [`SyncWrite`]
+    writer: Arc<Mutex<T>>,
+    rolling: Option<Rolling<T>>,
     receiver: Receiver<Message>,
     shutdown: Receiver<()>,
+    flush_interval: Option<Duration>,
+    flush_on_buffer_bytes: Option<usize>,
+    flush_on_record_count: Option<usize>,
+    flush_aligned: Option<Duration>,
+    // the wall-clock time of the next aligned flush, in milliseconds since the Unix epoch
+    next_aligned_flush: Option<i64>,
+    clock: Clock,
+    // the time of the last flush by the clock, in milliseconds since the Unix epoch
+    last_flush: i64,
+    unflushed: bool,
+    unflushed_bytes: usize,
+    unflushed_records: usize,
+    errors: ErrorSlot,
+    stats: StatsHandle,
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -36,37 +149,202 @@ pub(super) enum WorkerState {
 }
 
 impl<T: Write + Send + 'static> Worker<T> {
-    pub(crate) fn new(writer: T, receiver: Receiver<Message>, shutdown: Receiver<()>) -> Worker<T> {
+    pub(crate) fn new(
+        writer: T,
+        receiver: Receiver<Message>,
+        shutdown: Receiver<()>,
+        flush_interval: Option<Duration>,
+    ) -> Worker<T> {
+        // follow the clock of a rolling file writer, so that the flushes and the rotations agree
+        let writer_clock = (&writer as &dyn Any)
+            .downcast_ref::<RollingFileWriter>()
+            .map(RollingFileWriter::clock);
+        let clock = writer_clock.unwrap_or(Clock::DefaultClock);
         Self {
-            writer,
+            rolling: Rolling::resolve(&writer),
+            writer: Arc::new(Mutex::new(writer)),
             receiver,
             shutdown,
+            flush_interval,
+            flush_on_buffer_bytes: None,
+            flush_on_record_count: None,
+            flush_aligned: None,
+            next_aligned_flush: None,
+            last_flush: now_millis(&clock),
+            clock,
+            unflushed: false,
+            unflushed_bytes: 0,
+            unflushed_records: 0,
+            errors: ErrorSlot::default(),
+            stats: StatsHandle::default(),
         }
     }
 
-    fn recv(&mut self) -> io::Result<WorkerState> {
-        match self.receiver.recv() {
-            Ok(Message::Record(record)) => {
-                self.writer.write_all(&record)?;
+    /// A handle writing records to the writer on the calling thread.
+    pub(super) fn sync_writer(&self) -> Arc<dyn SyncWrite> {
+        Arc::new(SharedWriter {
+            writer: self.writer.clone(),
+            rolling: self.rolling,
+        })
+    }
+
+    /// Whether the writer can be rolled over by [`Message::Rotate`].
+    pub(super) fn rotatable(&self) -> bool {
+        self.rolling.is_some()
+    }
+
+    pub(super) fn error_slot(mut self, errors: ErrorSlot) -> Worker<T> {
+        if let Some(rolling) = self.rolling {
+            (rolling.report_errors_to)(&mut *self.writer.lock(), errors.clone());
+        }
+        self.errors = errors;
+        self
+    }
+
+    pub(super) fn stats(mut self, stats: StatsHandle) -> Worker<T> {
+        self.stats = stats;
+        self
+    }
+
+    pub(super) fn flush_on_buffer_bytes(mut self, bytes: Option<usize>) -> Worker<T> {
+        self.flush_on_buffer_bytes = bytes;
+        self
+    }
+
+    pub(super) fn flush_on_record_count(mut self, count: Option<usize>) -> Worker<T> {
+        self.flush_on_record_count = count;
+        self
+    }
+
+    pub(super) fn flush_aligned(mut self, period: Option<Duration>) -> Worker<T> {
+        self.flush_aligned = period;
+        self.next_aligned_flush = period.map(|period| next_aligned(&self.clock.now(), period));
+        self
+    }
+
+    pub(super) fn clock(mut self, clock: Clock) -> Worker<T> {
+        self.last_flush = now_millis(&clock);
+        self.clock = clock;
+        self
+    }
+
+    fn handle_message(&mut self, message: Message) -> io::Result<WorkerState> {
+        match message {
+            Message::Record(record, time) => {
+                // the boundary passed while idle, so schedule the next one from now rather than
+                // flushing the first record right away
+                if let (false, Some(period)) = (self.unflushed, self.flush_aligned) {
+                    self.next_aligned_flush = Some(next_aligned(&self.clock.now(), period));
+                }
+                write_record(
+                    &mut *self.writer.lock(),
+                    self.rolling,
+                    &record,
+                    time.as_ref(),
+                )?;
+                self.stats.record(record.len());
+                self.unflushed = true;
+                self.unflushed_bytes += record.len();
+                self.unflushed_records += 1;
+                if self
+                    .flush_on_buffer_bytes
+                    .is_some_and(|bytes| self.unflushed_bytes >= bytes)
+                    || self
+                        .flush_on_record_count
+                        .is_some_and(|count| self.unflushed_records >= count)
+                {
+                    self.flush()?;
+                }
                 Ok(WorkerState::Continue)
             }
-            Ok(Message::Shutdown) => Ok(WorkerState::Shutdown),
-            Err(RecvError) => Ok(WorkerState::Disconnected),
+            Message::Flush(done) => {
+                self.flush()?;
+                if let Some(done) = done {
+                    let _ = done.send(());
+                }
+                Ok(WorkerState::Continue)
+            }
+            Message::Rotate(done) => {
+                let result = match self.rolling {
+                    Some(rolling) => (rolling.rotate_now)(&mut *self.writer.lock()),
+                    None => Err(anyhow::anyhow!("the writer doesn't support rotation")),
+                };
+                let _ = done.send(result);
+                Ok(WorkerState::Continue)
+            }
+            Message::Shutdown => Ok(WorkerState::Shutdown),
+        }
+    }
+
+    fn recv(&mut self) -> io::Result<WorkerState> {
+        // wake up in time to flush pending writes if a flush schedule is configured
+        let interval_deadline = match self.flush_interval {
+            Some(interval) if self.unflushed => Some(self.last_flush + interval.as_millis() as i64),
+            _ => None,
+        };
+        let aligned_deadline = match self.next_aligned_flush {
+            Some(next) if self.unflushed => Some(next),
+            _ => None,
+        };
+        let deadline = match (interval_deadline, aligned_deadline) {
+            (Some(interval), Some(aligned)) => Some(interval.min(aligned)),
+            (interval, aligned) => interval.or(aligned),
+        };
+
+        match deadline {
+            Some(deadline) => {
+                let wait = (deadline - now_millis(&self.clock)).max(0) as u64;
+                self.recv_timeout(Duration::from_millis(wait))
+            }
+            None => match self.receiver.recv() {
+                Ok(message) => self.handle_message(message),
+                Err(RecvError) => Ok(WorkerState::Disconnected),
+            },
+        }
+    }
+
+    fn recv_timeout(&mut self, timeout: Duration) -> io::Result<WorkerState> {
+        match self.receiver.recv_timeout(timeout) {
+            Ok(message) => self.handle_message(message),
+            Err(RecvTimeoutError::Timeout) => Ok(WorkerState::Empty),
+            Err(RecvTimeoutError::Disconnected) => Ok(WorkerState::Disconnected),
         }
     }
 
     fn try_recv(&mut self) -> io::Result<WorkerState> {
         match self.receiver.try_recv() {
-            Ok(Message::Record(record)) => {
-                self.writer.write_all(&record)?;
-                Ok(WorkerState::Continue)
-            }
-            Ok(Message::Shutdown) => Ok(WorkerState::Shutdown),
+            Ok(message) => self.handle_message(message),
             Err(TryRecvError::Empty) => Ok(WorkerState::Empty),
             Err(TryRecvError::Disconnected) => Ok(WorkerState::Disconnected),
         }
     }
 
+    fn should_flush(&self, worker_state: WorkerState) -> bool {
+        match (self.flush_interval, self.next_aligned_flush, worker_state) {
+            (_, _, WorkerState::Shutdown | WorkerState::Disconnected) => true,
+            (None, None, _) => true,
+            (interval, next_aligned, _) => {
+                let now = now_millis(&self.clock);
+                let interval_due = interval
+                    .is_some_and(|interval| now - self.last_flush >= interval.as_millis() as i64);
+                let aligned_due = next_aligned.is_some_and(|next| now >= next);
+                self.unflushed && (interval_due || aligned_due)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.lock().flush()?;
+        self.last_flush = now_millis(&self.clock);
+        self.unflushed = false;
+        self.unflushed_bytes = 0;
+        self.unflushed_records = 0;
+        if let Some(period) = self.flush_aligned {
+            self.next_aligned_flush = Some(next_aligned(&self.clock.now(), period));
+        }
+        Ok(())
+    }
+
     pub(super) fn work(&mut self) -> io::Result<WorkerState> {
         let mut worker_state = self.recv()?;
 
@@ -74,7 +352,9 @@ impl<T: Write + Send + 'static> Worker<T> {
             worker_state = self.try_recv()?;
         }
 
-        self.writer.flush()?;
+        if self.should_flush(worker_state) {
+            self.flush()?;
+        }
         Ok(worker_state)
     }
 
@@ -90,14 +370,240 @@ impl<T: Write + Send + 'static> Worker<T> {
                             break;
                         }
                         Err(err) => {
-                            eprintln!("failed to write log: {err}");
+                            let mut errors =
+                                self.errors.lock().unwrap_or_else(PoisonError::into_inner);
+                            *errors = Some(err);
                         }
                     }
                 }
-                if let Err(err) = self.writer.flush() {
+                if let Err(err) = self.writer.lock().flush() {
                     eprintln!("failed to flush: {err}");
                 }
             })
             .expect("failed to spawn the non-blocking rolling file writer thread")
     }
 }
+
+fn now_millis(clock: &Clock) -> i64 {
+    clock.now().timestamp().as_millisecond()
+}
+
+/// The next wall-clock time after `now` that is a multiple of the period in the time zone of `now`,
+/// e.g., the top of the next minute, in milliseconds since the Unix epoch.
+fn next_aligned(now: &Zoned, period: Duration) -> i64 {
+    let period = (period.as_millis() as i64).max(1);
+    let offset = i64::from(now.offset().seconds()) * 1000;
+    let local = now.timestamp().as_millisecond() + offset;
+    (local.div_euclid(period) + 1) * period - offset
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use crossbeam_channel::bounded;
+    use crossbeam_channel::unbounded;
+
+    use super::*;
+    use crate::append::rolling_file::clock::ManualClock;
+
+    fn forward(time: &Zoned, secs: i64) -> Zoned {
+        time.checked_add(jiff::Span::new().seconds(secs)).unwrap()
+    }
+
+    /// A writer that buffers written bytes until flushed.
+    #[derive(Default, Clone)]
+    pub(in crate::append::rolling_file) struct BufferedWriter {
+        buffered: Arc<Mutex<Vec<u8>>>,
+        flushed: Arc<Mutex<Vec<u8>>>,
+    }
+
+    impl BufferedWriter {
+        pub(in crate::append::rolling_file) fn flushed(&self) -> Vec<u8> {
+            self.flushed.lock().unwrap().clone()
+        }
+    }
+
+    impl Write for BufferedWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.buffered.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            let mut buffered = self.buffered.lock().unwrap();
+            self.flushed.lock().unwrap().append(&mut buffered);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_flush_interval() {
+        let start = Zoned::from_str("2024-08-10T00:00:00+08:00[+08:00]").unwrap();
+        let writer = BufferedWriter::default();
+        let (sender, receiver) = unbounded();
+        let (_shutdown_sender, shutdown_receiver) = bounded(0);
+        let mut worker = Worker::new(
+            writer.clone(),
+            receiver,
+            shutdown_receiver,
+            Some(Duration::from_secs(60)),
+        )
+        .clock(Clock::ManualClock(ManualClock::new(start.clone())));
+
+        sender
+            .send(Message::Record(b"hello\n".to_vec(), None))
+            .unwrap();
+        assert_eq!(worker.work().unwrap(), WorkerState::Empty);
+        assert!(writer.flushed().is_empty());
+
+        // the interval hasn't elapsed by the clock yet
+        worker.clock.set_now(forward(&start, 30));
+        sender
+            .send(Message::Record(b"world\n".to_vec(), None))
+            .unwrap();
+        assert_eq!(worker.work().unwrap(), WorkerState::Empty);
+        assert!(writer.flushed().is_empty());
+
+        // no more records arrive, but the worker wakes up to flush the pending records
+        let due = forward(&start, 60);
+        worker.clock.set_now(due.clone());
+        assert_eq!(worker.work().unwrap(), WorkerState::Empty);
+        assert_eq!(writer.flushed(), b"hello\nworld\n");
+        assert_eq!(worker.last_flush, due.timestamp().as_millisecond());
+    }
+
+    #[test]
+    fn test_flush_on_buffer_bytes() {
+        let writer = BufferedWriter::default();
+        let (sender, receiver) = unbounded();
+        let (_shutdown_sender, shutdown_receiver) = bounded(0);
+        let mut worker = Worker::new(
+            writer.clone(),
+            receiver,
+            shutdown_receiver,
+            Some(Duration::from_secs(3600)),
+        )
+        .flush_on_buffer_bytes(Some(10));
+
+        sender
+            .send(Message::Record(b"hello\n".to_vec(), None))
+            .unwrap();
+        assert_eq!(worker.work().unwrap(), WorkerState::Empty);
+        assert!(writer.flushed().is_empty());
+
+        // the buffered bytes cross the threshold
+        sender
+            .send(Message::Record(b"world\n".to_vec(), None))
+            .unwrap();
+        assert_eq!(worker.work().unwrap(), WorkerState::Empty);
+        assert_eq!(writer.flushed(), b"hello\nworld\n");
+    }
+
+    #[test]
+    fn test_flush_on_record_count() {
+        let writer = BufferedWriter::default();
+        let (sender, receiver) = unbounded();
+        let (_shutdown_sender, shutdown_receiver) = bounded(0);
+        let mut worker = Worker::new(
+            writer.clone(),
+            receiver,
+            shutdown_receiver,
+            Some(Duration::from_secs(3600)),
+        )
+        .flush_on_record_count(Some(3));
+
+        for record in [b"a\n", b"b\n"] {
+            sender.send(Message::Record(record.to_vec(), None)).unwrap();
+            assert_eq!(worker.work().unwrap(), WorkerState::Empty);
+            assert!(writer.flushed().is_empty());
+        }
+
+        // the 3rd record triggers the flush
+        sender.send(Message::Record(b"c\n".to_vec(), None)).unwrap();
+        assert_eq!(worker.work().unwrap(), WorkerState::Empty);
+        assert_eq!(writer.flushed(), b"a\nb\nc\n");
+
+        // within a batch, the flush fires right at the 3rd record
+        for record in [b"d\n", b"e\n", b"f\n", b"g\n"] {
+            sender.send(Message::Record(record.to_vec(), None)).unwrap();
+        }
+        assert_eq!(worker.work().unwrap(), WorkerState::Empty);
+        assert_eq!(writer.flushed(), b"a\nb\nc\nd\ne\nf\n");
+    }
+
+    #[test]
+    fn test_rolling_file_writer_clock() {
+        use crate::append::rolling_file::RollingFileWriterBuilder;
+
+        let start = Zoned::from_str("2024-08-10T00:00:30+08:00[+08:00]").unwrap();
+        let clock = Clock::ManualClock(ManualClock::new(start));
+        let dir = tempfile::TempDir::new().unwrap();
+        let writer = RollingFileWriterBuilder::new()
+            .clock(clock.clone())
+            .build(&dir)
+            .unwrap();
+        let (_sender, receiver) = unbounded();
+        let (_shutdown_sender, shutdown_receiver) = bounded(0);
+        let worker = Worker::new(writer, receiver, shutdown_receiver, None)
+            .flush_aligned(Some(Duration::from_secs(60)));
+
+        // the worker schedules its flushes by the clock of the writer
+        let next = Zoned::from_str("2024-08-10T00:01:00+08:00[+08:00]").unwrap();
+        assert_eq!(
+            worker.next_aligned_flush,
+            Some(next.timestamp().as_millisecond())
+        );
+        clock.set_now(next.clone());
+        assert_eq!(worker.clock.now(), next);
+    }
+
+    #[test]
+    fn test_flush_aligned() {
+        let start = Zoned::from_str("2024-08-10T00:00:59.500+05:30[+05:30]").unwrap();
+        let writer = BufferedWriter::default();
+        let (sender, receiver) = unbounded();
+        let (_shutdown_sender, shutdown_receiver) = bounded(0);
+        let mut worker = Worker::new(writer.clone(), receiver, shutdown_receiver, None)
+            .clock(Clock::ManualClock(ManualClock::new(start.clone())))
+            .flush_aligned(Some(Duration::from_secs(60)));
+
+        sender
+            .send(Message::Record(b"hello\n".to_vec(), None))
+            .unwrap();
+        assert_eq!(worker.work().unwrap(), WorkerState::Empty);
+        assert!(writer.flushed().is_empty());
+
+        // the clock crosses the top of the minute
+        let boundary = Zoned::from_str("2024-08-10T00:01:00+05:30[+05:30]").unwrap();
+        worker.clock.set_now(boundary.clone());
+        sender
+            .send(Message::Record(b"world\n".to_vec(), None))
+            .unwrap();
+        assert_eq!(worker.work().unwrap(), WorkerState::Empty);
+        assert_eq!(writer.flushed(), b"hello\nworld\n");
+
+        let next = Zoned::from_str("2024-08-10T00:02:00+05:30[+05:30]").unwrap();
+        assert_eq!(
+            worker.next_aligned_flush,
+            Some(next.timestamp().as_millisecond())
+        );
+
+        // after idling past the next boundary, the first record waits for the one after it
+        let idle = Zoned::from_str("2024-08-10T00:12:30+05:30[+05:30]").unwrap();
+        worker.clock.set_now(idle);
+        sender
+            .send(Message::Record(b"again\n".to_vec(), None))
+            .unwrap();
+        assert_eq!(worker.work().unwrap(), WorkerState::Empty);
+        assert_eq!(writer.flushed(), b"hello\nworld\n");
+        let next = Zoned::from_str("2024-08-10T00:13:00+05:30[+05:30]").unwrap();
+        assert_eq!(
+            worker.next_aligned_flush,
+            Some(next.timestamp().as_millisecond())
+        );
+    }
+}
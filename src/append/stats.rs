@@ -0,0 +1,59 @@
+// Copyright 2024 CratesLand Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+/// A snapshot of how many records and bytes an appender has written.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AppendStats {
+    /// The number of records written successfully.
+    pub records: u64,
+    /// The number of bytes written successfully.
+    pub bytes: u64,
+}
+
+/// A handle to the counters of an appender, taken before the appender is moved into a
+/// [`Dispatch`](crate::Dispatch), that reads how many records and bytes it has written since.
+///
+/// The counters are incremented with relaxed atomics once a write succeeds, and the clones of the
+/// handle share them.
+#[derive(Debug, Default, Clone)]
+pub struct StatsHandle {
+    counters: Arc<Counters>,
+}
+
+impl StatsHandle {
+    /// How many records and bytes the appender has written so far.
+    pub fn get(&self) -> AppendStats {
+        AppendStats {
+            records: self.counters.records.load(Ordering::Relaxed),
+            bytes: self.counters.bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn record(&self, bytes: usize) {
+        self.counters.records.fetch_add(1, Ordering::Relaxed);
+        self.counters
+            .bytes
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    records: AtomicU64,
+    bytes: AtomicU64,
+}
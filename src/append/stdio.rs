@@ -14,16 +14,45 @@
 
 use std::io::Write;
 
+use crate::append::encoding::DEFAULT_RECORD_DELIMITER;
 use crate::append::Append;
+use crate::append::Encoding;
+use crate::append::StatsHandle;
 
 /// An appender that prints log records to stdout.
-#[derive(Default, Debug)]
-pub struct Stdout;
+#[derive(Debug)]
+pub struct Stdout {
+    record_delimiter: Vec<u8>,
+    stats: StatsHandle,
+}
+
+impl Default for Stdout {
+    fn default() -> Self {
+        Self {
+            record_delimiter: DEFAULT_RECORD_DELIMITER.to_vec(),
+            stats: StatsHandle::default(),
+        }
+    }
+}
+
+impl Stdout {
+    /// Sets the bytes written after each record, a newline by default.
+    pub fn record_delimiter(mut self, delimiter: impl Into<Vec<u8>>) -> Self {
+        self.record_delimiter = delimiter.into();
+        self
+    }
+
+    /// Returns a handle to how many records and bytes this appender has written.
+    pub fn stats(&self) -> StatsHandle {
+        self.stats.clone()
+    }
+}
 
 impl Append for Stdout {
     fn append(&self, record: &log::Record) -> anyhow::Result<()> {
-        let bytes = format!("{}\n", record.args()).into_bytes();
+        let bytes = Encoding::Utf8.encode_record(record, &self.record_delimiter);
         std::io::stdout().write_all(&bytes)?;
+        self.stats.record(bytes.len());
         Ok(())
     }
 
@@ -33,13 +62,39 @@ impl Append for Stdout {
 }
 
 /// An appender that prints log records to stderr.
-#[derive(Default, Debug)]
-pub struct Stderr;
+#[derive(Debug)]
+pub struct Stderr {
+    record_delimiter: Vec<u8>,
+    stats: StatsHandle,
+}
+
+impl Default for Stderr {
+    fn default() -> Self {
+        Self {
+            record_delimiter: DEFAULT_RECORD_DELIMITER.to_vec(),
+            stats: StatsHandle::default(),
+        }
+    }
+}
+
+impl Stderr {
+    /// Sets the bytes written after each record, a newline by default.
+    pub fn record_delimiter(mut self, delimiter: impl Into<Vec<u8>>) -> Self {
+        self.record_delimiter = delimiter.into();
+        self
+    }
+
+    /// Returns a handle to how many records and bytes this appender has written.
+    pub fn stats(&self) -> StatsHandle {
+        self.stats.clone()
+    }
+}
 
 impl Append for Stderr {
     fn append(&self, record: &log::Record) -> anyhow::Result<()> {
-        let bytes = format!("{}\n", record.args()).into_bytes();
+        let bytes = Encoding::Utf8.encode_record(record, &self.record_delimiter);
         std::io::stderr().write_all(&bytes)?;
+        self.stats.record(bytes.len());
         Ok(())
     }
 
@@ -47,3 +102,32 @@ impl Append for Stderr {
         let _ = std::io::stderr().flush();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use log::Record;
+
+    use super::*;
+    use crate::append::AppendStats;
+
+    #[test]
+    fn test_stats() {
+        let append = Stderr::default();
+        let stats = append.stats();
+        for message in ["hello", "world!"] {
+            append
+                .append(&Record::builder().args(format_args!("{message}")).build())
+                .unwrap();
+        }
+        assert_eq!(
+            stats.get(),
+            AppendStats {
+                records: 2,
+                bytes: 13,
+            }
+        );
+
+        // another appender counts on its own
+        assert_eq!(Stderr::default().stats().get(), AppendStats::default());
+    }
+}
@@ -0,0 +1,162 @@
+// Copyright 2024 CratesLand Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use log::Metadata;
+use log::Record;
+
+use crate::append::Append;
+
+/// An appender that forwards each log record to multiple child appenders.
+///
+/// The record is formatted once by the layout of the [`Dispatch`](crate::Dispatch), and the
+/// formatted record is passed to all the children, e.g., to write identical lines to both stdout
+/// and a file without formatting each record twice. The layouts of the children are not used.
+///
+/// ```rust
+/// use logforth::append::Stderr;
+/// use logforth::append::Stdout;
+/// use logforth::append::Tee;
+///
+/// let append = Tee::new()
+///     .append(Stdout::default())
+///     .append(Stderr::default());
+/// ```
+#[derive(Debug, Default)]
+pub struct Tee {
+    appends: Vec<Box<dyn Append>>,
+}
+
+impl Tee {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a child [`Append`] to the [`Tee`].
+    pub fn append(mut self, append: impl Append) -> Self {
+        self.appends.push(Box::new(append));
+        self
+    }
+}
+
+impl Tee {
+    fn append_all(&self, record: &Record) -> anyhow::Result<()> {
+        // write to all the children even if some fail, and report the first error
+        let mut result = Ok(());
+        for append in &self.appends {
+            if append.enabled(record.metadata()) {
+                if let Err(err) = append.append(record) {
+                    result = result.and(Err(err));
+                }
+            }
+        }
+        result
+    }
+}
+
+impl Append for Tee {
+    fn append(&self, record: &Record) -> anyhow::Result<()> {
+        match record.args().as_str() {
+            Some(_) => self.append_all(record),
+            // render the message once instead of once per child
+            None => {
+                let message = record.args().to_string();
+                self.append_all(&record.to_builder().args(format_args!("{message}")).build())
+            }
+        }
+    }
+
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.appends.iter().any(|append| append.enabled(metadata))
+    }
+
+    fn flush(&self) {
+        for append in &self.appends {
+            append.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    use log::Log;
+
+    use super::*;
+    use crate::append::Writer;
+    use crate::layout::CustomLayout;
+    use crate::Dispatch;
+    use crate::Logger;
+
+    #[test]
+    fn test_format_once() {
+        let formats = Arc::new(AtomicUsize::new(0));
+        let counter = formats.clone();
+        let stdout = Arc::new(Mutex::new(Vec::new()));
+        let file = Arc::new(Mutex::new(Vec::new()));
+        let logger = Logger::new().dispatch(
+            Dispatch::new()
+                .layout(CustomLayout::new(move |record, f| {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                    f(format_args!("{} {}", record.level(), record.args()))
+                }))
+                .append(
+                    Tee::new()
+                        .append(Writer::new(stdout.clone()))
+                        .append(Writer::new(file.clone())),
+                ),
+        );
+
+        logger.log(&Record::builder().args(format_args!("hello")).build());
+        logger.flush();
+
+        assert_eq!(formats.load(Ordering::Relaxed), 1);
+        assert_eq!(stdout.lock().unwrap().as_slice(), b"INFO hello\n");
+        assert_eq!(*stdout.lock().unwrap(), *file.lock().unwrap());
+    }
+
+    #[test]
+    fn test_render_once() {
+        struct Counted(Arc<AtomicUsize>);
+
+        impl fmt::Display for Counted {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fetch_add(1, Ordering::Relaxed);
+                f.write_str("hello")
+            }
+        }
+
+        let renders = Arc::new(AtomicUsize::new(0));
+        let stdout = Arc::new(Mutex::new(Vec::new()));
+        let file = Arc::new(Mutex::new(Vec::new()));
+        let tee = Tee::new()
+            .append(Writer::new(stdout.clone()))
+            .append(Writer::new(file.clone()));
+
+        let message = Counted(renders.clone());
+        Append::append(
+            &tee,
+            &Record::builder().args(format_args!("{message}")).build(),
+        )
+        .unwrap();
+
+        assert_eq!(renders.load(Ordering::Relaxed), 1);
+        assert_eq!(stdout.lock().unwrap().as_slice(), b"hello\n");
+        assert_eq!(*stdout.lock().unwrap(), *file.lock().unwrap());
+    }
+}
@@ -0,0 +1,236 @@
+// Copyright 2024 CratesLand Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use log::Record;
+use tracing::callsite::Callsite;
+use tracing::callsite::DefaultCallsite;
+use tracing::field::display;
+use tracing::field::FieldSet;
+use tracing::field::Value;
+use tracing::metadata::Kind;
+use tracing::Event;
+use tracing::Level;
+use tracing::Metadata;
+
+use crate::append::Append;
+use crate::layout::KvDisplay;
+use crate::layout::WithoutKey;
+
+/// An appender that forwards log records to the current [`tracing`] subscriber as events, so that
+/// code on `tracing` and on `log` can share the subscriber while migrating from one to the other.
+///
+/// Each record becomes an event at the matching level, with the following fields:
+///
+/// * `message`: the message of the record.
+/// * `log.target`, `log.module_path`, `log.file`, `log.line`: the metadata of the record, if
+///   present.
+/// * `error`, `request_id`, `trace_id`, `span_id`: the key-values of the record with these
+///   well-known keys, if present.
+/// * `log.kvs`: the other key-values of the record in the logfmt style, e.g., `user=alice count=3`,
+///   if any.
+///
+/// This deviates from forwarding every key-value as a field of its own: the fields of a `tracing`
+/// event are declared statically, so only the well-known keys above get fields, and the rest are
+/// collapsed into `log.kvs`.
+///
+/// Don't forward records to a subscriber that sends events back to `log`, which would loop.
+#[derive(Default, Debug, Clone)]
+pub struct TracingBridge;
+
+/// The keys of the key-values forwarded as fields of their own.
+const KEY_FIELDS: [&str; 4] = ["error", "request_id", "trace_id", "span_id"];
+
+const FIELDS: [&str; 10] = [
+    "message",
+    "log.target",
+    "log.module_path",
+    "log.file",
+    "log.line",
+    KEY_FIELDS[0],
+    KEY_FIELDS[1],
+    KEY_FIELDS[2],
+    KEY_FIELDS[3],
+    "log.kvs",
+];
+
+macro_rules! callsite {
+    ($callsite:ident, $metadata:ident, $level:expr) => {
+        static $callsite: DefaultCallsite = DefaultCallsite::new(&$metadata);
+        static $metadata: Metadata<'static> = Metadata::new(
+            "log record",
+            "logforth",
+            $level,
+            None,
+            None,
+            None,
+            FieldSet::new(&FIELDS, tracing::callsite::Identifier(&$callsite)),
+            Kind::EVENT,
+        );
+    };
+}
+
+callsite!(ERROR_CALLSITE, ERROR_METADATA, Level::ERROR);
+callsite!(WARN_CALLSITE, WARN_METADATA, Level::WARN);
+callsite!(INFO_CALLSITE, INFO_METADATA, Level::INFO);
+callsite!(DEBUG_CALLSITE, DEBUG_METADATA, Level::DEBUG);
+callsite!(TRACE_CALLSITE, TRACE_METADATA, Level::TRACE);
+
+impl Append for TracingBridge {
+    fn append(&self, record: &Record) -> anyhow::Result<()> {
+        let callsite = match record.level() {
+            log::Level::Error => &ERROR_CALLSITE,
+            log::Level::Warn => &WARN_CALLSITE,
+            log::Level::Info => &INFO_CALLSITE,
+            log::Level::Debug => &DEBUG_CALLSITE,
+            log::Level::Trace => &TRACE_CALLSITE,
+        };
+        if callsite.interest().is_never() {
+            return Ok(());
+        }
+        let metadata = callsite.metadata();
+
+        tracing::dispatcher::get_default(|dispatch| {
+            if !dispatch.enabled(metadata) {
+                return;
+            }
+
+            let message = display(record.args());
+            let target = record.target();
+            let module_path = record.module_path();
+            let file = record.file();
+            let line = record.line();
+            let key_fields = KEY_FIELDS.map(|key| record.key_values().get(key.into()).map(display));
+            let others = WithoutKey {
+                source: record.key_values(),
+                keys: &KEY_FIELDS,
+            };
+            let kvs = KvDisplay::new(&others).to_string();
+            let kvs = Some(kvs.trim_start()).filter(|kvs| !kvs.is_empty());
+
+            let fields = metadata.fields();
+            let field = |name| fields.field(name).expect("declared field");
+            let names = FIELDS.map(field);
+            let values: [(_, Option<&dyn Value>); 10] = [
+                (&names[0], Some(&message)),
+                (&names[1], Some(&target)),
+                (&names[2], module_path.as_ref().map(|v| v as _)),
+                (&names[3], file.as_ref().map(|v| v as _)),
+                (&names[4], line.as_ref().map(|v| v as _)),
+                (&names[5], key_fields[0].as_ref().map(|v| v as _)),
+                (&names[6], key_fields[1].as_ref().map(|v| v as _)),
+                (&names[7], key_fields[2].as_ref().map(|v| v as _)),
+                (&names[8], key_fields[3].as_ref().map(|v| v as _)),
+                (&names[9], kvs.as_ref().map(|v| v as _)),
+            ];
+            dispatch.event(&Event::new(metadata, &fields.value_set(&values)));
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    use tracing::field::Field;
+    use tracing::field::Visit;
+    use tracing::span;
+    use tracing::Subscriber;
+
+    use super::*;
+
+    /// Captures the level and the fields of each event.
+    #[derive(Default, Clone)]
+    struct CaptureSubscriber(Arc<Mutex<Vec<CapturedEvent>>>);
+
+    type CapturedEvent = (Level, Vec<(String, String)>);
+
+    impl Subscriber for CaptureSubscriber {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+            span::Id::from_u64(1)
+        }
+
+        fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            struct Fields(Vec<(String, String)>);
+
+            impl Visit for Fields {
+                fn record_str(&mut self, field: &Field, value: &str) {
+                    self.0.push((field.name().to_string(), value.to_string()));
+                }
+
+                fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+                    self.0
+                        .push((field.name().to_string(), format!("{value:?}")));
+                }
+            }
+
+            let mut fields = Fields(vec![]);
+            event.record(&mut fields);
+            let level = *event.metadata().level();
+            self.0.lock().unwrap().push((level, fields.0));
+        }
+
+        fn enter(&self, _: &span::Id) {}
+
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    #[test]
+    fn test_tracing_bridge() {
+        let subscriber = CaptureSubscriber::default();
+        tracing::subscriber::with_default(subscriber.clone(), || {
+            let kvs = [("user", "alice"), ("request_id", "42ab")];
+            TracingBridge
+                .append(
+                    &Record::builder()
+                        .level(log::Level::Warn)
+                        .target("app")
+                        .line(Some(42))
+                        .args(format_args!("hello {}", "world"))
+                        .key_values(&kvs)
+                        .build(),
+                )
+                .unwrap();
+        });
+
+        let events = subscriber.0.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        let (level, fields) = &events[0];
+        assert_eq!(*level, Level::WARN);
+        let fields = fields
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            [
+                ("message", "hello world"),
+                ("log.target", "app"),
+                ("log.line", "42"),
+                ("request_id", "42ab"),
+                ("log.kvs", "user=alice"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,241 @@
+// Copyright 2024 CratesLand Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::Write;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::time::Duration;
+use std::time::Instant;
+
+use log::Record;
+
+use crate::append::encoding::DEFAULT_RECORD_DELIMITER;
+use crate::append::Append;
+use crate::append::Encoding;
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+const DEFAULT_WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// An appender that writes log records to a Unix domain socket, so that a separate viewer, e.g.,
+/// `socat UNIX-LISTEN:/tmp/app.sock -`, can attach to the live logs.
+///
+/// The socket is connected on the first record. If connecting or writing fails, the error is
+/// reported and records are dropped until the next reconnection attempt, which is retried with an
+/// exponential backoff from 100ms up to 30s. A write that doesn't complete within the write
+/// timeout, 1s by default, e.g., because the viewer stopped reading, counts as a failure too, so
+/// that a stalled viewer doesn't block the logging threads.
+///
+/// ```rust
+/// use logforth::append::UnixSocket;
+///
+/// let append = UnixSocket::new("/tmp/app.sock");
+/// ```
+#[derive(Debug)]
+pub struct UnixSocket {
+    path: PathBuf,
+    record_delimiter: Vec<u8>,
+    write_timeout: Duration,
+    connection: Mutex<Connection>,
+}
+
+#[derive(Debug)]
+struct Connection {
+    stream: Option<UnixStream>,
+    backoff: Duration,
+    retry_at: Option<Instant>,
+}
+
+impl UnixSocket {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            record_delimiter: DEFAULT_RECORD_DELIMITER.to_vec(),
+            write_timeout: DEFAULT_WRITE_TIMEOUT,
+            connection: Mutex::new(Connection {
+                stream: None,
+                backoff: INITIAL_BACKOFF,
+                retry_at: None,
+            }),
+        }
+    }
+
+    /// Sets the bytes written after each record, a newline by default, e.g., a NUL byte for a
+    /// viewer that frames records on it.
+    pub fn record_delimiter(mut self, delimiter: impl Into<Vec<u8>>) -> Self {
+        self.record_delimiter = delimiter.into();
+        self
+    }
+
+    /// Sets how long writing a record may block before the connection is dropped, 1s by default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the timeout is zero.
+    pub fn write_timeout(mut self, timeout: Duration) -> Self {
+        assert!(!timeout.is_zero(), "write timeout must be non-zero");
+        self.write_timeout = timeout;
+        self
+    }
+}
+
+impl Connection {
+    fn connect(
+        &mut self,
+        path: &Path,
+        write_timeout: Duration,
+    ) -> anyhow::Result<Option<&mut UnixStream>> {
+        if self.stream.is_none() {
+            if self
+                .retry_at
+                .is_some_and(|retry_at| Instant::now() < retry_at)
+            {
+                return Ok(None);
+            }
+            let connected = UnixStream::connect(path).and_then(|stream| {
+                stream.set_write_timeout(Some(write_timeout))?;
+                Ok(stream)
+            });
+            match connected {
+                Ok(stream) => {
+                    self.stream = Some(stream);
+                    self.backoff = INITIAL_BACKOFF;
+                    self.retry_at = None;
+                }
+                Err(err) => {
+                    self.disconnect();
+                    return Err(err.into());
+                }
+            }
+        }
+        Ok(self.stream.as_mut())
+    }
+
+    fn disconnect(&mut self) {
+        self.stream = None;
+        self.retry_at = Some(Instant::now() + self.backoff);
+        self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+impl Append for UnixSocket {
+    fn append(&self, record: &Record) -> anyhow::Result<()> {
+        let bytes = Encoding::Utf8.encode_record(record, &self.record_delimiter);
+        let mut connection = self
+            .connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let Some(stream) = connection.connect(&self.path, self.write_timeout)? else {
+            return Ok(());
+        };
+        if let Err(err) = stream.write_all(&bytes) {
+            connection.disconnect();
+            return Err(err.into());
+        }
+        Ok(())
+    }
+
+    fn flush(&self) {
+        let mut connection = self
+            .connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(stream) = &mut connection.stream {
+            let _ = stream.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufRead;
+    use std::io::BufReader;
+    use std::os::unix::net::UnixListener;
+
+    use log::Log;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::Dispatch;
+    use crate::Logger;
+
+    #[test]
+    fn test_write_to_listener() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("logforth.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+
+        let logger = Logger::new().dispatch(Dispatch::new().append(UnixSocket::new(&path)));
+        logger.log(&Record::builder().args(format_args!("hello")).build());
+        logger.flush();
+
+        let (stream, _) = listener.accept().unwrap();
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line).unwrap();
+        assert_eq!(line, "hello\n");
+    }
+
+    #[test]
+    fn test_record_delimiter() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("logforth.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+
+        let append = UnixSocket::new(&path).record_delimiter(*b"\0");
+        for message in ["hello", "multi\nline"] {
+            append
+                .append(&Record::builder().args(format_args!("{message}")).build())
+                .unwrap();
+        }
+        drop(append);
+
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut bytes = Vec::new();
+        std::io::Read::read_to_end(&mut stream, &mut bytes).unwrap();
+        assert_eq!(bytes, b"hello\0multi\nline\0");
+    }
+
+    #[test]
+    fn test_write_timeout() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("logforth.sock");
+        // a viewer that never reads
+        let _listener = UnixListener::bind(&path).unwrap();
+
+        let append = UnixSocket::new(&path).write_timeout(Duration::from_millis(10));
+        let message = "x".repeat(16 * 1024 * 1024);
+        assert!(append
+            .append(&Record::builder().args(format_args!("{message}")).build())
+            .is_err());
+        let connection = append.connection.lock().unwrap();
+        assert!(connection.stream.is_none());
+        assert_eq!(connection.backoff, INITIAL_BACKOFF * 2);
+    }
+
+    #[test]
+    fn test_backoff_after_failure() {
+        let dir = TempDir::new().unwrap();
+        let append = UnixSocket::new(dir.path().join("missing.sock"));
+        let record = Record::builder().args(format_args!("hello")).build();
+
+        assert!(append.append(&record).is_err());
+        // records are dropped until the next reconnection attempt
+        assert!(append.append(&record).is_ok());
+        let connection = append.connection.lock().unwrap();
+        assert_eq!(connection.backoff, INITIAL_BACKOFF * 2);
+    }
+}
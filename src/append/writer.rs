@@ -0,0 +1,149 @@
+// Copyright 2024 CratesLand Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+
+use log::Record;
+
+use crate::append::encoding::DEFAULT_RECORD_DELIMITER;
+use crate::append::Append;
+use crate::append::Encoding;
+
+/// An appender that writes log records to a user-supplied [`Write`] sink, e.g., an in-memory
+/// buffer or a TUI pane.
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use std::sync::Mutex;
+///
+/// use logforth::append::Writer;
+///
+/// let buffer = Arc::new(Mutex::new(Vec::<u8>::new()));
+/// let append = Writer::new(buffer.clone());
+/// ```
+#[derive(Clone)]
+pub struct Writer {
+    sink: Arc<Mutex<dyn Write + Send>>,
+    encoding: Encoding,
+    record_delimiter: Vec<u8>,
+}
+
+impl fmt::Debug for Writer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Writer {{ ... }}")
+    }
+}
+
+impl Writer {
+    pub fn new(sink: Arc<Mutex<dyn Write + Send>>) -> Self {
+        Self {
+            sink,
+            encoding: Encoding::default(),
+            record_delimiter: DEFAULT_RECORD_DELIMITER.to_vec(),
+        }
+    }
+
+    /// Sets the encoding of the bytes written to the sink, UTF-8 by default.
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Sets the bytes written after each record, a newline by default, e.g., a NUL byte for a
+    /// consumer that frames records on it.
+    ///
+    /// The delimiter is written as is, regardless of the encoding.
+    pub fn record_delimiter(mut self, delimiter: impl Into<Vec<u8>>) -> Self {
+        self.record_delimiter = delimiter.into();
+        self
+    }
+}
+
+impl Append for Writer {
+    fn append(&self, record: &Record) -> anyhow::Result<()> {
+        let bytes = self.encoding.encode_record(record, &self.record_delimiter);
+        let mut sink = self.sink.lock().unwrap_or_else(PoisonError::into_inner);
+        sink.write_all(&bytes)?;
+        Ok(())
+    }
+
+    fn flush(&self) {
+        let mut sink = self.sink.lock().unwrap_or_else(PoisonError::into_inner);
+        let _ = sink.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use log::Log;
+
+    use super::*;
+    use crate::layout::CustomLayout;
+    use crate::Dispatch;
+    use crate::Logger;
+
+    #[test]
+    fn test_write_to_vec() {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let logger = Logger::new().dispatch(
+            Dispatch::new()
+                .layout(CustomLayout::new(|record, f| {
+                    f(format_args!("{} {}", record.level(), record.args()))
+                }))
+                .append(Writer::new(buffer.clone())),
+        );
+
+        for message in ["hello", "world"] {
+            logger.log(
+                &Record::builder()
+                    .level(log::Level::Warn)
+                    .args(format_args!("{message}"))
+                    .build(),
+            );
+        }
+        logger.flush();
+
+        assert_eq!(
+            buffer.lock().unwrap().as_slice(),
+            b"WARN hello\nWARN world\n"
+        );
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn test_encoding() {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let append = Writer::new(buffer.clone()).encoding(Encoding::Latin1);
+        append
+            .append(&Record::builder().args(format_args!("naïve → 日志")).build())
+            .unwrap();
+        assert_eq!(buffer.lock().unwrap().as_slice(), b"na\xefve ? ??\n");
+    }
+
+    #[test]
+    fn test_record_delimiter() {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let append = Writer::new(buffer.clone()).record_delimiter(*b"\0");
+        for message in ["hello", "multi\nline"] {
+            append
+                .append(&Record::builder().args(format_args!("{message}")).build())
+                .unwrap();
+        }
+        assert_eq!(buffer.lock().unwrap().as_slice(), b"hello\0multi\nline\0");
+    }
+}
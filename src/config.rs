@@ -0,0 +1,493 @@
+// Copyright 2024 CratesLand Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Build a [`Logger`] from a configuration file.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::Context;
+use serde::Deserialize;
+use serde::Deserializer;
+
+use crate::append;
+use crate::filter::EnvGateFilter;
+use crate::filter::FilePathFilter;
+use crate::filter::TargetLevelFilter;
+use crate::layout;
+use crate::layout::TextLayout;
+use crate::Dispatch;
+use crate::Filter;
+use crate::Layout;
+use crate::Logger;
+
+/// The configuration of a [`Logger`], deserialized from JSON.
+///
+/// Unknown fields are rejected, so that a typo doesn't silently fall back to a default.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LoggerConfig {
+    #[serde(default)]
+    dispatches: Vec<DispatchConfig>,
+    #[serde(default)]
+    routes: Vec<RouteConfig>,
+    #[serde(default)]
+    capture_panics: bool,
+    #[serde(default)]
+    startup_banner: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RouteConfig {
+    target: String,
+    dispatch: DispatchConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DispatchConfig {
+    #[serde(default)]
+    filters: Vec<FilterConfig>,
+    #[serde(default)]
+    layout: Option<LayoutConfig>,
+    appends: Vec<AppendConfig>,
+    #[serde(default)]
+    kvs: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+enum FilterConfig {
+    Level(#[serde(deserialize_with = "level_filter")] log::LevelFilter),
+    TargetLevel {
+        #[serde(default, deserialize_with = "level_filters")]
+        levels: BTreeMap<String, log::LevelFilter>,
+        #[serde(deserialize_with = "level_filter")]
+        default: log::LevelFilter,
+    },
+    EnvGate {
+        var: String,
+        #[serde(deserialize_with = "level_filter")]
+        level: log::LevelFilter,
+    },
+    FilePath(Vec<String>),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+enum LayoutConfig {
+    Identical,
+    Text(TextLayoutConfig),
+    #[cfg(feature = "json")]
+    Json(JsonLayoutConfig),
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct TextLayoutConfig {
+    prefix: Option<String>,
+    version_tag: Option<String>,
+    level_style: LevelStyleConfig,
+    level_first: bool,
+    time_style: TimeStyleConfig,
+    module_source: ModuleSourceConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum LevelStyleConfig {
+    #[default]
+    Word,
+    Numeric,
+    Char,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum TimeStyleConfig {
+    #[default]
+    Full,
+    TimeOfDay,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ModuleSourceConfig {
+    #[default]
+    ModulePath,
+    Target,
+    Both,
+}
+
+#[cfg(feature = "json")]
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct JsonLayoutConfig {
+    pretty: bool,
+    kvs_key: Option<String>,
+    timestamp_format: TimestampFormatConfig,
+}
+
+#[cfg(feature = "json")]
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum TimestampFormatConfig {
+    #[default]
+    Rfc3339,
+    EpochMillis,
+    EpochSeconds,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+enum AppendConfig {
+    Stdout,
+    Stderr,
+    Null,
+}
+
+fn level_filter<'de, D>(deserializer: D) -> Result<log::LevelFilter, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let level = String::deserialize(deserializer)?;
+    log::LevelFilter::from_str(&level)
+        .map_err(|_| serde::de::Error::custom(format!("invalid level: {level}")))
+}
+
+fn level_filters<'de, D>(deserializer: D) -> Result<BTreeMap<String, log::LevelFilter>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    BTreeMap::<String, String>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(target, level)| {
+            let level = log::LevelFilter::from_str(&level)
+                .map_err(|_| serde::de::Error::custom(format!("invalid level: {level}")))?;
+            Ok((target, level))
+        })
+        .collect()
+}
+
+impl Logger {
+    /// Build a [`Logger`] from a JSON configuration file, so that the logging setup can be
+    /// changed without a rebuild.
+    ///
+    /// The file describes the dispatches and routes of the logger, each with its filters, layout,
+    /// appenders, and static key-values. Only the appenders and layouts that need no runtime
+    /// resources are supported, i.e., `stdout`, `stderr`, and `null` appenders, and `identical`,
+    /// `text`, and `json` layouts.
+    ///
+    /// ```json
+    /// {
+    ///   "capture_panics": true,
+    ///   "dispatches": [
+    ///     {
+    ///       "filters": [{ "target_level": { "levels": { "my_crate": "debug" }, "default": "warn" } }],
+    ///       "layout": { "text": { "prefix": "[api]" } },
+    ///       "appends": ["stderr"]
+    ///     }
+    ///   ],
+    ///   "routes": [
+    ///     {
+    ///       "target": "audit",
+    ///       "dispatch": { "layout": { "json": {} }, "appends": ["stdout"] }
+    ///     }
+    ///   ]
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the file can't be read, or if it isn't a valid configuration, e.g.,
+    /// it has an unknown field or a dispatch without appenders.
+    pub fn from_config_file(path: impl AsRef<Path>) -> anyhow::Result<Logger> {
+        let path = path.as_ref();
+        let config = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read config file: {}", path.display()))?;
+        Logger::from_config_str(&config)
+            .with_context(|| format!("invalid config file: {}", path.display()))
+    }
+
+    /// Build a [`Logger`] from a JSON configuration. See [`Logger::from_config_file`] for the
+    /// format.
+    pub fn from_config_str(config: &str) -> anyhow::Result<Logger> {
+        Logger::from_config_with(config, None)
+    }
+
+    /// Build a [`Logger`] from a JSON configuration, with the appenders replaced by the writers
+    /// returned by `redirect` if any, e.g., to check the rendered records in tests.
+    fn from_config_with(config: &str, redirect: Option<&Redirect<'_>>) -> anyhow::Result<Logger> {
+        let config: LoggerConfig = serde_json::from_str(config)?;
+
+        let mut logger = Logger::new();
+        for (i, dispatch) in config.dispatches.into_iter().enumerate() {
+            let dispatch = dispatch
+                .build(redirect)
+                .with_context(|| format!("invalid dispatches[{i}]"))?;
+            logger = logger.dispatch(dispatch);
+        }
+        for (i, route) in config.routes.into_iter().enumerate() {
+            let dispatch = route
+                .dispatch
+                .build(redirect)
+                .with_context(|| format!("invalid routes[{i}]"))?;
+            logger = logger.route(route.target, dispatch);
+        }
+        if config.capture_panics {
+            logger = logger.capture_panics();
+        }
+        if config.startup_banner {
+            logger = logger.log_startup_banner();
+        }
+        Ok(logger)
+    }
+}
+
+/// Replaces a configured appender with a writer, see [`Logger::from_config_with`].
+type Redirect<'a> = dyn Fn(&AppendConfig) -> append::Writer + 'a;
+
+impl DispatchConfig {
+    fn build(self, redirect: Option<&Redirect<'_>>) -> anyhow::Result<Dispatch> {
+        let mut appends = self.appends.into_iter();
+        let first = appends
+            .next()
+            .context("a dispatch requires at least one append")?;
+
+        let mut dispatch = Dispatch::new();
+        for filter in self.filters {
+            dispatch = dispatch.filter(filter);
+        }
+        let mut dispatch = match self.layout {
+            Some(layout) => first.add_to(dispatch.layout(layout), redirect),
+            None => first.add_to(dispatch, redirect),
+        };
+        for append in appends {
+            dispatch = append.add_to(dispatch, redirect);
+        }
+        for (key, value) in self.kvs {
+            dispatch = dispatch.kv(key, value);
+        }
+        Ok(dispatch)
+    }
+}
+
+impl AppendConfig {
+    fn add_to<const LAYOUT: bool, const APPEND: bool>(
+        self,
+        dispatch: Dispatch<LAYOUT, APPEND>,
+        redirect: Option<&Redirect<'_>>,
+    ) -> Dispatch {
+        if let Some(redirect) = redirect {
+            return dispatch.append(redirect(&self));
+        }
+        match self {
+            AppendConfig::Stdout => dispatch.append(append::Stdout::default()),
+            AppendConfig::Stderr => dispatch.append(append::Stderr::default()),
+            AppendConfig::Null => dispatch.append(append::Null::default()),
+        }
+    }
+}
+
+impl From<FilterConfig> for Filter {
+    fn from(filter: FilterConfig) -> Self {
+        match filter {
+            FilterConfig::Level(level) => level.into(),
+            FilterConfig::TargetLevel { levels, default } => {
+                TargetLevelFilter::new(levels, default).into()
+            }
+            FilterConfig::EnvGate { var, level } => EnvGateFilter::new(&var, level).into(),
+            FilterConfig::FilePath(patterns) => FilePathFilter::new(patterns).into(),
+        }
+    }
+}
+
+impl From<LayoutConfig> for Layout {
+    fn from(layout: LayoutConfig) -> Self {
+        match layout {
+            LayoutConfig::Identical => layout::IdenticalLayout.into(),
+            LayoutConfig::Text(text) => TextLayout {
+                prefix: text.prefix,
+                version_tag: text.version_tag,
+                level_style: match text.level_style {
+                    LevelStyleConfig::Word => layout::LevelStyle::Word,
+                    LevelStyleConfig::Numeric => layout::LevelStyle::Numeric,
+                    LevelStyleConfig::Char => layout::LevelStyle::Char,
+                },
+                level_first: text.level_first,
+                time_style: match text.time_style {
+                    TimeStyleConfig::Full => layout::TimeStyle::Full,
+                    TimeStyleConfig::TimeOfDay => layout::TimeStyle::TimeOfDay,
+                },
+                module_source: match text.module_source {
+                    ModuleSourceConfig::ModulePath => layout::ModuleSource::ModulePath,
+                    ModuleSourceConfig::Target => layout::ModuleSource::Target,
+                    ModuleSourceConfig::Both => layout::ModuleSource::Both,
+                },
+                ..Default::default()
+            }
+            .into(),
+            #[cfg(feature = "json")]
+            LayoutConfig::Json(json) => layout::JsonLayout {
+                pretty: json.pretty,
+                kvs_key: json.kvs_key,
+                timestamp_format: match json.timestamp_format {
+                    TimestampFormatConfig::Rfc3339 => layout::TimestampFormat::Rfc3339,
+                    TimestampFormatConfig::EpochMillis => layout::TimestampFormat::EpochMillis,
+                    TimestampFormatConfig::EpochSeconds => layout::TimestampFormat::EpochSeconds,
+                },
+                ..Default::default()
+            }
+            .into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    use log::Log;
+    use log::Metadata;
+    use log::Record;
+
+    use super::*;
+
+    fn enabled(logger: &Logger, target: &str, level: log::Level) -> bool {
+        logger.enabled(&Metadata::builder().target(target).level(level).build())
+    }
+
+    type Buffer = Arc<Mutex<Vec<u8>>>;
+
+    /// The output of the stdout, stderr, and null appenders, redirected to buffers.
+    #[derive(Default)]
+    struct Outputs {
+        stdout: Buffer,
+        stderr: Buffer,
+        null: Buffer,
+    }
+
+    impl Outputs {
+        fn redirect(&self, append: &AppendConfig) -> append::Writer {
+            let buffer = match append {
+                AppendConfig::Stdout => &self.stdout,
+                AppendConfig::Stderr => &self.stderr,
+                AppendConfig::Null => &self.null,
+            };
+            append::Writer::new(buffer.clone())
+        }
+
+        fn read(buffer: &Buffer) -> String {
+            String::from_utf8(buffer.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    fn log(logger: &Logger, target: &str, level: log::Level, message: &str) {
+        logger.log(
+            &Record::builder()
+                .target(target)
+                .level(level)
+                .args(format_args!("{message}"))
+                .build(),
+        );
+    }
+
+    const CONFIG: &str = r#"{
+        "dispatches": [
+            {
+                "filters": [
+                    { "target_level": { "levels": { "my_crate": "debug" }, "default": "warn" } }
+                ],
+                "layout": { "text": { "prefix": "[api]", "level_style": "char", "level_first": true, "time_style": "time_of_day" } },
+                "appends": ["stderr", "null"],
+                "kvs": { "service": "api" }
+            }
+        ],
+        "routes": [
+            { "target": "audit", "dispatch": { "filters": [{ "level": "error" }], "layout": "identical", "appends": ["stdout"] } }
+        ]
+    }"#;
+
+    #[test]
+    fn test_from_config_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("logforth.json");
+        std::fs::write(&path, CONFIG).unwrap();
+
+        let logger = Logger::from_config_file(&path).unwrap();
+        assert!(enabled(&logger, "my_crate::db", log::Level::Debug));
+        assert!(!enabled(&logger, "hyper", log::Level::Info));
+        assert!(enabled(&logger, "hyper", log::Level::Warn));
+        assert!(enabled(&logger, "audit", log::Level::Error));
+        assert!(!enabled(&logger, "audit", log::Level::Warn));
+    }
+
+    #[test]
+    fn test_config_output() {
+        let outputs = Outputs::default();
+        let logger =
+            Logger::from_config_with(CONFIG, Some(&|append| outputs.redirect(append))).unwrap();
+
+        log(&logger, "my_crate::db", log::Level::Debug, "connected");
+        log(&logger, "hyper", log::Level::Info, "dropped");
+        log(&logger, "audit", log::Level::Warn, "dropped");
+        log(&logger, "audit", log::Level::Error, "denied");
+        logger.flush();
+
+        // a single line, e.g., `[api] D 12:00:00.123 : connected service=api`
+        let stderr = Outputs::read(&outputs.stderr);
+        let time = stderr
+            .strip_prefix("[api] D ")
+            .and_then(|line| line.split_once(' '))
+            .map(|(time, _)| time);
+        assert!(
+            time.is_some_and(|time| jiff::civil::Time::strptime("%H:%M:%S%.f", time).is_ok()),
+            "{stderr:?}"
+        );
+        assert!(stderr.ends_with(" connected service=api\n"), "{stderr:?}");
+        assert_eq!(stderr.lines().count(), 1, "{stderr:?}");
+        assert_eq!(Outputs::read(&outputs.null), stderr);
+        assert_eq!(Outputs::read(&outputs.stdout), "denied\n");
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let err = Logger::from_config_str(r#"{ "dispatchs": [] }"#).unwrap_err();
+        assert!(
+            err.to_string().contains("unknown field `dispatchs`"),
+            "{err}"
+        );
+
+        let err = Logger::from_config_str(
+            r#"{ "dispatches": [{ "appends": ["stdout"], "layout": { "text": { "colour": true } } }] }"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("unknown field `colour`"), "{err}");
+
+        let err = Logger::from_config_str(r#"{ "dispatches": [{ "appends": [] }] }"#).unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            "invalid dispatches[0]: a dispatch requires at least one append"
+        );
+
+        let err = Logger::from_config_str(
+            r#"{ "dispatches": [{ "filters": [{ "level": "loud" }], "appends": ["stdout"] }] }"#,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("invalid level: loud"), "{err}");
+    }
+}
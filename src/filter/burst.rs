@@ -0,0 +1,194 @@
+// Copyright 2024 CratesLand Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::time::Duration;
+use std::time::Instant;
+
+use log::Metadata;
+use log::Record;
+
+use crate::filter::Filter;
+use crate::filter::FilterResult;
+use crate::layout::message;
+
+/// The number of distinct messages tracked at most, see [`BurstFilter::new`].
+const MAX_TRACKED: usize = 1024;
+
+/// A filter that passes the first occurrences of a repeated message in full, and then samples the
+/// rest.
+///
+/// Records are keyed by their target, level, and message. Within each window, the first `first`
+/// records of a key pass unconditionally, and then one in every `sample_every` subsequent records
+/// passes. The count of a key restarts once its window elapses.
+///
+/// A record is counted as soon as it reaches this filter, even if a later filter or an appender
+/// drops it. The message isn't known when the [`Logger`](crate::Logger) checks whether a record
+/// is enabled by its metadata, so this filter returns [`FilterResult::Neutral`] then.
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// use logforth::filter::BurstFilter;
+///
+/// // pass the first 5 occurrences of each message per minute, and then every 100th
+/// let filter = BurstFilter::new(5, 100, Duration::from_secs(60));
+/// ```
+#[derive(Debug)]
+pub struct BurstFilter {
+    first: u64,
+    sample_every: u64,
+    window: Duration,
+    seen: Mutex<HashMap<u64, Occurrences>>,
+}
+
+#[derive(Debug)]
+struct Occurrences {
+    window_start: Instant,
+    count: u64,
+}
+
+impl BurstFilter {
+    /// Passes the `first` records of each message per `window`, and then one in every
+    /// `sample_every`, which is at least 1.
+    ///
+    /// At most 1024 distinct messages are tracked. Once that many are, the expired ones are
+    /// evicted, or else the one whose window started the earliest, so that its count restarts.
+    pub fn new(first: u64, sample_every: u64, window: Duration) -> Self {
+        BurstFilter {
+            first,
+            sample_every: sample_every.max(1),
+            window,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) fn filter(&self, _metadata: &Metadata) -> FilterResult {
+        FilterResult::Neutral
+    }
+
+    pub(crate) fn filter_record(&self, record: &Record) -> FilterResult {
+        self.filter_at(record, Instant::now())
+    }
+
+    fn filter_at(&self, record: &Record, now: Instant) -> FilterResult {
+        let mut hasher = DefaultHasher::new();
+        record.target().hash(&mut hasher);
+        record.level().hash(&mut hasher);
+        message(record.args()).hash(&mut hasher);
+        let key = hasher.finish();
+
+        let mut seen = self.seen.lock().unwrap_or_else(PoisonError::into_inner);
+        if seen.len() >= MAX_TRACKED && !seen.contains_key(&key) {
+            seen.retain(|_, occurrences| {
+                now.duration_since(occurrences.window_start) < self.window
+            });
+            if seen.len() >= MAX_TRACKED {
+                let oldest = seen
+                    .iter()
+                    .min_by_key(|(_, occurrences)| occurrences.window_start)
+                    .map(|(key, _)| *key);
+                if let Some(oldest) = oldest {
+                    seen.remove(&oldest);
+                }
+            }
+        }
+        let occurrences = seen.entry(key).or_insert(Occurrences {
+            window_start: now,
+            count: 0,
+        });
+        if now.duration_since(occurrences.window_start) >= self.window {
+            occurrences.window_start = now;
+            occurrences.count = 0;
+        }
+        occurrences.count += 1;
+
+        let count = occurrences.count;
+        if count <= self.first || (count - self.first) % self.sample_every == 0 {
+            FilterResult::Neutral
+        } else {
+            FilterResult::Reject
+        }
+    }
+}
+
+impl From<BurstFilter> for Filter {
+    fn from(filter: BurstFilter) -> Self {
+        Filter::Burst(filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_then_sample() {
+        let filter = BurstFilter::new(3, 4, Duration::from_secs(60));
+        let start = Instant::now();
+        let passed = |message: &str, now: Instant| {
+            let result = filter.filter_at(
+                &Record::builder().args(format_args!("{message}")).build(),
+                now,
+            );
+            result == FilterResult::Neutral
+        };
+
+        let results = (0..12)
+            .map(|_| passed("retrying", start))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            results,
+            [true, true, true, false, false, false, true, false, false, false, true, false]
+        );
+
+        // other messages are counted independently
+        assert!(passed("connected", start));
+
+        // the count restarts once the window elapses
+        let later = start + Duration::from_secs(60);
+        assert!((0..3).all(|_| passed("retrying", later)));
+        assert!(!passed("retrying", later));
+    }
+
+    #[test]
+    fn test_max_tracked() {
+        let filter = BurstFilter::new(1, 1000, Duration::from_secs(60));
+        let start = Instant::now();
+        let passed = |message: &str, now: Instant| {
+            let result = filter.filter_at(
+                &Record::builder().args(format_args!("{message}")).build(),
+                now,
+            );
+            result == FilterResult::Neutral
+        };
+
+        assert!(passed("first", start));
+        assert!(!passed("first", start));
+        for n in 1..=MAX_TRACKED {
+            let now = start + Duration::from_millis(n as u64);
+            assert!(passed(&n.to_string(), now));
+        }
+        assert_eq!(filter.seen.lock().unwrap().len(), MAX_TRACKED);
+
+        // the oldest message was evicted, so its count restarts
+        assert!(passed("first", start + Duration::from_secs(1)));
+        assert_eq!(filter.seen.lock().unwrap().len(), MAX_TRACKED);
+    }
+}
@@ -64,3 +64,32 @@ impl From<CustomFilter> for Filter {
         Filter::Custom(filter)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn test_captured_flag() {
+        let debug_session = Arc::new(AtomicBool::new(false));
+        let filter = CustomFilter::new({
+            let debug_session = debug_session.clone();
+            move |_: &Metadata| {
+                if debug_session.load(Ordering::Relaxed) {
+                    FilterResult::Neutral
+                } else {
+                    FilterResult::Reject
+                }
+            }
+        });
+
+        let metadata = Metadata::builder().build();
+        assert_eq!(filter.filter(&metadata), FilterResult::Reject);
+        debug_session.store(true, Ordering::Relaxed);
+        assert_eq!(filter.filter(&metadata), FilterResult::Neutral);
+    }
+}
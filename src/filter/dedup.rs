@@ -0,0 +1,221 @@
+// Copyright 2024 CratesLand Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::time::Duration;
+use std::time::Instant;
+
+use log::Metadata;
+use log::Record;
+
+use crate::filter::Filter;
+use crate::filter::FilterResult;
+use crate::layout::message;
+
+/// A filter that suppresses a record whose message is among the `capacity` most recently seen
+/// distinct messages, so that duplicates are caught even when other messages are interleaved.
+///
+/// Records are keyed by their target, level, and message. The recent messages are kept in a
+/// bounded LRU keyed on the hash of the key: seeing a message again makes it the most recent one,
+/// and once the capacity is reached, the least recently seen message is forgotten. With a window
+/// set, a message passes again once the window has elapsed since it last passed, even if it's still
+/// remembered.
+///
+/// A message is remembered as soon as its record reaches this filter, even if a later filter or an
+/// appender drops the record. The message isn't known when the [`Logger`](crate::Logger) checks
+/// whether a record is enabled by its metadata, so this filter returns [`FilterResult::Neutral`]
+/// then.
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// use logforth::filter::DedupFilter;
+///
+/// let filter = DedupFilter::new(1000).window(Duration::from_secs(60));
+/// ```
+#[derive(Debug)]
+pub struct DedupFilter {
+    capacity: usize,
+    window: Option<Duration>,
+    recent: Mutex<Recent>,
+}
+
+#[derive(Debug, Default)]
+struct Recent {
+    messages: HashMap<u64, Seen>,
+    // the messages in the order they're seen, tagged with the tick they're seen at; a message
+    // seen again is pushed again, and the stale entries are skipped on eviction
+    order: VecDeque<(u64, u64)>,
+    tick: u64,
+}
+
+#[derive(Debug)]
+struct Seen {
+    passed_at: Instant,
+    tick: u64,
+}
+
+impl DedupFilter {
+    pub fn new(capacity: usize) -> Self {
+        DedupFilter {
+            capacity: capacity.max(1),
+            window: None,
+            recent: Mutex::new(Recent::default()),
+        }
+    }
+
+    /// Sets the window after which a suppressed message passes again.
+    pub fn window(mut self, window: Duration) -> Self {
+        self.window = Some(window);
+        self
+    }
+
+    pub(crate) fn filter(&self, _metadata: &Metadata) -> FilterResult {
+        FilterResult::Neutral
+    }
+
+    pub(crate) fn filter_record(&self, record: &Record) -> FilterResult {
+        self.filter_at(record, Instant::now())
+    }
+
+    fn filter_at(&self, record: &Record, now: Instant) -> FilterResult {
+        let mut hasher = DefaultHasher::new();
+        record.target().hash(&mut hasher);
+        record.level().hash(&mut hasher);
+        message(record.args()).hash(&mut hasher);
+        let message = hasher.finish();
+
+        let mut recent = self.recent.lock().unwrap_or_else(PoisonError::into_inner);
+        recent.tick += 1;
+        let tick = recent.tick;
+        recent.order.push_back((message, tick));
+
+        let result = match recent.messages.get_mut(&message) {
+            Some(seen)
+                if self
+                    .window
+                    .map_or(true, |window| now.duration_since(seen.passed_at) < window) =>
+            {
+                seen.tick = tick;
+                FilterResult::Reject
+            }
+            _ => {
+                let seen = Seen {
+                    passed_at: now,
+                    tick,
+                };
+                recent.messages.insert(message, seen);
+                FilterResult::Neutral
+            }
+        };
+
+        while recent.messages.len() > self.capacity {
+            let Some((oldest, tick)) = recent.order.pop_front() else {
+                break;
+            };
+            if recent
+                .messages
+                .get(&oldest)
+                .is_some_and(|seen| seen.tick == tick)
+            {
+                recent.messages.remove(&oldest);
+            }
+        }
+        // drop the stale entries of the messages seen again, to keep the memory bounded
+        if recent.order.len() > self.capacity * 2 {
+            let Recent {
+                messages, order, ..
+            } = &mut *recent;
+            order.retain(|(message, tick)| messages[message].tick == *tick);
+        }
+        result
+    }
+}
+
+impl From<DedupFilter> for Filter {
+    fn from(filter: DedupFilter) -> Self {
+        Filter::Dedup(filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use log::Level;
+
+    use super::*;
+
+    fn passed(filter: &DedupFilter, message: &str) -> bool {
+        let result =
+            filter.filter_record(&Record::builder().args(format_args!("{message}")).build());
+        result == FilterResult::Neutral
+    }
+
+    #[test]
+    fn test_interleaved_duplicates() {
+        let filter = DedupFilter::new(2);
+        let results = ["a", "b", "a", "b", "a"].map(|message| passed(&filter, message));
+        assert_eq!(results, [true, true, false, false, false]);
+
+        // "c" evicts the least recently seen message, which is "b"
+        assert!(passed(&filter, "c"));
+        assert!(!passed(&filter, "a"));
+        assert!(passed(&filter, "b"));
+
+        // the memory stays bounded
+        for i in 0..100 {
+            passed(&filter, &i.to_string());
+            passed(&filter, "a");
+        }
+        let recent = filter.recent.lock().unwrap();
+        assert!(recent.messages.len() <= 2);
+        assert!(recent.order.len() <= 4);
+    }
+
+    #[test]
+    fn test_keyed_by_target_and_level() {
+        let filter = DedupFilter::new(10);
+        let passed = |target, level| {
+            let record = Record::builder()
+                .target(target)
+                .level(level)
+                .args(format_args!("hello"))
+                .build();
+            filter.filter_record(&record) == FilterResult::Neutral
+        };
+        assert!(passed("app", Level::Info));
+        assert!(!passed("app", Level::Info));
+        assert!(passed("db", Level::Info));
+        assert!(passed("app", Level::Warn));
+    }
+
+    #[test]
+    fn test_window() {
+        let filter = DedupFilter::new(10).window(Duration::from_secs(60));
+        let start = Instant::now();
+        let passed = |now| {
+            let record = Record::builder().args(format_args!("a")).build();
+            filter.filter_at(&record, now) == FilterResult::Neutral
+        };
+        assert!(passed(start));
+        assert!(!passed(start + Duration::from_secs(59)));
+        assert!(passed(start + Duration::from_secs(60)));
+        assert!(!passed(start + Duration::from_secs(61)));
+    }
+}
@@ -0,0 +1,87 @@
+// Copyright 2024 CratesLand Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use log::Metadata;
+
+use crate::filter::Filter;
+use crate::filter::FilterResult;
+
+/// A filter that rejects log records more verbose than the specified level unless an environment
+/// variable is set.
+///
+/// The environment variable is read once when the filter is created, so toggling it afterwards
+/// does not take effect. The gate is open if the variable is set to a value other than empty,
+/// `0`, or `false`.
+///
+/// ```rust
+/// use logforth::filter::EnvGateFilter;
+///
+/// // DEBUG and TRACE logs are rejected unless `LOG_DEBUG=1` is present
+/// let filter = EnvGateFilter::new("LOG_DEBUG", log::LevelFilter::Info);
+/// ```
+#[derive(Debug, Clone)]
+pub struct EnvGateFilter {
+    level: log::LevelFilter,
+    open: bool,
+}
+
+impl EnvGateFilter {
+    pub fn new(var: &str, level: log::LevelFilter) -> Self {
+        let open = std::env::var_os(var)
+            .is_some_and(|value| !value.is_empty() && value != "0" && value != "false");
+        EnvGateFilter { level, open }
+    }
+
+    pub(crate) fn filter(&self, metadata: &Metadata) -> FilterResult {
+        if self.open || metadata.level() <= self.level {
+            FilterResult::Neutral
+        } else {
+            FilterResult::Reject
+        }
+    }
+}
+
+impl From<EnvGateFilter> for Filter {
+    fn from(filter: EnvGateFilter) -> Self {
+        Filter::EnvGate(filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use log::Level;
+
+    use super::*;
+
+    #[test]
+    fn test_env_gate() {
+        const VAR: &str = "LOGFORTH_TEST_ENV_GATE";
+        let debug = Metadata::builder().level(Level::Debug).build();
+        let info = Metadata::builder().level(Level::Info).build();
+
+        std::env::remove_var(VAR);
+        let closed = EnvGateFilter::new(VAR, log::LevelFilter::Info);
+        std::env::set_var(VAR, "1");
+        let open = EnvGateFilter::new(VAR, log::LevelFilter::Info);
+        std::env::set_var(VAR, "0");
+        let disabled = EnvGateFilter::new(VAR, log::LevelFilter::Info);
+        std::env::remove_var(VAR);
+
+        assert_eq!(closed.filter(&debug), FilterResult::Reject);
+        assert_eq!(closed.filter(&info), FilterResult::Neutral);
+        assert_eq!(open.filter(&debug), FilterResult::Neutral);
+        assert_eq!(open.filter(&info), FilterResult::Neutral);
+        assert_eq!(disabled.filter(&debug), FilterResult::Reject);
+    }
+}
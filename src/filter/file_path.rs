@@ -0,0 +1,137 @@
+// Copyright 2024 CratesLand Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use log::Metadata;
+use log::Record;
+
+use crate::filter::Filter;
+use crate::filter::FilterResult;
+
+/// A filter that rejects log records emitted from matching source files, e.g., a noisy generated
+/// file.
+///
+/// A pattern that contains `*` or `?` is a glob matched against the whole file path, where `*`
+/// matches any sequence of characters, including `/`, and `?` matches a single character.
+/// Otherwise, the pattern is a prefix of the file path.
+///
+/// Since [`Metadata`] doesn't carry the file path, this filter returns [`FilterResult::Neutral`]
+/// when the [`Logger`](crate::Logger) checks whether a record is enabled, e.g., in
+/// [`log::log_enabled!`], and only applies once the record is logged. So matching records are
+/// still constructed before being rejected.
+///
+/// ```rust
+/// use logforth::filter::FilePathFilter;
+///
+/// let filter = FilePathFilter::new(["src/generated/", "*_pb.rs"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct FilePathFilter {
+    patterns: Vec<String>,
+}
+
+impl FilePathFilter {
+    pub fn new(patterns: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        let patterns = patterns.into_iter().map(Into::into).collect();
+        FilePathFilter { patterns }
+    }
+
+    pub(crate) fn filter(&self, _metadata: &Metadata) -> FilterResult {
+        FilterResult::Neutral
+    }
+
+    pub(crate) fn filter_record(&self, record: &Record) -> FilterResult {
+        let Some(file) = record.file() else {
+            return FilterResult::Neutral;
+        };
+
+        let matched = self.patterns.iter().any(|pattern| {
+            if pattern.contains(['*', '?']) {
+                glob_match(pattern.as_bytes(), file.as_bytes())
+            } else {
+                file.starts_with(pattern.as_str())
+            }
+        });
+        if matched {
+            FilterResult::Reject
+        } else {
+            FilterResult::Neutral
+        }
+    }
+}
+
+impl From<FilePathFilter> for Filter {
+    fn from(filter: FilePathFilter) -> Self {
+        Filter::FilePath(filter)
+    }
+}
+
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // the position after the last `*` in the pattern, and the text position it matched up to
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                p += 1;
+                backtrack = Some((p, t));
+            }
+            Some(&c) if c == b'?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((bp, bt)) => {
+                    p = bp;
+                    t = bt + 1;
+                    backtrack = Some((bp, bt + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(filter: &FilePathFilter, file: Option<&str>) -> FilterResult {
+        filter.filter_record(&Record::builder().file(file).build())
+    }
+
+    #[test]
+    fn test_file_path() {
+        let files = FilePathFilter::new(["src/generated/", "*_pb.rs", "src/?.rs"]);
+
+        assert_eq!(
+            filter(&files, Some("src/generated/api.rs")),
+            FilterResult::Reject
+        );
+        assert_eq!(
+            filter(&files, Some("src/proto/user_pb.rs")),
+            FilterResult::Reject
+        );
+        assert_eq!(filter(&files, Some("src/a.rs")), FilterResult::Reject);
+        assert_eq!(filter(&files, Some("src/main.rs")), FilterResult::Neutral);
+        assert_eq!(
+            filter(&files, Some("src/user_pb.rs.bak")),
+            FilterResult::Neutral
+        );
+        assert_eq!(filter(&files, None), FilterResult::Neutral);
+
+        let metadata = Metadata::builder().build();
+        assert_eq!(files.filter(&metadata), FilterResult::Neutral);
+    }
+}
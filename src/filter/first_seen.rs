@@ -0,0 +1,131 @@
+// Copyright 2024 CratesLand Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+
+use log::Metadata;
+use log::Record;
+
+use crate::filter::Filter;
+use crate::filter::FilterResult;
+
+/// A filter that passes only the first record for each distinct value of a key-value, e.g., to
+/// log the first login of each user once per process.
+///
+/// Records without the key-value are left to the other filters. At most `capacity` distinct
+/// values are remembered; once it's reached, the oldest value is forgotten, so that a record with
+/// that value passes again.
+///
+/// A value is remembered as soon as its record reaches this filter, even if a later filter or an
+/// appender drops the record. The key-values aren't known when the [`Logger`](crate::Logger)
+/// checks whether a record is enabled by its metadata, so this filter returns
+/// [`FilterResult::Neutral`] then.
+///
+/// ```rust
+/// use logforth::filter::FirstSeenFilter;
+///
+/// let filter = FirstSeenFilter::new("user_id", 10_000);
+/// ```
+#[derive(Debug)]
+pub struct FirstSeenFilter {
+    key: String,
+    capacity: usize,
+    seen: Mutex<Seen>,
+}
+
+#[derive(Debug, Default)]
+struct Seen {
+    values: HashSet<u64>,
+    // the values in the order they're first seen, to forget the oldest one
+    order: VecDeque<u64>,
+}
+
+impl FirstSeenFilter {
+    pub fn new(key: impl Into<String>, capacity: usize) -> Self {
+        FirstSeenFilter {
+            key: key.into(),
+            capacity: capacity.max(1),
+            seen: Mutex::new(Seen::default()),
+        }
+    }
+
+    pub(crate) fn filter(&self, _metadata: &Metadata) -> FilterResult {
+        FilterResult::Neutral
+    }
+
+    pub(crate) fn filter_record(&self, record: &Record) -> FilterResult {
+        let Some(value) = record.key_values().get(self.key.as_str().into()) else {
+            return FilterResult::Neutral;
+        };
+        let mut hasher = DefaultHasher::new();
+        value.to_string().hash(&mut hasher);
+        let value = hasher.finish();
+
+        let mut seen = self.seen.lock().unwrap_or_else(PoisonError::into_inner);
+        if !seen.values.insert(value) {
+            return FilterResult::Reject;
+        }
+        seen.order.push_back(value);
+        if seen.order.len() > self.capacity {
+            if let Some(oldest) = seen.order.pop_front() {
+                seen.values.remove(&oldest);
+            }
+        }
+        FilterResult::Neutral
+    }
+}
+
+impl From<FirstSeenFilter> for Filter {
+    fn from(filter: FirstSeenFilter) -> Self {
+        Filter::FirstSeen(filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_seen() {
+        let filter = FirstSeenFilter::new("user_id", 2);
+        let passed = |user_id: &str| {
+            let kvs = [("user_id", user_id)];
+            let result = filter.filter_record(
+                &Record::builder()
+                    .args(format_args!("first login"))
+                    .key_values(&kvs)
+                    .build(),
+            );
+            result == FilterResult::Neutral
+        };
+
+        let results = ["alice", "bob", "alice"].map(passed);
+        assert_eq!(results, [true, true, false]);
+
+        // records without the key-value are passed
+        let result = filter.filter_record(&Record::builder().args(format_args!("login")).build());
+        assert_eq!(result, FilterResult::Neutral);
+
+        // the oldest value is forgotten once the capacity is reached
+        assert!(passed("carol"));
+        assert!(passed("alice"));
+        assert!(!passed("carol"));
+    }
+}
@@ -14,11 +14,27 @@
 
 //! Determinate whether a log record should be processed.
 
+pub use self::burst::BurstFilter;
 pub use self::custom::CustomFilter;
+pub use self::dedup::DedupFilter;
+pub use self::env_gate::EnvGateFilter;
+pub use self::file_path::FilePathFilter;
+pub use self::first_seen::FirstSeenFilter;
 pub use self::level::LevelFilter;
+pub use self::rate_limit::RateLimitFilter;
+pub use self::target_level::TargetLevelFilter;
+pub use self::warmup::WarmupFilter;
 
+mod burst;
 mod custom;
+mod dedup;
+mod env_gate;
+mod file_path;
+mod first_seen;
 mod level;
+mod rate_limit;
+mod target_level;
+mod warmup;
 
 /// The result of a filter may return.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -34,6 +50,14 @@ pub enum FilterResult {
 #[derive(Debug)]
 pub enum Filter {
     Level(LevelFilter),
+    TargetLevel(TargetLevelFilter),
+    RateLimit(RateLimitFilter),
+    Burst(BurstFilter),
+    FirstSeen(FirstSeenFilter),
+    Dedup(DedupFilter),
+    Warmup(WarmupFilter),
+    EnvGate(EnvGateFilter),
+    FilePath(FilePathFilter),
     Custom(CustomFilter),
 }
 
@@ -41,7 +65,28 @@ impl Filter {
     pub(crate) fn filter(&self, metadata: &log::Metadata) -> FilterResult {
         match self {
             Filter::Level(filter) => filter.filter(metadata),
+            Filter::TargetLevel(filter) => filter.filter(metadata),
+            Filter::RateLimit(filter) => filter.filter(metadata),
+            Filter::Burst(filter) => filter.filter(metadata),
+            Filter::FirstSeen(filter) => filter.filter(metadata),
+            Filter::Dedup(filter) => filter.filter(metadata),
+            Filter::Warmup(filter) => filter.filter(metadata),
+            Filter::EnvGate(filter) => filter.filter(metadata),
+            Filter::FilePath(filter) => filter.filter(metadata),
             Filter::Custom(filter) => filter.filter(metadata),
         }
     }
+
+    /// Filter a log record, with access to the fields that [`log::Metadata`] doesn't carry.
+    pub(crate) fn filter_record(&self, record: &log::Record) -> FilterResult {
+        match self {
+            Filter::FilePath(filter) => filter.filter_record(record),
+            Filter::RateLimit(filter) => filter.filter_record(record),
+            Filter::Burst(filter) => filter.filter_record(record),
+            Filter::FirstSeen(filter) => filter.filter_record(record),
+            Filter::Dedup(filter) => filter.filter_record(record),
+            Filter::Warmup(filter) => filter.filter_record(record),
+            _ => self.filter(record.metadata()),
+        }
+    }
 }
@@ -0,0 +1,154 @@
+// Copyright 2024 CratesLand Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+
+use log::Level;
+use log::Metadata;
+use log::Record;
+
+use crate::filter::Filter;
+use crate::filter::FilterResult;
+
+/// A filter that rejects log records beyond a per-level budget within a time window.
+///
+/// Each level has an independent window and counter, so that a flood of `Trace` logs doesn't use
+/// up the budget of `Error` logs. Levels without a budget are unlimited.
+///
+/// Records are only counted once they're logged, so this filter returns [`FilterResult::Neutral`]
+/// when the [`Logger`](crate::Logger) checks whether a record is enabled.
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// use log::Level;
+/// use logforth::filter::RateLimitFilter;
+///
+/// // at most 1000 errors and 10 traces per second
+/// let filter = RateLimitFilter::new(
+///     Duration::from_secs(1),
+///     [(Level::Error, 1000), (Level::Trace, 10)],
+/// );
+/// ```
+#[derive(Debug)]
+pub struct RateLimitFilter {
+    window: Duration,
+    origin: Instant,
+    // indexed by `level as usize - 1`
+    budgets: [Option<Budget>; 5],
+}
+
+#[derive(Debug)]
+struct Budget {
+    max: u32,
+    // the millis since the origin when the current window started
+    window_start: AtomicU64,
+    count: AtomicU32,
+}
+
+impl RateLimitFilter {
+    pub fn new(window: Duration, budgets: impl IntoIterator<Item = (Level, u32)>) -> Self {
+        let mut filter = RateLimitFilter {
+            window,
+            origin: Instant::now(),
+            budgets: Default::default(),
+        };
+        for (level, max) in budgets {
+            filter.budgets[level as usize - 1] = Some(Budget {
+                max,
+                window_start: AtomicU64::new(0),
+                count: AtomicU32::new(0),
+            });
+        }
+        filter
+    }
+
+    pub(crate) fn filter(&self, _metadata: &Metadata) -> FilterResult {
+        FilterResult::Neutral
+    }
+
+    pub(crate) fn filter_record(&self, record: &Record) -> FilterResult {
+        let now = self.origin.elapsed();
+        match &self.budgets[record.level() as usize - 1] {
+            Some(budget) if !budget.acquire(now, self.window) => FilterResult::Reject,
+            _ => FilterResult::Neutral,
+        }
+    }
+}
+
+impl Budget {
+    fn acquire(&self, now: Duration, window: Duration) -> bool {
+        let now = now.as_millis() as u64;
+        let window_start = self.window_start.load(Ordering::Acquire);
+        if now.saturating_sub(window_start) >= window.as_millis() as u64
+            && self
+                .window_start
+                .compare_exchange(window_start, now, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+        {
+            self.count.store(0, Ordering::Release);
+        }
+        self.count.fetch_add(1, Ordering::AcqRel) < self.max
+    }
+}
+
+impl From<RateLimitFilter> for Filter {
+    fn from(filter: RateLimitFilter) -> Self {
+        Filter::RateLimit(filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(filter: &RateLimitFilter, level: Level) -> FilterResult {
+        filter.filter_record(&Record::builder().level(level).build())
+    }
+
+    #[test]
+    fn test_per_level_budget() {
+        let limit = RateLimitFilter::new(
+            Duration::from_secs(3600),
+            [(Level::Error, 100), (Level::Trace, 2)],
+        );
+
+        assert_eq!(filter(&limit, Level::Trace), FilterResult::Neutral);
+        assert_eq!(filter(&limit, Level::Trace), FilterResult::Neutral);
+        for _ in 0..10 {
+            assert_eq!(filter(&limit, Level::Trace), FilterResult::Reject);
+            assert_eq!(filter(&limit, Level::Error), FilterResult::Neutral);
+            assert_eq!(filter(&limit, Level::Info), FilterResult::Neutral);
+        }
+    }
+
+    #[test]
+    fn test_window_reset() {
+        let budget = Budget {
+            max: 1,
+            window_start: AtomicU64::new(0),
+            count: AtomicU32::new(0),
+        };
+        let window = Duration::from_secs(1);
+
+        assert!(budget.acquire(Duration::from_millis(0), window));
+        assert!(!budget.acquire(Duration::from_millis(999), window));
+        assert!(budget.acquire(Duration::from_millis(1000), window));
+        assert!(!budget.acquire(Duration::from_millis(1500), window));
+    }
+}
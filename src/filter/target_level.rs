@@ -0,0 +1,136 @@
+// Copyright 2024 CratesLand Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+
+use log::Metadata;
+
+use crate::filter::Filter;
+use crate::filter::FilterResult;
+
+/// A filter that checks the log level against a per-target maximum level.
+///
+/// The level of the longest configured target that the record's target equals or is nested under
+/// applies, e.g., `my_crate` applies to both `my_crate` and `my_crate::db`, but not to
+/// `my_crate_ext`. If no configured target matches, the default level applies.
+///
+/// ```rust
+/// use log::LevelFilter;
+/// use logforth::filter::TargetLevelFilter;
+///
+/// // DEBUG logs of `my_crate`, and WARN logs of the dependencies
+/// let filter = TargetLevelFilter::new([("my_crate", LevelFilter::Debug)], LevelFilter::Warn);
+/// ```
+#[derive(Debug, Clone)]
+pub struct TargetLevelFilter {
+    levels: HashMap<String, log::LevelFilter>,
+    default: log::LevelFilter,
+}
+
+impl TargetLevelFilter {
+    pub fn new(
+        levels: impl IntoIterator<Item = (impl Into<String>, log::LevelFilter)>,
+        default: log::LevelFilter,
+    ) -> Self {
+        let levels = levels
+            .into_iter()
+            .map(|(target, level)| (target.into(), level))
+            .collect();
+        TargetLevelFilter { levels, default }
+    }
+
+    fn max_level(&self, target: &str) -> log::LevelFilter {
+        let mut target = target;
+        loop {
+            if let Some(level) = self.levels.get(target) {
+                return *level;
+            }
+            match target.rfind("::") {
+                Some(index) => target = &target[..index],
+                None => return self.default,
+            }
+        }
+    }
+
+    pub(crate) fn filter(&self, metadata: &Metadata) -> FilterResult {
+        if metadata.level() <= self.max_level(metadata.target()) {
+            FilterResult::Neutral
+        } else {
+            FilterResult::Reject
+        }
+    }
+}
+
+impl From<TargetLevelFilter> for Filter {
+    fn from(filter: TargetLevelFilter) -> Self {
+        Filter::TargetLevel(filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use log::Level;
+    use log::LevelFilter;
+
+    use super::*;
+
+    fn filter(filter: &TargetLevelFilter, target: &str, level: Level) -> FilterResult {
+        filter.filter(&Metadata::builder().target(target).level(level).build())
+    }
+
+    #[test]
+    fn test_prefix_matching() {
+        let levels = TargetLevelFilter::new(
+            [
+                ("my_crate", LevelFilter::Debug),
+                ("my_crate::db", LevelFilter::Error),
+            ],
+            LevelFilter::Warn,
+        );
+
+        assert_eq!(
+            filter(&levels, "my_crate", Level::Debug),
+            FilterResult::Neutral
+        );
+        assert_eq!(
+            filter(&levels, "my_crate::api", Level::Debug),
+            FilterResult::Neutral
+        );
+        assert_eq!(
+            filter(&levels, "my_crate::api", Level::Trace),
+            FilterResult::Reject
+        );
+        assert_eq!(
+            filter(&levels, "my_crate::db", Level::Warn),
+            FilterResult::Reject
+        );
+        assert_eq!(
+            filter(&levels, "my_crate::db::pool", Level::Error),
+            FilterResult::Neutral
+        );
+    }
+
+    #[test]
+    fn test_default_fallback() {
+        let levels = TargetLevelFilter::new([("my_crate", LevelFilter::Debug)], LevelFilter::Warn);
+
+        assert_eq!(filter(&levels, "hyper", Level::Warn), FilterResult::Neutral);
+        assert_eq!(filter(&levels, "hyper", Level::Info), FilterResult::Reject);
+        // not nested under `my_crate`
+        assert_eq!(
+            filter(&levels, "my_crate_ext", Level::Debug),
+            FilterResult::Reject
+        );
+    }
+}
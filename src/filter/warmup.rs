@@ -0,0 +1,118 @@
+// Copyright 2024 CratesLand Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+use std::time::Instant;
+
+use log::Level;
+use log::Metadata;
+use log::Record;
+
+use crate::filter::Filter;
+use crate::filter::FilterResult;
+
+/// A filter that rejects records for a period after it's created, e.g., to silence the harmless
+/// warnings of dependencies during startup, and then leaves all records to the other filters.
+///
+/// Records at or above a minimum level, e.g., [`Level::Error`], can be let through during the
+/// warmup by [`WarmupFilter::min_level`].
+///
+/// The warmup only ends with the clock, so this filter returns [`FilterResult::Neutral`] when the
+/// [`Logger`](crate::Logger) checks whether a record is enabled, and decides once the record is
+/// logged.
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// use log::Level;
+/// use logforth::filter::WarmupFilter;
+///
+/// let filter = WarmupFilter::new(Duration::from_secs(5)).min_level(Level::Error);
+/// ```
+#[derive(Debug)]
+pub struct WarmupFilter {
+    until: Instant,
+    min_level: Option<Level>,
+}
+
+impl WarmupFilter {
+    pub fn new(warmup: Duration) -> Self {
+        WarmupFilter {
+            until: Instant::now() + warmup,
+            min_level: None,
+        }
+    }
+
+    /// Sets the minimum level of the records to pass during the warmup.
+    pub fn min_level(mut self, level: Level) -> Self {
+        self.min_level = Some(level);
+        self
+    }
+
+    pub(crate) fn filter(&self, _metadata: &Metadata) -> FilterResult {
+        FilterResult::Neutral
+    }
+
+    pub(crate) fn filter_record(&self, record: &Record) -> FilterResult {
+        self.filter_at(record, Instant::now())
+    }
+
+    fn filter_at(&self, record: &Record, now: Instant) -> FilterResult {
+        if now >= self.until
+            || self
+                .min_level
+                .is_some_and(|min_level| record.level() <= min_level)
+        {
+            FilterResult::Neutral
+        } else {
+            FilterResult::Reject
+        }
+    }
+}
+
+impl From<WarmupFilter> for Filter {
+    fn from(filter: WarmupFilter) -> Self {
+        Filter::Warmup(filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warmup() {
+        let filter = WarmupFilter::new(Duration::from_secs(5)).min_level(Level::Error);
+        let start = filter.until - Duration::from_secs(5);
+        let passed = |level, now| {
+            let result = filter.filter_at(
+                &Record::builder()
+                    .level(level)
+                    .args(format_args!("hello"))
+                    .build(),
+                now,
+            );
+            result == FilterResult::Neutral
+        };
+
+        let during = start + Duration::from_secs(4);
+        assert!(!passed(Level::Warn, start));
+        assert!(!passed(Level::Warn, during));
+        assert!(passed(Level::Error, during));
+
+        let after = start + Duration::from_secs(5);
+        assert!(passed(Level::Warn, after));
+        assert!(passed(Level::Trace, after));
+    }
+}
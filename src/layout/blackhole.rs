@@ -0,0 +1,64 @@
+// Copyright 2024 CratesLand Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::Arguments;
+
+use crate::layout::Layout;
+
+/// A layout that formats every log record as an empty message, skipping all the formatting work.
+///
+/// This is meant for benchmarking the cost of filtering and dispatching records apart from the
+/// cost of formatting them. Pair it with an enabled [`Null`][crate::append::Null] appender to
+/// measure the logging pipeline alone.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BlackholeLayout;
+
+impl BlackholeLayout {
+    pub(crate) fn format<F>(&self, _record: &log::Record, f: &F) -> anyhow::Result<()>
+    where
+        F: Fn(Arguments) -> anyhow::Result<()>,
+    {
+        f(format_args!(""))
+    }
+}
+
+impl From<BlackholeLayout> for Layout {
+    fn from(layout: BlackholeLayout) -> Self {
+        Layout::Blackhole(layout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    #[test]
+    fn test_blackhole_layout() {
+        let output = RefCell::new(None);
+        BlackholeLayout
+            .format(
+                &log::Record::builder()
+                    .args(format_args!("hello {}", "world"))
+                    .build(),
+                &|args| {
+                    *output.borrow_mut() = Some(args.to_string());
+                    Ok(())
+                },
+            )
+            .unwrap();
+        assert_eq!(output.into_inner().as_deref(), Some(""));
+    }
+}
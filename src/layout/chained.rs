@@ -0,0 +1,210 @@
+// Copyright 2024 CratesLand Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::fmt::Arguments;
+
+use jiff::Zoned;
+
+use crate::layout::Layout;
+
+/// A layout that renders a record with two layouts and concatenates their outputs.
+///
+/// This composes existing layouts, e.g., a human-readable prefix followed by a JSON body on the
+/// same line. The outputs are joined with a space by default. You can change it by calling
+/// [`ChainedLayout::separator`].
+///
+/// You can render the second layout only for some records by calling [`ChainedLayout::when`],
+/// e.g., to follow the text line of an error with a JSON detail line in the same file. The other
+/// records are rendered by the first layout only, without the separator.
+///
+/// ```rust
+/// use logforth::layout::ChainedLayout;
+/// use logforth::layout::CustomLayout;
+/// use logforth::layout::TextLayout;
+///
+/// let layout = ChainedLayout::new(
+///     CustomLayout::new(|record, f| f(format_args!("{}", record.level()))),
+///     TextLayout::default(),
+/// )
+/// .separator(" | ");
+/// ```
+#[derive(Debug)]
+pub struct ChainedLayout {
+    first: Box<Layout>,
+    second: Box<Layout>,
+    separator: String,
+    when: Option<Predicate>,
+}
+
+struct Predicate(Box<dyn Fn(&log::Record) -> bool + Send + Sync + 'static>);
+
+impl fmt::Debug for Predicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Predicate {{ ... }}")
+    }
+}
+
+impl ChainedLayout {
+    pub fn new(first: impl Into<Layout>, second: impl Into<Layout>) -> Self {
+        ChainedLayout {
+            first: Box::new(first.into()),
+            second: Box::new(second.into()),
+            separator: " ".to_string(),
+            when: None,
+        }
+    }
+
+    /// Set the separator between the outputs of the two layouts.
+    pub fn separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    /// Render the second layout only for the records that match the predicate.
+    pub fn when(
+        mut self,
+        predicate: impl Fn(&log::Record) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.when = Some(Predicate(Box::new(predicate)));
+        self
+    }
+
+    pub(crate) fn format<F>(&self, record: &log::Record, now: &Zoned, f: &F) -> anyhow::Result<()>
+    where
+        F: Fn(Arguments) -> anyhow::Result<()>,
+    {
+        let first = render(&self.first, record, now)?;
+        if self.when.as_ref().is_some_and(|when| !(when.0)(record)) {
+            return f(format_args!("{first}"));
+        }
+        let second = render(&self.second, record, now)?;
+        f(format_args!("{first}{}{second}", self.separator))
+    }
+}
+
+fn render(layout: &Layout, record: &log::Record, now: &Zoned) -> anyhow::Result<String> {
+    let output = RefCell::new(String::new());
+    layout.format(record, now, &|record| {
+        *output.borrow_mut() = record.args().to_string();
+        Ok(())
+    })?;
+    Ok(output.into_inner())
+}
+
+impl From<ChainedLayout> for Layout {
+    fn from(layout: ChainedLayout) -> Self {
+        Layout::Chained(layout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use log::Level;
+    use log::Record;
+
+    use super::*;
+    use crate::layout::CustomLayout;
+
+    #[test]
+    fn test_chained() {
+        let prefix = CustomLayout::new(|record, f| f(format_args!("{}", record.level())));
+        let body =
+            CustomLayout::new(|record, f| f(format_args!("{{\"message\":\"{}\"}}", record.args())));
+        let layout = ChainedLayout::new(prefix, body).separator(" - ");
+
+        let output = RefCell::new(String::new());
+        layout
+            .format(
+                &Record::builder()
+                    .level(Level::Info)
+                    .args(format_args!("hello"))
+                    .build(),
+                &Zoned::now(),
+                &|args| {
+                    *output.borrow_mut() = args.to_string();
+                    Ok(())
+                },
+            )
+            .unwrap();
+        assert_eq!(output.into_inner(), r#"INFO - {"message":"hello"}"#);
+    }
+
+    #[test]
+    fn test_chained_when() {
+        let text =
+            CustomLayout::new(|record, f| f(format_args!("{} {}", record.level(), record.args())));
+        let detail = CustomLayout::new(|record, f| {
+            f(format_args!(
+                "{{\"line\":{}}}",
+                record.line().unwrap_or_default()
+            ))
+        });
+        let layout = ChainedLayout::new(text, detail)
+            .separator("\n")
+            .when(|record| record.level() <= Level::Error);
+
+        let format = |level| {
+            let output = RefCell::new(String::new());
+            layout
+                .format(
+                    &Record::builder()
+                        .level(level)
+                        .line(Some(42))
+                        .args(format_args!("hello"))
+                        .build(),
+                    &Zoned::now(),
+                    &|args| {
+                        *output.borrow_mut() = args.to_string();
+                        Ok(())
+                    },
+                )
+                .unwrap();
+            output.into_inner()
+        };
+        assert_eq!(format(Level::Error), "ERROR hello\n{\"line\":42}");
+        assert_eq!(format(Level::Info), "INFO hello");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_chained_json() {
+        use crate::layout::JsonLayout;
+
+        let prefix = CustomLayout::new(|record, f| f(format_args!("{}", record.level())));
+        let layout = ChainedLayout::new(prefix, JsonLayout::default());
+
+        let output = RefCell::new(String::new());
+        layout
+            .format(
+                &Record::builder()
+                    .level(Level::Warn)
+                    .args(format_args!("hello"))
+                    .build(),
+                &Zoned::now(),
+                &|args| {
+                    *output.borrow_mut() = args.to_string();
+                    Ok(())
+                },
+            )
+            .unwrap();
+        let output = output.into_inner();
+        let (level, json) = output.split_once(' ').unwrap();
+        assert_eq!(level, "WARN");
+        let json: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(json["level"], "WARN");
+        assert_eq!(json["message"], "hello");
+    }
+}
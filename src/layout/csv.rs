@@ -0,0 +1,248 @@
+// Copyright 2024 CratesLand Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::borrow::Cow;
+use std::fmt::Arguments;
+use std::fmt::Write;
+
+use jiff::tz::TimeZone;
+use jiff::Zoned;
+use log::Record;
+
+use crate::layout::message;
+use crate::layout::KvDisplay;
+use crate::layout::Layout;
+
+/// A layout that formats a log record as a row of comma-separated values, e.g., to import logs
+/// into a spreadsheet.
+///
+/// Output format:
+///
+/// ```text
+/// 2024-08-11T22:44:57.172353+08:00,ERROR,rolling_file,Hello error!,
+/// 2024-08-11T22:44:57.172353+08:00,INFO,rolling_file,"Hello, world!",user=alice count=3
+/// ```
+///
+/// Fields are quoted as RFC 4180 describes: a field containing a comma, a double quote, or a line
+/// break is wrapped in double quotes, with the double quotes inside doubled. Rows end with the
+/// line break that the appender writes after each record.
+///
+/// By default, the columns are the timestamp, the level, the module path, the message, and the
+/// key-values. You can choose the columns and their order by setting the `columns` field with
+/// [`CsvColumn`] variants. The [`CsvColumn::Kvs`] column flattens all the key-values of a record
+/// into one field in the logfmt style, e.g., `user=alice count=3`, while a [`CsvColumn::Kv`]
+/// column holds the value of a single key, or is empty if the record doesn't have it.
+///
+/// Use [`CsvLayout::header`] to write the header row at the top of each file, e.g., with
+/// `RollingFileWriterBuilder::file_header`.
+///
+/// You can customize the timezone of the timestamp by setting the `tz` field with a [`TimeZone`]
+/// instance. Otherwise, the system timezone is used.
+#[derive(Debug, Clone)]
+pub struct CsvLayout {
+    pub columns: Vec<CsvColumn>,
+    pub tz: Option<TimeZone>,
+}
+
+/// A column of a CSV log row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CsvColumn {
+    /// The timestamp, e.g., `2024-08-11T22:44:57.172353+08:00`.
+    Timestamp,
+    /// The level, e.g., `INFO`.
+    Level,
+    /// The module path.
+    Module,
+    /// The message.
+    Message,
+    /// All the key-values in the logfmt style, e.g., `user=alice count=3`.
+    Kvs,
+    /// The value of the key-value with the given key.
+    Kv(String),
+}
+
+impl CsvColumn {
+    fn name(&self) -> &str {
+        match self {
+            CsvColumn::Timestamp => "timestamp",
+            CsvColumn::Level => "level",
+            CsvColumn::Module => "module",
+            CsvColumn::Message => "message",
+            CsvColumn::Kvs => "kvs",
+            CsvColumn::Kv(key) => key,
+        }
+    }
+}
+
+impl Default for CsvLayout {
+    fn default() -> Self {
+        CsvLayout {
+            columns: vec![
+                CsvColumn::Timestamp,
+                CsvColumn::Level,
+                CsvColumn::Module,
+                CsvColumn::Message,
+                CsvColumn::Kvs,
+            ],
+            tz: None,
+        }
+    }
+}
+
+impl CsvLayout {
+    /// The header row naming the columns, followed by a line break.
+    pub fn header(&self) -> String {
+        let mut header = String::new();
+        for (i, column) in self.columns.iter().enumerate() {
+            if i > 0 {
+                header.push(',');
+            }
+            header.push_str(&quote(column.name()));
+        }
+        header.push('\n');
+        header
+    }
+
+    pub(crate) fn format<F>(&self, record: &Record, now: &Zoned, f: &F) -> anyhow::Result<()>
+    where
+        F: Fn(Arguments) -> anyhow::Result<()>,
+    {
+        let mut row = String::new();
+        for (i, column) in self.columns.iter().enumerate() {
+            if i > 0 {
+                row.push(',');
+            }
+            let field: Cow<str> = match column {
+                CsvColumn::Timestamp => {
+                    let time = match self.tz.clone() {
+                        Some(tz) => now.with_time_zone(tz),
+                        None => now.clone(),
+                    };
+                    time.strftime("%Y-%m-%dT%H:%M:%S.%6f%:z").to_string().into()
+                }
+                CsvColumn::Level => record.level().as_str().into(),
+                CsvColumn::Module => record.module_path().unwrap_or_default().into(),
+                CsvColumn::Message => message(record.args()),
+                CsvColumn::Kvs => {
+                    let mut kvs = String::new();
+                    write!(kvs, "{}", KvDisplay::new(record.key_values()))?;
+                    kvs.trim_start().to_string().into()
+                }
+                CsvColumn::Kv(key) => match record.key_values().get(key.as_str().into()) {
+                    Some(value) => value.to_string().into(),
+                    None => "".into(),
+                },
+            };
+            row.push_str(&quote(&field));
+        }
+        f(format_args!("{row}"))
+    }
+}
+
+/// Quote a field if it contains a comma, a double quote, or a line break.
+fn quote(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\"")).into()
+    } else {
+        field.into()
+    }
+}
+
+impl From<CsvLayout> for Layout {
+    fn from(layout: CsvLayout) -> Self {
+        Layout::Csv(layout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::str::FromStr;
+
+    use log::Level;
+
+    use super::*;
+
+    fn format(layout: &CsvLayout, record: &Record) -> String {
+        let output = RefCell::new(String::new());
+        let now = Zoned::from_str("2024-08-11T22:44:57.172353+08:00[+08:00]").unwrap();
+        layout
+            .format(record, &now, &|args| {
+                *output.borrow_mut() = args.to_string();
+                Ok(())
+            })
+            .unwrap();
+        output.into_inner()
+    }
+
+    #[test]
+    fn test_default_columns() {
+        let layout = CsvLayout::default();
+        let kvs = [("user", "alice"), ("count", "3")];
+        let record = Record::builder()
+            .level(Level::Info)
+            .module_path(Some("app"))
+            .args(format_args!("hello"))
+            .key_values(&kvs)
+            .build();
+
+        assert_eq!(layout.header(), "timestamp,level,module,message,kvs\n");
+        assert_eq!(
+            format(&layout, &record),
+            "2024-08-11T22:44:57.172353+08:00,INFO,app,hello,user=alice count=3"
+        );
+    }
+
+    #[test]
+    fn test_quoting() {
+        let layout = CsvLayout {
+            columns: vec![CsvColumn::Message, CsvColumn::Level],
+            ..Default::default()
+        };
+        let quoted = |message: &str| {
+            format(
+                &layout,
+                &Record::builder()
+                    .level(Level::Warn)
+                    .args(format_args!("{message}"))
+                    .build(),
+            )
+        };
+
+        assert_eq!(quoted("plain"), "plain,WARN");
+        assert_eq!(quoted("a, b"), "\"a, b\",WARN");
+        assert_eq!(quoted("say \"hi\""), "\"say \"\"hi\"\"\",WARN");
+        assert_eq!(quoted("two\nlines"), "\"two\nlines\",WARN");
+    }
+
+    #[test]
+    fn test_kv_columns() {
+        let layout = CsvLayout {
+            columns: vec![
+                CsvColumn::Kv("request_id".to_string()),
+                CsvColumn::Message,
+                CsvColumn::Kv("missing".to_string()),
+            ],
+            ..Default::default()
+        };
+        let kvs = [("request_id", "r1")];
+        let record = Record::builder()
+            .args(format_args!("done"))
+            .key_values(&kvs)
+            .build();
+
+        assert_eq!(layout.header(), "request_id,message,missing\n");
+        assert_eq!(format(&layout, &record), "r1,done,");
+    }
+}
@@ -12,16 +12,27 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
 use std::fmt::Arguments;
+use std::sync::Arc;
 
 use jiff::tz::TimeZone;
 use jiff::Zoned;
+use log::Level;
 use log::Record;
 use serde::Serialize;
 use serde_json::Map;
 use serde_json::Value;
 
+use crate::layout::backtrace;
+use crate::layout::column;
+use crate::layout::kv::as_f64;
+use crate::layout::message;
 use crate::layout::Layout;
+use crate::layout::WithoutKey;
+use crate::layout::COLUMN_KEY;
 
 /// A layout that formats log record as JSON lines.
 ///
@@ -35,15 +46,128 @@ use crate::layout::Layout;
 /// {"timestamp":"2024-08-11T22:44:57.172353+08:00","level":"TRACE","module_path":"rolling_file","file":"examples/rolling_file.rs","line":55,"message":"Hello trace!","kvs":{}}
 /// ```
 ///
+/// The `file` and `line` fields are omitted if the record has no location information, and the
+/// `message` field is omitted if the message is empty. If the record has a `column` key-value,
+/// e.g., attached by a macro, along with its location, it's promoted to a `column` field next to
+/// the `line` field.
+///
 /// You can customize the timezone of the timestamp by setting the `tz` field with a [`TimeZone`]
 /// instance. Otherwise, the system timezone is used.
+///
+/// You can rename individual levels in the `level` field, e.g., `Warn` to `CAUTION`, by setting
+/// the `level_labels` field.
+///
+/// You can set the `pretty` field to render each record as indented multi-line JSON, which is
+/// easier to read during local development. Records are rendered on a single line by default.
+///
+/// The `timestamp` field is an RFC 3339 string by default. You can render it as a number of
+/// milliseconds or seconds since the Unix epoch by setting the `timestamp_format` field. For
+/// systems counting from another epoch, set the `epoch_base` field to render the number of
+/// milliseconds or seconds since that instant instead, which is negative for earlier records.
+///
+/// Key-values are rendered as strings, independent of the locale. You can set the number of digits
+/// after the decimal point of float values by setting the `float_precision` field. You can cap the
+/// number of key-values by setting the `max_kvs` field; the omitted ones are counted in a `…` key,
+/// e.g., `"…":"+97 more"`.
+///
+/// Key-values are nested under the `kvs` field, apart from the standard fields. You can nest them
+/// under another field, e.g., `fields`, by setting the `kvs_key` field. You can promote the
+/// key-values of some keys to top-level fields, e.g., to index them in a search backend, by
+/// setting the `indexed_keys` field; the key-values of the other keys stay nested. A key with the
+/// name of a standard field or the key-values field isn't promoted.
+///
+/// You can add a `backtrace` field to records at or above a level, e.g., [`log::Level::Error`], by
+/// setting the `capture_backtrace_at` field. Backtraces are only captured if they're enabled by
+/// the `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE` environment variables, since capturing is costly.
+///
+/// You can add a constant `schema_version` field to every record, e.g., `"2"`, so that consumers
+/// can tell apart the records of different schema versions, by setting the `schema_version` field.
+///
+/// You can add computed top-level fields, e.g., to promote a correlation id out of the key-values,
+/// by setting the `fields` field with a [`FieldsHook`].
 #[derive(Default, Debug, Clone)]
 pub struct JsonLayout {
     pub tz: Option<TimeZone>,
+    pub level_labels: HashMap<Level, String>,
+    pub timestamp_format: TimestampFormat,
+    pub epoch_base: Option<jiff::Timestamp>,
+    pub pretty: bool,
+    pub kvs_key: Option<String>,
+    pub indexed_keys: Vec<String>,
+    pub float_precision: Option<usize>,
+    pub max_kvs: Option<usize>,
+    pub fields: Option<FieldsHook>,
+    pub capture_backtrace_at: Option<Level>,
+    pub schema_version: Option<String>,
+}
+
+/// A hook that computes extra top-level fields of a JSON log line from the record.
+///
+/// The fields are merged after the standard fields, and a field with the name of a standard field
+/// or the key-values field is ignored, so that the standard fields are never overwritten. If the
+/// hook returns a field name more than once, the last value wins.
+///
+/// ```rust
+/// use logforth::layout::FieldsHook;
+/// use logforth::layout::JsonLayout;
+///
+/// let layout = JsonLayout {
+///     fields: Some(FieldsHook::new(|record| {
+///         let trace_id = record.key_values().get("trace_id".into());
+///         let trace_id = trace_id.map(|value| value.to_string());
+///         trace_id
+///             .map(|value| vec![("trace_id".to_string(), value.into())])
+///             .unwrap_or_default()
+///     })),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone)]
+pub struct FieldsHook(Arc<FieldsFn>);
+
+type FieldsFn = dyn Fn(&Record) -> Vec<(String, Value)> + Send + Sync + 'static;
+
+impl FieldsHook {
+    pub fn new(hook: impl Fn(&Record) -> Vec<(String, Value)> + Send + Sync + 'static) -> Self {
+        FieldsHook(Arc::new(hook))
+    }
+}
+
+impl fmt::Debug for FieldsHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FieldsHook {{ ... }}")
+    }
+}
+
+const STANDARD_FIELDS: [&str; 9] = [
+    "schema_version",
+    "timestamp",
+    "level",
+    "module_path",
+    "file",
+    "line",
+    "column",
+    "message",
+    "backtrace",
+];
+
+/// How to serialize the `timestamp` field of a JSON log line.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampFormat {
+    /// An RFC 3339 string, e.g., `"2024-08-11T22:44:57.172051+08:00"`.
+    #[default]
+    Rfc3339,
+    /// An integer number of milliseconds since the Unix epoch, e.g., `1723387497172`.
+    EpochMillis,
+    /// An integer number of seconds since the Unix epoch, e.g., `1723387497`.
+    EpochSeconds,
 }
 
 struct KvCollector<'a> {
     kvs: &'a mut Map<String, Value>,
+    float_precision: Option<usize>,
+    max_kvs: Option<usize>,
+    omitted: usize,
 }
 
 impl<'a, 'kvs> log::kv::Visitor<'kvs> for KvCollector<'a> {
@@ -52,8 +176,18 @@ impl<'a, 'kvs> log::kv::Visitor<'kvs> for KvCollector<'a> {
         key: log::kv::Key<'kvs>,
         value: log::kv::Value<'kvs>,
     ) -> Result<(), log::kv::Error> {
+        if self
+            .max_kvs
+            .is_some_and(|max_kvs| self.kvs.len() >= max_kvs)
+        {
+            self.omitted += 1;
+            return Ok(());
+        }
         let k = key.to_string();
-        let v = value.to_string();
+        let v = match (self.float_precision, as_f64(&value)) {
+            (Some(precision), Some(value)) => format!("{value:.precision$}"),
+            _ => value.to_string(),
+        };
         self.kvs.insert(k, v.into());
         Ok(())
     }
@@ -61,54 +195,138 @@ impl<'a, 'kvs> log::kv::Visitor<'kvs> for KvCollector<'a> {
 
 #[derive(Debug, Clone, Serialize)]
 struct RecordLine<'a> {
-    #[serde(serialize_with = "serialize_time_zone")]
-    timestamp: Zoned,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    schema_version: Option<&'a str>,
+    timestamp: Timestamp,
     level: &'a str,
     module_path: &'a str,
-    file: &'a str,
-    line: u32,
-    #[serde(serialize_with = "serialize_args")]
-    message: &'a Arguments<'a>,
-    kvs: Map<String, Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    column: Option<u32>,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    message: Cow<'a, str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    backtrace: Option<String>,
+    // the key-values nested under the configured key, and the fields of the hook
+    #[serde(flatten)]
+    extra: Map<String, Value>,
 }
 
-fn serialize_time_zone<S>(timestamp: &Zoned, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: serde::Serializer,
-{
-    serializer.collect_str(&timestamp.strftime("%Y-%m-%dT%H:%M:%S.%6f%:z"))
+#[derive(Debug, Clone)]
+struct Timestamp {
+    time: Zoned,
+    format: TimestampFormat,
+    epoch_base: Option<jiff::Timestamp>,
 }
 
-fn serialize_args<S>(args: &Arguments, serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: serde::Serializer,
-{
-    serializer.collect_str(args)
+impl Timestamp {
+    /// The number of nanoseconds since the epoch base, or the Unix epoch.
+    fn since_epoch(&self) -> i128 {
+        let base = self.epoch_base.map_or(0, |base| base.as_nanosecond());
+        self.time.timestamp().as_nanosecond() - base
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self.format {
+            TimestampFormat::Rfc3339 => {
+                serializer.collect_str(&self.time.strftime("%Y-%m-%dT%H:%M:%S.%6f%:z"))
+            }
+            TimestampFormat::EpochMillis => {
+                serializer.serialize_i64(self.since_epoch().div_euclid(1_000_000) as i64)
+            }
+            TimestampFormat::EpochSeconds => {
+                serializer.serialize_i64(self.since_epoch().div_euclid(1_000_000_000) as i64)
+            }
+        }
+    }
 }
 
 impl JsonLayout {
-    pub(crate) fn format<F>(&self, record: &Record, f: &F) -> anyhow::Result<()>
+    pub(crate) fn format<F>(&self, record: &Record, now: &Zoned, f: &F) -> anyhow::Result<()>
     where
         F: Fn(Arguments) -> anyhow::Result<()>,
     {
+        // promote the column along with the location, out of the key-values
+        let column = record
+            .file()
+            .and(record.line())
+            .and_then(|_| column(record));
+        let without_column = WithoutKey {
+            source: record.key_values(),
+            keys: &[COLUMN_KEY],
+        };
+        let source: &dyn log::kv::Source = match column {
+            Some(_) => &without_column,
+            None => record.key_values(),
+        };
+
         let mut kvs = Map::new();
-        let mut visitor = KvCollector { kvs: &mut kvs };
-        record.key_values().visit(&mut visitor)?;
+        let mut visitor = KvCollector {
+            kvs: &mut kvs,
+            float_precision: self.float_precision,
+            max_kvs: self.max_kvs,
+            omitted: 0,
+        };
+        source.visit(&mut visitor)?;
+        if visitor.omitted > 0 {
+            let marker = format!("+{} more", visitor.omitted);
+            kvs.insert("…".to_string(), marker.into());
+        }
+
+        let kvs_key = self.kvs_key.as_deref().unwrap_or("kvs");
+        let mut extra = Map::new();
+        for key in &self.indexed_keys {
+            if key != kvs_key && !STANDARD_FIELDS.contains(&key.as_str()) {
+                if let Some(value) = kvs.remove(key) {
+                    extra.insert(key.clone(), value);
+                }
+            }
+        }
+        if let Some(fields) = &self.fields {
+            for (name, value) in (fields.0)(record) {
+                if name != kvs_key && !STANDARD_FIELDS.contains(&name.as_str()) {
+                    extra.insert(name, value);
+                }
+            }
+        }
+        extra.insert(kvs_key.to_string(), Value::Object(kvs));
 
         let record_line = RecordLine {
-            timestamp: match self.tz.clone() {
-                Some(tz) => Zoned::now().with_time_zone(tz),
-                None => Zoned::now(),
+            schema_version: self.schema_version.as_deref(),
+            timestamp: Timestamp {
+                time: match self.tz.clone() {
+                    Some(tz) => now.with_time_zone(tz),
+                    None => now.clone(),
+                },
+                format: self.timestamp_format,
+                epoch_base: self.epoch_base,
             },
-            level: record.level().as_str(),
+            level: self
+                .level_labels
+                .get(&record.level())
+                .map_or(record.level().as_str(), String::as_str),
             module_path: record.module_path().unwrap_or_default(),
-            file: record.file().unwrap_or_default(),
-            line: record.line().unwrap_or_default(),
-            message: record.args(),
-            kvs,
+            file: record.file(),
+            line: record.line(),
+            column,
+            message: message(record.args()),
+            backtrace: backtrace(record, self.capture_backtrace_at).map(|b| b.to_string()),
+            extra,
         };
 
-        let text = serde_json::to_string(&record_line)?;
+        let text = if self.pretty {
+            serde_json::to_string_pretty(&record_line)?
+        } else {
+            serde_json::to_string(&record_line)?
+        };
         f(format_args!("{text}"))
     }
 }
@@ -118,3 +336,348 @@ impl From<JsonLayout> for Layout {
         Layout::Json(layout)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::backtrace::Backtrace;
+    use std::backtrace::BacktraceStatus;
+    use std::cell::RefCell;
+    use std::str::FromStr;
+
+    use super::*;
+
+    pub(crate) fn format(layout: &JsonLayout, record: &Record) -> Value {
+        serde_json::from_str(&format_text(layout, record)).unwrap()
+    }
+
+    fn format_text(layout: &JsonLayout, record: &Record) -> String {
+        format_text_at(layout, record, &Zoned::now())
+    }
+
+    fn format_text_at(layout: &JsonLayout, record: &Record, now: &Zoned) -> String {
+        let output = RefCell::new(String::new());
+        layout
+            .format(record, now, &|args| {
+                *output.borrow_mut() = args.to_string();
+                Ok(())
+            })
+            .unwrap();
+        output.into_inner()
+    }
+
+    #[test]
+    fn test_absent_location() {
+        let record = Record::builder().args(format_args!("hello")).build();
+        let line = format(&JsonLayout::default(), &record);
+        assert!(line.get("file").is_none(), "{line}");
+        assert!(line.get("line").is_none(), "{line}");
+
+        let record = Record::builder()
+            .file(Some("src/main.rs"))
+            .line(Some(42))
+            .args(format_args!("hello"))
+            .build();
+        let line = format(&JsonLayout::default(), &record);
+        assert_eq!(line["file"], "src/main.rs");
+        assert_eq!(line["line"], 42);
+    }
+
+    #[test]
+    fn test_empty_message() {
+        let kvs = [("event_type", log::kv::Value::from("login"))];
+        let record = Record::builder()
+            .args(format_args!(""))
+            .key_values(&kvs)
+            .build();
+        let line = format(&JsonLayout::default(), &record);
+        assert!(line.get("message").is_none(), "{line}");
+        assert_eq!(line["kvs"]["event_type"], "login");
+    }
+
+    #[test]
+    fn test_pretty() {
+        let record = Record::builder().args(format_args!("hello")).build();
+
+        let output = format_text(&JsonLayout::default(), &record);
+        assert!(!output.contains('\n'), "{output}");
+
+        let layout = JsonLayout {
+            pretty: true,
+            ..Default::default()
+        };
+        let output = format_text(&layout, &record);
+        assert!(output.contains("\n  \"message\": \"hello\""), "{output}");
+        assert_eq!(
+            serde_json::from_str::<Value>(&output).unwrap()["level"],
+            "INFO"
+        );
+    }
+
+    #[test]
+    fn test_kvs_key() {
+        let kvs = [("user", log::kv::Value::from("alice"))];
+        let record = Record::builder()
+            .args(format_args!("hello"))
+            .key_values(&kvs)
+            .build();
+
+        let line = format(&JsonLayout::default(), &record);
+        assert_eq!(line["kvs"]["user"], "alice");
+
+        let layout = JsonLayout {
+            kvs_key: Some("fields".to_string()),
+            ..Default::default()
+        };
+        let line = format(&layout, &record);
+        assert_eq!(line["fields"]["user"], "alice");
+        assert!(line.get("kvs").is_none(), "{line}");
+        assert!(line.get("user").is_none(), "{line}");
+        assert_eq!(line["message"], "hello");
+    }
+
+    #[test]
+    fn test_indexed_keys() {
+        let kvs = [
+            ("user", "alice"),
+            ("request_id", "42"),
+            ("level", "shadowed"),
+            ("path", "/"),
+        ];
+        let record = Record::builder()
+            .args(format_args!("hello"))
+            .key_values(&kvs)
+            .build();
+        let layout = JsonLayout {
+            kvs_key: Some("fields".to_string()),
+            indexed_keys: vec![
+                "request_id".to_string(),
+                "user".to_string(),
+                "level".to_string(),
+                "absent".to_string(),
+            ],
+            ..Default::default()
+        };
+        let line = format(&layout, &record);
+        assert_eq!(line["user"], "alice");
+        assert_eq!(line["request_id"], "42");
+        assert!(line.get("absent").is_none(), "{line}");
+        // standard fields are never overwritten
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(
+            line["fields"],
+            serde_json::json!({"level": "shadowed", "path": "/"})
+        );
+    }
+
+    #[test]
+    fn test_column() {
+        let kvs = [("column", 7)];
+        let record = Record::builder()
+            .file(Some("src/main.rs"))
+            .line(Some(42))
+            .args(format_args!("hello"))
+            .key_values(&kvs)
+            .build();
+        let line = format(&JsonLayout::default(), &record);
+        assert_eq!(line["line"], 42);
+        assert_eq!(line["column"], 7);
+        assert_eq!(line["kvs"], serde_json::json!({}));
+
+        let record = Record::builder()
+            .args(format_args!("hello"))
+            .key_values(&kvs)
+            .build();
+        let line = format(&JsonLayout::default(), &record);
+        assert!(line.get("column").is_none(), "{line}");
+        assert_eq!(line["kvs"]["column"], "7");
+    }
+
+    #[test]
+    fn test_schema_version() {
+        let kvs = [("schema_version", "forged")];
+        let record = Record::builder()
+            .args(format_args!("hello"))
+            .key_values(&kvs)
+            .build();
+        let line = format(&JsonLayout::default(), &record);
+        assert!(line.get("schema_version").is_none(), "{line}");
+
+        let layout = JsonLayout {
+            schema_version: Some("2".to_string()),
+            indexed_keys: vec!["schema_version".to_string()],
+            ..Default::default()
+        };
+        let text = format_text(&layout, &record);
+        assert!(text.starts_with(r#"{"schema_version":"2","#), "{text}");
+        let line = format(&layout, &record);
+        assert_eq!(line["kvs"]["schema_version"], "forged");
+    }
+
+    #[test]
+    fn test_timestamp_format() {
+        let now = Zoned::from_str("2024-08-11T22:44:57.172051+08[+08]").unwrap();
+        let record = Record::builder().args(format_args!("hello")).build();
+        let timestamp = |timestamp_format| {
+            let layout = JsonLayout {
+                timestamp_format,
+                ..Default::default()
+            };
+            let line = format_text_at(&layout, &record, &now);
+            serde_json::from_str::<Value>(&line).unwrap()["timestamp"].clone()
+        };
+
+        assert_eq!(
+            timestamp(TimestampFormat::Rfc3339),
+            "2024-08-11T22:44:57.172051+08:00"
+        );
+        assert_eq!(timestamp(TimestampFormat::EpochMillis), 1723387497172i64);
+        assert_eq!(timestamp(TimestampFormat::EpochSeconds), 1723387497i64);
+    }
+
+    #[test]
+    fn test_epoch_base() {
+        let now = Zoned::from_str("2024-08-11T22:44:57.172051+08[+08]").unwrap();
+        let base = jiff::Timestamp::from_str("2000-01-01T00:00:00Z").unwrap();
+        let record = Record::builder().args(format_args!("hello")).build();
+        let timestamp = |timestamp_format| {
+            let layout = JsonLayout {
+                timestamp_format,
+                epoch_base: Some(base),
+                ..Default::default()
+            };
+            let line = format_text_at(&layout, &record, &now);
+            serde_json::from_str::<Value>(&line).unwrap()["timestamp"].clone()
+        };
+
+        let since_base = now.timestamp().as_millisecond() - base.as_millisecond();
+        assert_eq!(timestamp(TimestampFormat::EpochMillis), since_base);
+        assert_eq!(timestamp(TimestampFormat::EpochSeconds), since_base / 1000);
+        // RFC 3339 timestamps are absolute
+        assert_eq!(
+            timestamp(TimestampFormat::Rfc3339),
+            "2024-08-11T22:44:57.172051+08:00"
+        );
+    }
+
+    #[test]
+    fn test_float_precision() {
+        let kvs = [
+            ("ratio", log::kv::Value::from(1234567.891)),
+            ("count", log::kv::Value::from(1234567)),
+        ];
+        let record = Record::builder()
+            .args(format_args!("hello"))
+            .key_values(&kvs)
+            .build();
+        let layout = JsonLayout {
+            float_precision: Some(1),
+            ..Default::default()
+        };
+
+        let line = format(&layout, &record);
+        assert_eq!(line["kvs"]["ratio"], "1234567.9");
+        assert_eq!(line["kvs"]["count"], "1234567");
+    }
+
+    #[test]
+    fn test_fields_hook() {
+        let kvs = [
+            ("correlation_id", log::kv::Value::from("abc")),
+            ("user", log::kv::Value::from("alice")),
+        ];
+        let record = Record::builder()
+            .args(format_args!("hello"))
+            .key_values(&kvs)
+            .build();
+        let layout = JsonLayout {
+            fields: Some(FieldsHook::new(|record| {
+                let correlation_id = record.key_values().get("correlation_id".into());
+                vec![
+                    (
+                        "correlation_id".to_string(),
+                        correlation_id.map(|v| v.to_string()).into(),
+                    ),
+                    ("message".to_string(), "overwritten".into()),
+                    ("kvs".to_string(), "overwritten".into()),
+                ]
+            })),
+            ..Default::default()
+        };
+
+        let line = format(&layout, &record);
+        assert_eq!(line["correlation_id"], "abc");
+        assert_eq!(line["message"], "hello");
+        assert_eq!(line["kvs"]["user"], "alice");
+    }
+
+    #[test]
+    fn test_capture_backtrace() {
+        let layout = JsonLayout {
+            capture_backtrace_at: Some(Level::Warn),
+            ..Default::default()
+        };
+
+        // captured as enabled by the environment variables of the test process
+        let enabled = Backtrace::capture().status() == BacktraceStatus::Captured;
+        let line = format(
+            &layout,
+            &Record::builder()
+                .level(Level::Error)
+                .args(format_args!("failed"))
+                .build(),
+        );
+        assert_eq!(
+            line["backtrace"].as_str().is_some_and(|b| !b.is_empty()),
+            enabled
+        );
+
+        let line = format(
+            &layout,
+            &Record::builder()
+                .level(Level::Info)
+                .args(format_args!("succeeded"))
+                .build(),
+        );
+        assert!(line.get("backtrace").is_none());
+    }
+
+    #[test]
+    fn test_level_labels() {
+        let layout = JsonLayout {
+            level_labels: HashMap::from([(Level::Warn, "CAUTION".to_string())]),
+            ..Default::default()
+        };
+        let line = |level| {
+            format(
+                &layout,
+                &Record::builder()
+                    .level(level)
+                    .args(format_args!("hello"))
+                    .build(),
+            )
+        };
+
+        assert_eq!(line(Level::Warn)["level"], "CAUTION");
+        assert_eq!(line(Level::Info)["level"], "INFO");
+    }
+
+    #[test]
+    fn test_max_kvs() {
+        let kvs = [("a", 1), ("b", 2), ("c", 3), ("d", 4)];
+        let layout = JsonLayout {
+            max_kvs: Some(2),
+            ..Default::default()
+        };
+        let line = format(
+            &layout,
+            &Record::builder()
+                .args(format_args!("hello"))
+                .key_values(&kvs)
+                .build(),
+        );
+        assert_eq!(
+            line["kvs"],
+            serde_json::json!({"a": "1", "b": "2", "…": "+2 more"})
+        );
+    }
+}
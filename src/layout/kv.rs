@@ -12,36 +12,543 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-/// A helper struct to format log's key-value pairs.
+use std::fmt;
+
+use colored::Color;
+use log::kv::Error;
+use log::kv::Key;
+use log::kv::Value;
+use log::kv::VisitValue;
+
+/// How to render key-value pairs whose value is null.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum KvNullPolicy {
+    /// Render the key with an empty value, e.g., `key=`.
+    #[default]
+    Empty,
+    /// Omit the key-value pair.
+    Omit,
+}
+
+/// When to quote string values in the logfmt style.
+#[derive(Debug, Default, Clone, Copy)]
+pub enum QuotePolicy {
+    /// Quote values that are empty or contain whitespace, `=`, `"` or control characters.
+    #[default]
+    Minimal,
+    /// Quote all values.
+    Always,
+    /// Quote values for which the function returns `true`.
+    ///
+    /// Values that contain `"` or control characters are quoted and escaped regardless, so that
+    /// they can't break the line apart.
+    Custom(fn(&str) -> bool),
+}
+
+impl QuotePolicy {
+    fn quotes(self, value: &str) -> bool {
+        match self {
+            QuotePolicy::Minimal => needs_quoting(value),
+            QuotePolicy::Always => true,
+            QuotePolicy::Custom(quotes) => quotes(value) || must_quote(value),
+        }
+    }
+}
+
+/// A helper struct to format log's key-value pairs in the logfmt style.
+///
+/// Each pair is rendered as ` key=value`. Numbers and booleans are rendered bare, and strings are
+/// quoted only if they are empty or contain whitespace, `=`, `"` or control characters, unless
+/// another [`QuotePolicy`] is set. Inside quotes, `"`, the escape character, and control characters
+/// are escaped with the escape character, which defaults to `\`.
+///
+/// Numbers are rendered independent of the locale, without digit grouping and with `.` as the
+/// decimal separator. Floats are rendered with the shortest representation that round-trips,
+/// unless a fixed precision is set.
+///
+/// With a maximum set, at most that many pairs are rendered in the order of the record, followed
+/// by a ` …(+N more)` marker for the rest.
+///
+/// With a color set, keys are rendered dimmed and values in the color, using ANSI escape codes
+/// regardless of whether the output is a terminal.
 pub struct KvDisplay<'kvs> {
     kv: &'kvs dyn log::kv::Source,
+    null_policy: KvNullPolicy,
+    float_precision: Option<usize>,
+    color: Option<Color>,
+    max_kvs: Option<usize>,
+    quote_policy: QuotePolicy,
+    escape: char,
 }
 
 impl<'kvs> KvDisplay<'kvs> {
     pub fn new(kv: &'kvs dyn log::kv::Source) -> Self {
-        Self { kv }
+        Self {
+            kv,
+            quote_policy: QuotePolicy::default(),
+            escape: '\\',
+            null_policy: KvNullPolicy::default(),
+            float_precision: None,
+            color: None,
+            max_kvs: None,
+        }
+    }
+
+    /// Sets when to quote string values.
+    pub fn quote_policy(mut self, quote_policy: QuotePolicy) -> Self {
+        self.quote_policy = quote_policy;
+        self
+    }
+
+    /// Sets the character escaping special characters inside quoted values.
+    pub fn escape(mut self, escape: char) -> Self {
+        self.escape = escape;
+        self
+    }
+
+    /// Sets how to render key-value pairs whose value is null.
+    pub fn null_policy(mut self, null_policy: KvNullPolicy) -> Self {
+        self.null_policy = null_policy;
+        self
+    }
+
+    /// Sets the number of digits after the decimal point of float values.
+    pub fn float_precision(mut self, float_precision: Option<usize>) -> Self {
+        self.float_precision = float_precision;
+        self
+    }
+
+    /// Sets the maximum number of key-value pairs to render.
+    pub fn max_kvs(mut self, max_kvs: Option<usize>) -> Self {
+        self.max_kvs = max_kvs;
+        self
+    }
+
+    /// Sets the color of the key-value pairs.
+    pub fn color(mut self, color: Option<Color>) -> Self {
+        self.color = color;
+        self
     }
 }
 
-impl std::fmt::Display for KvDisplay<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let mut visitor = KvWriter { writer: f };
+impl fmt::Display for KvDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut visitor = KvWriter {
+            writer: f,
+            null_policy: self.null_policy,
+            float_precision: self.float_precision,
+            color: self.color,
+            quote_policy: self.quote_policy,
+            escape: self.escape,
+            remaining: self.max_kvs,
+            omitted: 0,
+        };
         self.kv.visit(&mut visitor).ok();
+        if visitor.omitted > 0 {
+            write!(visitor.writer, " …(+{} more)", visitor.omitted)?;
+        }
         Ok(())
     }
 }
 
 struct KvWriter<'a, 'kvs> {
-    writer: &'kvs mut std::fmt::Formatter<'a>,
+    writer: &'kvs mut fmt::Formatter<'a>,
+    null_policy: KvNullPolicy,
+    float_precision: Option<usize>,
+    color: Option<Color>,
+    quote_policy: QuotePolicy,
+    escape: char,
+    // how many more pairs may be rendered, and how many are omitted past the maximum
+    remaining: Option<usize>,
+    omitted: usize,
 }
 
 impl<'a, 'kvs> log::kv::Visitor<'kvs> for KvWriter<'a, 'kvs> {
-    fn visit_pair(
-        &mut self,
-        key: log::kv::Key<'kvs>,
-        value: log::kv::Value<'kvs>,
-    ) -> Result<(), log::kv::Error> {
-        write!(self.writer, " {key}={value}")?;
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), Error> {
+        if self.remaining == Some(0) {
+            self.omitted += 1;
+            return Ok(());
+        }
+        let mut writer = ValueWriter {
+            writer: self.writer,
+            key,
+            null_policy: self.null_policy,
+            float_precision: self.float_precision,
+            color: self.color,
+            quote_policy: self.quote_policy,
+            escape: self.escape,
+            written: false,
+        };
+        value.visit(&mut writer)?;
+        if writer.written {
+            self.remaining = self.remaining.map(|remaining| remaining - 1);
+        }
         Ok(())
     }
 }
+
+struct ValueWriter<'a, 'b, 'kvs> {
+    writer: &'b mut fmt::Formatter<'a>,
+    key: Key<'kvs>,
+    null_policy: KvNullPolicy,
+    float_precision: Option<usize>,
+    color: Option<Color>,
+    quote_policy: QuotePolicy,
+    escape: char,
+    written: bool,
+}
+
+impl ValueWriter<'_, '_, '_> {
+    fn write_bare(&mut self, value: impl fmt::Display) -> Result<(), Error> {
+        self.written = true;
+        match &self.color {
+            Some(color) => {
+                let color = color.to_fg_str();
+                write!(
+                    self.writer,
+                    " \x1b[2;{color}m{}\x1b[0m=\x1b[{color}m{value}\x1b[0m",
+                    self.key
+                )?;
+            }
+            None => write!(self.writer, " {}={value}", self.key)?,
+        }
+        Ok(())
+    }
+
+    fn write_text(&mut self, value: &str) -> Result<(), Error> {
+        if self.quote_policy.quotes(value) {
+            let escape = self.escape;
+            self.write_bare(Quoted { value, escape })
+        } else {
+            self.write_bare(value)
+        }
+    }
+}
+
+/// A string value in double quotes, with the special characters escaped.
+struct Quoted<'a> {
+    value: &'a str,
+    escape: char,
+}
+
+impl fmt::Display for Quoted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let escape = self.escape;
+        write!(f, "\"")?;
+        for c in self.value.chars() {
+            match c {
+                '"' => write!(f, "{escape}\"")?,
+                '\n' => write!(f, "{escape}n")?,
+                '\r' => write!(f, "{escape}r")?,
+                '\t' => write!(f, "{escape}t")?,
+                c if c == escape => write!(f, "{escape}{escape}")?,
+                c if c.is_control() => write!(f, "{escape}u{{{:x}}}", c as u32)?,
+                c => write!(f, "{c}")?,
+            }
+        }
+        write!(f, "\"")
+    }
+}
+
+/// Whether the value can't be rendered bare without breaking the line apart.
+fn must_quote(value: &str) -> bool {
+    value.chars().any(|c| c.is_control() || c == '"')
+}
+
+fn needs_quoting(value: &str) -> bool {
+    value.is_empty()
+        || value
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || c == '=' || c == '"')
+}
+
+impl<'kvs> VisitValue<'kvs> for ValueWriter<'_, '_, 'kvs> {
+    fn visit_any(&mut self, value: Value) -> Result<(), Error> {
+        self.write_text(&value.to_string())
+    }
+
+    fn visit_null(&mut self) -> Result<(), Error> {
+        match self.null_policy {
+            KvNullPolicy::Empty => self.write_bare(""),
+            KvNullPolicy::Omit => Ok(()),
+        }
+    }
+
+    fn visit_u64(&mut self, value: u64) -> Result<(), Error> {
+        self.write_bare(value)
+    }
+
+    fn visit_i64(&mut self, value: i64) -> Result<(), Error> {
+        self.write_bare(value)
+    }
+
+    fn visit_u128(&mut self, value: u128) -> Result<(), Error> {
+        self.write_bare(value)
+    }
+
+    fn visit_i128(&mut self, value: i128) -> Result<(), Error> {
+        self.write_bare(value)
+    }
+
+    fn visit_f64(&mut self, value: f64) -> Result<(), Error> {
+        match self.float_precision {
+            Some(precision) => self.write_bare(format_args!("{value:.precision$}")),
+            None => self.write_bare(value),
+        }
+    }
+
+    fn visit_bool(&mut self, value: bool) -> Result<(), Error> {
+        self.write_bare(value)
+    }
+
+    fn visit_str(&mut self, value: &str) -> Result<(), Error> {
+        self.write_text(value)
+    }
+
+    fn visit_char(&mut self, value: char) -> Result<(), Error> {
+        self.write_text(value.encode_utf8(&mut [0; 4]))
+    }
+}
+
+/// A helper struct to format log's key-value pairs in the query-string style, e.g.,
+/// `?key=value&key2=value2`, for tools that parse such trailers.
+///
+/// Keys and values are percent-encoded as UTF-8, except for the unreserved characters of
+/// RFC 3986, i.e., ASCII letters, digits, `-`, `.`, `_` and `~`. Null values are rendered empty.
+/// Nothing is rendered if there are no key-value pairs.
+pub struct QueryStringKvDisplay<'kvs> {
+    kv: &'kvs dyn log::kv::Source,
+}
+
+impl<'kvs> QueryStringKvDisplay<'kvs> {
+    pub fn new(kv: &'kvs dyn log::kv::Source) -> Self {
+        Self { kv }
+    }
+}
+
+impl fmt::Display for QueryStringKvDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut visitor = QueryStringWriter {
+            writer: f,
+            first: true,
+        };
+        self.kv.visit(&mut visitor).ok();
+        Ok(())
+    }
+}
+
+struct QueryStringWriter<'a, 'b> {
+    writer: &'b mut fmt::Formatter<'a>,
+    first: bool,
+}
+
+impl<'kvs> log::kv::Visitor<'kvs> for QueryStringWriter<'_, '_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), Error> {
+        struct Text(String);
+
+        impl VisitValue<'_> for Text {
+            fn visit_any(&mut self, value: Value) -> Result<(), Error> {
+                self.0 = value.to_string();
+                Ok(())
+            }
+
+            fn visit_null(&mut self) -> Result<(), Error> {
+                Ok(())
+            }
+        }
+
+        let mut text = Text(String::new());
+        value.visit(&mut text)?;
+
+        let separator = if self.first { '?' } else { '&' };
+        self.first = false;
+        write!(
+            self.writer,
+            "{separator}{}={}",
+            PercentEncoded(key.as_str()),
+            PercentEncoded(&text.0)
+        )?;
+        Ok(())
+    }
+}
+
+struct PercentEncoded<'a>(&'a str);
+
+impl fmt::Display for PercentEncoded<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in self.0.bytes() {
+            match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                    write!(f, "{}", byte as char)?
+                }
+                _ => write!(f, "%{byte:02X}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The value if it's a float, as opposed to any value that can be converted to a float.
+#[cfg(feature = "json")]
+pub(crate) fn as_f64(value: &Value) -> Option<f64> {
+    struct FloatVisitor(Option<f64>);
+
+    impl VisitValue<'_> for FloatVisitor {
+        fn visit_any(&mut self, _: Value) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn visit_f64(&mut self, value: f64) -> Result<(), Error> {
+            self.0 = Some(value);
+            Ok(())
+        }
+    }
+
+    let mut visitor = FloatVisitor(None);
+    value.visit(&mut visitor).ok()?;
+    visitor.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typed_values() {
+        let kvs = [
+            ("count", Value::from(3)),
+            ("ok", Value::from(true)),
+            ("name", Value::from("logforth")),
+            ("phrase", Value::from("hello world")),
+            ("null", Value::null()),
+        ];
+
+        assert_eq!(
+            KvDisplay::new(&kvs).to_string(),
+            r#" count=3 ok=true name=logforth phrase="hello world" null="#
+        );
+        assert_eq!(
+            KvDisplay::new(&kvs)
+                .null_policy(KvNullPolicy::Omit)
+                .to_string(),
+            r#" count=3 ok=true name=logforth phrase="hello world""#
+        );
+    }
+
+    #[test]
+    fn test_float_precision() {
+        let kvs = [
+            ("ratio", Value::from(1234567.891)),
+            ("count", Value::from(1234567)),
+        ];
+
+        assert_eq!(
+            KvDisplay::new(&kvs).to_string(),
+            " ratio=1234567.891 count=1234567"
+        );
+        assert_eq!(
+            KvDisplay::new(&kvs).float_precision(Some(2)).to_string(),
+            " ratio=1234567.89 count=1234567"
+        );
+    }
+
+    #[test]
+    fn test_color() {
+        let kvs = [("count", Value::from(3)), ("name", Value::from("a b"))];
+
+        assert_eq!(
+            KvDisplay::new(&kvs).color(Some(Color::Red)).to_string(),
+            " \x1b[2;31mcount\x1b[0m=\x1b[31m3\x1b[0m \x1b[2;31mname\x1b[0m=\x1b[31m\"a b\"\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_max_kvs() {
+        let kvs = (0..100).map(|i| (format!("k{i}"), i)).collect::<Vec<_>>();
+        let kvs = kvs
+            .iter()
+            .map(|(key, value)| (key.as_str(), Value::from(*value)))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            KvDisplay::new(&kvs).max_kvs(Some(3)).to_string(),
+            " k0=0 k1=1 k2=2 …(+97 more)"
+        );
+        assert_eq!(
+            KvDisplay::new(&kvs[..3].to_vec())
+                .max_kvs(Some(3))
+                .to_string(),
+            " k0=0 k1=1 k2=2"
+        );
+
+        // omitted null values don't count
+        let kvs = [
+            ("a", Value::null()),
+            ("b", Value::from(1)),
+            ("c", Value::from(2)),
+        ];
+        assert_eq!(
+            KvDisplay::new(&kvs)
+                .null_policy(KvNullPolicy::Omit)
+                .max_kvs(Some(1))
+                .to_string(),
+            " b=1 …(+1 more)"
+        );
+    }
+
+    #[test]
+    fn test_query_string() {
+        let kvs = [
+            ("count", Value::from(3)),
+            ("name", Value::from("a b&c=d")),
+            ("path?", Value::from("/tmp/日志")),
+            ("null", Value::null()),
+        ];
+
+        assert_eq!(
+            QueryStringKvDisplay::new(&kvs).to_string(),
+            "?count=3&name=a%20b%26c%3Dd&path%3F=%2Ftmp%2F%E6%97%A5%E5%BF%97&null="
+        );
+        assert_eq!(
+            QueryStringKvDisplay::new(&Vec::<(&str, Value)>::new()).to_string(),
+            ""
+        );
+    }
+
+    #[test]
+    fn test_quote_policy() {
+        let kvs = [
+            ("plain", Value::from("alice")),
+            ("special", Value::from("a \"b\"\\c\n")),
+            ("count", Value::from(3)),
+        ];
+
+        assert_eq!(
+            KvDisplay::new(&kvs).to_string(),
+            r#" plain=alice special="a \"b\"\\c\n" count=3"#
+        );
+        assert_eq!(
+            KvDisplay::new(&kvs)
+                .quote_policy(QuotePolicy::Always)
+                .to_string(),
+            r#" plain="alice" special="a \"b\"\\c\n" count=3"#
+        );
+        assert_eq!(
+            KvDisplay::new(&kvs)
+                .quote_policy(QuotePolicy::Custom(|value| value.contains('l')))
+                .to_string(),
+            r#" plain="alice" special="a \"b\"\\c\n" count=3"#
+        );
+        // quotes and control characters are escaped even if the policy doesn't quote
+        assert_eq!(
+            KvDisplay::new(&[("plain", "a b"), ("line", "x\ny")])
+                .quote_policy(QuotePolicy::Custom(|_| false))
+                .to_string(),
+            r#" plain=a b line="x\ny""#
+        );
+        assert_eq!(
+            KvDisplay::new(&kvs).escape('^').to_string(),
+            r#" plain=alice special="a ^"b^"\c^n" count=3"#
+        );
+    }
+}
@@ -14,33 +14,73 @@
 
 //! Describe how to format a log record.
 
+use std::backtrace::Backtrace;
+use std::backtrace::BacktraceStatus;
+use std::borrow::Cow;
+use std::fmt;
+use std::fmt::Arguments;
+
+pub use blackhole::BlackholeLayout;
+pub use chained::ChainedLayout;
+use colored::Color;
+pub use csv::CsvColumn;
+pub use csv::CsvLayout;
 pub use custom::CustomLayout;
 pub use identical::IdenticalLayout;
+use jiff::Zoned;
+#[cfg(feature = "json")]
+pub use json::FieldsHook;
 #[cfg(feature = "json")]
 pub use json::JsonLayout;
+#[cfg(feature = "json")]
+pub use json::TimestampFormat;
 pub use kv::KvDisplay;
+pub use kv::KvNullPolicy;
+pub use kv::QueryStringKvDisplay;
+pub use kv::QuotePolicy;
+use log::Level;
+pub use pretty::ColorMode;
+pub use pretty::PrettyLayout;
 pub use text::LevelColor;
+pub use text::LevelStyle;
+pub use text::ModuleSource;
 pub use text::TextLayout;
+pub use text::TextSeparators;
+pub use text::Theme;
+pub use text::TimeOffset;
+pub use text::TimeStyle;
 
+mod blackhole;
+mod chained;
+mod csv;
 mod custom;
 mod identical;
 #[cfg(feature = "json")]
 mod json;
 mod kv;
+mod pretty;
 mod text;
 
 /// A layout describes how to format a log record.
 #[derive(Debug)]
+// layouts are built once per dispatch, so boxing the large variants isn't worth it
+#[allow(clippy::large_enum_variant)]
 pub enum Layout {
     Identical(IdenticalLayout),
+    Blackhole(BlackholeLayout),
     Text(TextLayout),
     #[cfg(feature = "json")]
     Json(JsonLayout),
+    Csv(CsvLayout),
+    Pretty(PrettyLayout),
     Custom(CustomLayout),
+    Chained(ChainedLayout),
 }
 
 impl Layout {
-    pub(crate) fn format<F>(&self, record: &log::Record, f: &F) -> anyhow::Result<()>
+    /// Format the record with `now` as its timestamp, so that all the layouts render the same
+    /// instant for a record.
+    pub(crate) fn format<F>(&self, record: &log::Record, now: &Zoned, f: &F) -> anyhow::Result<()>
     where
         F: Fn(&log::Record) -> anyhow::Result<()>,
     {
@@ -48,16 +88,184 @@ impl Layout {
             Layout::Identical(layout) => {
                 layout.format(record, &|args| f(&record.to_builder().args(args).build()))
             }
-            Layout::Text(layout) => {
+            Layout::Blackhole(layout) => {
                 layout.format(record, &|args| f(&record.to_builder().args(args).build()))
             }
+            Layout::Text(layout) => layout.format(record, now, &|args| {
+                f(&record.to_builder().args(args).build())
+            }),
             #[cfg(feature = "json")]
-            Layout::Json(layout) => {
-                layout.format(record, &|args| f(&record.to_builder().args(args).build()))
-            }
+            Layout::Json(layout) => layout.format(record, now, &|args| {
+                f(&record.to_builder().args(args).build())
+            }),
+            Layout::Csv(layout) => layout.format(record, now, &|args| {
+                f(&record.to_builder().args(args).build())
+            }),
+            Layout::Pretty(layout) => layout.format(record, now, &|args| {
+                f(&record.to_builder().args(args).build())
+            }),
             Layout::Custom(layout) => {
                 layout.format(record, &|args| f(&record.to_builder().args(args).build()))
             }
+            Layout::Chained(layout) => layout.format(record, now, &|args| {
+                f(&record.to_builder().args(args).build())
+            }),
+        }
+    }
+}
+
+/// What to do with a record when its layout fails to format it, e.g., on a bad time format.
+///
+/// Errors of the appenders are always passed to the error handler of the logger.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutErrorPolicy {
+    /// Drop the record silently.
+    DropRecord,
+    /// Pass the record to the appender as a minimal line of its level, target, and message, so
+    /// that a layout bug never loses the message. The line isn't passed if the layout appended the
+    /// record before failing. The first failure of each appender is also passed to the error
+    /// handler of the logger.
+    #[default]
+    FallbackToText,
+    /// Pass the error to the error handler of the logger, and drop the record.
+    PropagateToErrorHandler,
+}
+
+/// Render the message of a record, without allocating if it's a plain string literal.
+pub(crate) fn message<'a>(args: &'a Arguments<'a>) -> Cow<'a, str> {
+    match args.as_str() {
+        Some(message) => message.into(),
+        None => args.to_string().into(),
+    }
+}
+
+/// Wraps the tokens in the escape codes of a color, if any.
+#[derive(Clone, Copy)]
+pub(crate) struct Paint {
+    pub(crate) color: Option<Color>,
+}
+
+impl Paint {
+    pub(crate) fn wrap<T: fmt::Display>(self, token: T) -> Painted<T> {
+        Painted { paint: self, token }
+    }
+}
+
+pub(crate) struct Painted<T> {
+    paint: Paint,
+    token: T,
+}
+
+impl<T: fmt::Display> fmt::Display for Painted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.paint.color {
+            Some(color) => write!(f, "\x1b[{}m{}\x1b[0m", color.to_fg_str(), self.token),
+            None => write!(f, "{}", self.token),
         }
     }
 }
+
+/// The key of the key-value carrying the column of a record's location, which [`log::Record`]
+/// doesn't have, so that macros can attach it.
+pub(crate) const COLUMN_KEY: &str = "column";
+
+/// The column of a record's location, carried by the `column` key-value.
+pub(crate) fn column(record: &log::Record) -> Option<u32> {
+    let value = record.key_values().get(COLUMN_KEY.into())?;
+    match value.to_u64() {
+        Some(column) => u32::try_from(column).ok(),
+        None => value.to_string().parse().ok(),
+    }
+}
+
+/// The key-values of a record without the ones of some keys, e.g., key-values promoted to fields
+/// of their own.
+pub(crate) struct WithoutKey<'a> {
+    pub(crate) source: &'a dyn log::kv::Source,
+    pub(crate) keys: &'a [&'a str],
+}
+
+impl log::kv::Source for WithoutKey<'_> {
+    fn visit<'kvs>(
+        &'kvs self,
+        visitor: &mut dyn log::kv::VisitSource<'kvs>,
+    ) -> Result<(), log::kv::Error> {
+        struct Skip<'a, 'kvs> {
+            visitor: &'a mut dyn log::kv::VisitSource<'kvs>,
+            keys: &'a [&'a str],
+        }
+
+        impl<'kvs> log::kv::VisitSource<'kvs> for Skip<'_, 'kvs> {
+            fn visit_pair(
+                &mut self,
+                key: log::kv::Key<'kvs>,
+                value: log::kv::Value<'kvs>,
+            ) -> Result<(), log::kv::Error> {
+                if self.keys.contains(&key.as_str()) {
+                    return Ok(());
+                }
+                self.visitor.visit_pair(key, value)
+            }
+        }
+
+        self.source.visit(&mut Skip {
+            visitor,
+            keys: self.keys,
+        })
+    }
+}
+
+/// Capture a backtrace for a record at or above the level, if backtraces are enabled by the
+/// `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE` environment variables.
+pub(crate) fn backtrace(record: &log::Record, level: Option<Level>) -> Option<Backtrace> {
+    capture_backtrace(record, level, Backtrace::capture)
+}
+
+fn capture_backtrace(
+    record: &log::Record,
+    level: Option<Level>,
+    capture: impl FnOnce() -> Backtrace,
+) -> Option<Backtrace> {
+    let level = level?;
+    if record.level() > level {
+        return None;
+    }
+    let backtrace = capture();
+    match backtrace.status() {
+        BacktraceStatus::Captured => Some(backtrace),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_backtrace() {
+        let record = |level| log::Record::builder().level(level).build();
+
+        let captured = capture_backtrace(
+            &record(Level::Error),
+            Some(Level::Warn),
+            Backtrace::force_capture,
+        );
+        assert!(captured.is_some());
+
+        // below the level, or disabled by the environment variables
+        let below = capture_backtrace(
+            &record(Level::Info),
+            Some(Level::Warn),
+            Backtrace::force_capture,
+        );
+        assert!(below.is_none());
+        let disabled = capture_backtrace(
+            &record(Level::Error),
+            Some(Level::Warn),
+            Backtrace::disabled,
+        );
+        assert!(disabled.is_none());
+        let unset = capture_backtrace(&record(Level::Error), None, Backtrace::force_capture);
+        assert!(unset.is_none());
+    }
+}
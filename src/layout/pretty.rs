@@ -0,0 +1,289 @@
+// Copyright 2024 CratesLand Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+use std::fmt::Arguments;
+
+use colored::Color;
+use jiff::tz::TimeZone;
+use jiff::Zoned;
+use log::kv::Error;
+use log::kv::Key;
+use log::kv::Value;
+use log::kv::VisitValue;
+use log::Level;
+use log::Record;
+
+use crate::layout::message;
+use crate::layout::Layout;
+use crate::layout::LevelColor;
+use crate::layout::Paint;
+
+/// When to emit escape codes for colors.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    /// Color if the `colored` crate detects a terminal, and the `no-color` feature is off.
+    #[default]
+    Auto,
+    /// Always color.
+    Always,
+    /// Never color, e.g., when piped into a file.
+    Never,
+}
+
+impl ColorMode {
+    pub(crate) fn colorize(self) -> bool {
+        match self {
+            ColorMode::Auto => {
+                cfg!(not(feature = "no-color"))
+                    && colored::control::SHOULD_COLORIZE.should_colorize()
+            }
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+        }
+    }
+}
+
+/// A layout for reading structured logs in a terminal during development, which colors each
+/// token by its type.
+///
+/// Output format:
+///
+/// ```text
+/// 22:44:57.172 ERROR app: Hello error! user="alice" count=3
+/// 22:44:57.172  INFO app: Hello info! ok=true
+/// ```
+///
+/// Unlike [`TextLayout`][crate::layout::TextLayout], which only colors the level, the keys,
+/// the string values, and the number and boolean values are each rendered in a color of their
+/// own. String values are always quoted and escaped, so that they're told apart from the other
+/// values even without colors.
+///
+/// Whether to color is controlled by the `color_mode` field with a [`ColorMode`]. You can
+/// customize the colors by setting the `colors`, `key_color`, `string_color` and `number_color`
+/// fields.
+///
+/// You can customize the timezone of the timestamp by setting the `tz` field with a [`TimeZone`]
+/// instance. Otherwise, the system timezone is used.
+#[derive(Debug, Clone)]
+pub struct PrettyLayout {
+    pub color_mode: ColorMode,
+    pub colors: LevelColor,
+    pub key_color: Color,
+    pub string_color: Color,
+    pub number_color: Color,
+    pub tz: Option<TimeZone>,
+}
+
+impl Default for PrettyLayout {
+    fn default() -> Self {
+        PrettyLayout {
+            color_mode: ColorMode::default(),
+            colors: LevelColor::default(),
+            key_color: Color::Cyan,
+            string_color: Color::Green,
+            number_color: Color::Magenta,
+            tz: None,
+        }
+    }
+}
+
+impl PrettyLayout {
+    pub(crate) fn format<F>(&self, record: &Record, now: &Zoned, f: &F) -> anyhow::Result<()>
+    where
+        F: Fn(Arguments) -> anyhow::Result<()>,
+    {
+        let colorize = self.color_mode.colorize();
+        let paint = |color| Paint {
+            color: colorize.then_some(color),
+        };
+
+        let time = match self.tz.clone() {
+            Some(tz) => now.with_time_zone(tz),
+            None => now.clone(),
+        };
+        let time = time.strftime("%H:%M:%S%.3f");
+        let level = record.level();
+        let level_color = match level {
+            Level::Error => self.colors.error,
+            Level::Warn => self.colors.warn,
+            Level::Info => self.colors.info,
+            Level::Debug => self.colors.debug,
+            Level::Trace => self.colors.trace,
+        };
+        let kvs = PrettyKvs {
+            record,
+            key: paint(self.key_color),
+            string: paint(self.string_color),
+            number: paint(self.number_color),
+        };
+
+        f(format_args!(
+            "{time} {} {}: {}{kvs}",
+            paint(level_color).wrap(format_args!("{:>5}", level.as_str())),
+            record.target(),
+            message(record.args()),
+        ))
+    }
+}
+
+struct PrettyKvs<'a, 'kvs> {
+    record: &'a Record<'kvs>,
+    key: Paint,
+    string: Paint,
+    number: Paint,
+}
+
+impl fmt::Display for PrettyKvs<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut visitor = PrettyKvWriter { kvs: self, f };
+        self.record.key_values().visit(&mut visitor).ok();
+        Ok(())
+    }
+}
+
+struct PrettyKvWriter<'a, 'b, 'c, 'kvs> {
+    kvs: &'a PrettyKvs<'a, 'kvs>,
+    f: &'b mut fmt::Formatter<'c>,
+}
+
+impl<'kvs> log::kv::Visitor<'kvs> for PrettyKvWriter<'_, '_, '_, '_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), Error> {
+        write!(self.f, " {}=", self.kvs.key.wrap(key))?;
+        value.visit(self)
+    }
+}
+
+impl PrettyKvWriter<'_, '_, '_, '_> {
+    fn write_string(&mut self, value: &str) -> Result<(), Error> {
+        write!(
+            self.f,
+            "{}",
+            self.kvs.string.wrap(format_args!("{value:?}"))
+        )?;
+        Ok(())
+    }
+
+    fn write_number(&mut self, value: impl fmt::Display) -> Result<(), Error> {
+        write!(self.f, "{}", self.kvs.number.wrap(value))?;
+        Ok(())
+    }
+}
+
+impl<'v> VisitValue<'v> for PrettyKvWriter<'_, '_, '_, '_> {
+    fn visit_any(&mut self, value: Value) -> Result<(), Error> {
+        self.write_string(&value.to_string())
+    }
+
+    fn visit_null(&mut self) -> Result<(), Error> {
+        write!(self.f, "null")?;
+        Ok(())
+    }
+
+    fn visit_u64(&mut self, value: u64) -> Result<(), Error> {
+        self.write_number(value)
+    }
+
+    fn visit_i64(&mut self, value: i64) -> Result<(), Error> {
+        self.write_number(value)
+    }
+
+    fn visit_u128(&mut self, value: u128) -> Result<(), Error> {
+        self.write_number(value)
+    }
+
+    fn visit_i128(&mut self, value: i128) -> Result<(), Error> {
+        self.write_number(value)
+    }
+
+    fn visit_f64(&mut self, value: f64) -> Result<(), Error> {
+        self.write_number(value)
+    }
+
+    fn visit_bool(&mut self, value: bool) -> Result<(), Error> {
+        self.write_number(value)
+    }
+
+    fn visit_str(&mut self, value: &str) -> Result<(), Error> {
+        self.write_string(value)
+    }
+
+    fn visit_char(&mut self, value: char) -> Result<(), Error> {
+        self.write_string(value.encode_utf8(&mut [0; 4]))
+    }
+}
+
+impl From<PrettyLayout> for Layout {
+    fn from(layout: PrettyLayout) -> Self {
+        Layout::Pretty(layout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn format(layout: &PrettyLayout) -> String {
+        let output = RefCell::new(String::new());
+        let now = Zoned::from_str("2024-08-11T22:44:57.172353+08:00[+08:00]").unwrap();
+        let kvs = [
+            ("user", Value::from("alice")),
+            ("count", Value::from(3)),
+            ("ok", Value::from(true)),
+        ];
+        layout
+            .format(
+                &Record::builder()
+                    .level(Level::Info)
+                    .target("app")
+                    .args(format_args!("hello"))
+                    .key_values(&kvs)
+                    .build(),
+                &now,
+                &|args| {
+                    *output.borrow_mut() = args.to_string();
+                    Ok(())
+                },
+            )
+            .unwrap();
+        output.into_inner()
+    }
+
+    #[test]
+    fn test_token_colors() {
+        let layout = PrettyLayout {
+            color_mode: ColorMode::Always,
+            ..Default::default()
+        };
+        assert_eq!(
+            format(&layout),
+            "22:44:57.172 \x1b[32m INFO\x1b[0m app: hello \
+             \x1b[36muser\x1b[0m=\x1b[32m\"alice\"\x1b[0m \
+             \x1b[36mcount\x1b[0m=\x1b[35m3\x1b[0m \
+             \x1b[36mok\x1b[0m=\x1b[35mtrue\x1b[0m"
+        );
+
+        let layout = PrettyLayout {
+            color_mode: ColorMode::Never,
+            ..Default::default()
+        };
+        assert_eq!(
+            format(&layout),
+            r#"22:44:57.172  INFO app: hello user="alice" count=3 ok=true"#
+        );
+    }
+}
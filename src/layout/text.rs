@@ -12,17 +12,27 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
 use std::fmt::Arguments;
 
 use colored::Color;
-use colored::ColoredString;
-use colored::Colorize;
 use jiff::tz::TimeZone;
 use jiff::Zoned;
 use log::Level;
 
+use crate::layout::backtrace;
+use crate::layout::column;
+use crate::layout::message;
+use crate::layout::ColorMode;
 use crate::layout::KvDisplay;
+use crate::layout::KvNullPolicy;
 use crate::layout::Layout;
+use crate::layout::Paint;
+use crate::layout::QuotePolicy;
+use crate::layout::WithoutKey;
+use crate::layout::COLUMN_KEY;
 
 /// A layout that formats log record as text.
 ///
@@ -36,18 +46,173 @@ use crate::layout::Layout;
 /// 2024-08-11T22:44:57.172382+08:00 TRACE rolling_file: examples/rolling_file.rs:55 Hello trace!
 /// ```
 ///
-/// By default, log levels are colored. You can turn on the `no-color` feature flag to disable this
-/// feature.
+/// By default, log levels are colored if stdout is a TTY. You can turn on the `no-color` feature
+/// flag to disable this feature, or decide it for the layout regardless of the environment by
+/// setting the `color_mode` field with a [`ColorMode`], e.g., to assert the escape codes in tests.
 ///
 /// You can also customize the color of each log level by setting the `colors` field with a
-/// [`LevelColor`] instance.
+/// [`LevelColor`] instance, e.g., the palette of a terminal theme by [`LevelColor::theme`].
 ///
 /// You can customize the timezone of the timestamp by setting the `tz` field with a [`TimeZone`]
-/// instance. Otherwise, the system timezone is used.
+/// instance. Otherwise, the system timezone is used. The timestamp always ends with the numeric
+/// offset of the timezone, e.g., `+08:00`; set the `time_offset` field to [`TimeOffset::Zulu`] to
+/// render a zero offset as `Z` instead. For dense logs, e.g., of embedded devices, set the
+/// `time_style` field to [`TimeStyle::TimeOfDay`] to render only the time of day in milliseconds,
+/// e.g., `12:00:00.123`.
+///
+/// You can customize the separators between the segments of a line by setting the `separators`
+/// field with a [`TextSeparators`] instance.
+///
+/// By default, the module path of the record is rendered. You can render the target instead, or
+/// both, by setting the `module_source` field with a [`ModuleSource`] variant.
+///
+/// You can leave out the timestamp, the module, and the `file:line` segments independently by
+/// setting the `omit_timestamp`, `omit_module`, and `omit_location` fields, e.g., for a minimal
+/// `INFO message` console. An omitted segment is left out along with the separator after it.
+///
+/// Key-value pairs are rendered in the logfmt style by [`KvDisplay`]. You can customize how null
+/// values are rendered by setting the `kv_null_policy` field, and the number of digits after the
+/// decimal point of float values by setting the `kv_float_precision` field. You can customize when
+/// string values are quoted by setting the `kv_quote_policy` field with a [`QuotePolicy`], and
+/// the character escaping special characters inside quotes, `\` by default, by setting the
+/// `kv_escape` field. You can cap the number of rendered pairs by setting the `max_kvs` field, so
+/// that a record with hundreds of key-values doesn't produce an enormous line.
+///
+/// If the message is empty, e.g., for key-value only events, it's omitted along with the separator
+/// before it.
+///
+/// You can color the key-value pairs with the color of the level, with dimmed keys, by setting the
+/// `kv_level_color` field. Like the level, they're left uncolored when colors are disabled, e.g.,
+/// if stdout isn't a TTY or the `no-color` feature flag is on.
+///
+/// By default, the level is rendered as a word, e.g., `ERROR`. You can render it as the syslog
+/// severity number, e.g., `3`, by setting the `level_style` field to [`LevelStyle::Numeric`], or as
+/// its initial, e.g., `E`, with [`LevelStyle::Char`]. The level follows the timestamp unless the
+/// `level_first` field is set. Combined with [`TimeStyle::TimeOfDay`] and the omitted module and
+/// location, this renders compact lines like `E 12:00:00.123 message`.
+/// You can rename individual levels, e.g., `Warn` to `CAUTION`, by setting the `level_labels`
+/// field; the level column is padded to the widest label, so that the columns after it align,
+/// unless the level leads the line.
+///
+/// If the record has a `column` key-value, e.g., attached by a macro, the location is rendered as
+/// `file:line:column`, and the key-value is left out of the key-values.
+///
+/// You can make the `file:line` segment a clickable OSC-8 hyperlink in terminals that support it
+/// by setting the `hyperlink` field with a URL template, where `{file}` and `{line}` are replaced
+/// with the location of the record, e.g., `file://{file}` or `vscode://file/{file}:{line}`. The
+/// hyperlink is omitted when colors are disabled, e.g., if stdout isn't a TTY.
+///
+/// You can prepend a static label, e.g., `[api]`, to every line by setting the `prefix` field,
+/// and color it by setting the `prefix_color` field. To tell the lines of different builds apart,
+/// e.g., in a canary, set the `version_tag` field, e.g., with the short git sha, which is rendered
+/// right after the level.
+///
+/// You can append a backtrace block to records at or above a level, e.g., [`Level::Error`], by
+/// setting the `capture_backtrace_at` field. Backtraces are only captured if they're enabled by
+/// the `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE` environment variables, since capturing is costly.
 #[derive(Default, Debug, Clone)]
 pub struct TextLayout {
+    pub prefix: Option<String>,
+    pub prefix_color: Option<Color>,
+    pub version_tag: Option<String>,
+    pub color_mode: ColorMode,
     pub colors: LevelColor,
+    pub level_style: LevelStyle,
+    pub level_labels: HashMap<Level, String>,
     pub tz: Option<TimeZone>,
+    pub time_offset: TimeOffset,
+    pub time_style: TimeStyle,
+    pub level_first: bool,
+    pub separators: TextSeparators,
+    pub module_source: ModuleSource,
+    pub omit_timestamp: bool,
+    pub omit_module: bool,
+    pub omit_location: bool,
+    pub kv_null_policy: KvNullPolicy,
+    pub kv_float_precision: Option<usize>,
+    pub kv_quote_policy: QuotePolicy,
+    pub kv_escape: Option<char>,
+    pub kv_level_color: bool,
+    pub max_kvs: Option<usize>,
+    pub hyperlink: Option<String>,
+    pub capture_backtrace_at: Option<Level>,
+}
+
+/// How to render the level of a record in a text log line.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LevelStyle {
+    /// Render the level name, e.g., `ERROR`.
+    #[default]
+    Word,
+    /// Render the syslog severity number, i.e., `3` for `Error`, `4` for `Warn`, `6` for `Info`,
+    /// and `7` for both `Debug` and `Trace`.
+    Numeric,
+    /// Render the initial of the level name, e.g., `E`.
+    Char,
+}
+
+/// How much of the timestamp to render in a text log line.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TimeStyle {
+    /// Render the date and time in microseconds with the UTC offset, e.g.,
+    /// `2024-08-11T22:44:57.172105+08:00`.
+    #[default]
+    Full,
+    /// Render the time of day in milliseconds without the date and the UTC offset, e.g.,
+    /// `22:44:57.172`.
+    TimeOfDay,
+}
+
+/// How to render the UTC offset suffix of the timestamp in a text log line.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TimeOffset {
+    /// Render the numeric offset, e.g., `+08:00` or `+00:00`.
+    #[default]
+    Numeric,
+    /// Render a zero offset as `Z`, and other offsets numerically, e.g., `+08:00`.
+    Zulu,
+}
+
+/// Which of the record's module path and target to render in a text log line.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ModuleSource {
+    /// Render the module path, e.g., `app::auth`.
+    #[default]
+    ModulePath,
+    /// Render the target, e.g., `audit`.
+    Target,
+    /// Render the target, followed by the module path in parentheses if they differ, e.g.,
+    /// `audit (app::auth)`.
+    Both,
+}
+
+/// Customize the separators between the segments of a text log line.
+///
+/// Each separator defaults to a single space.
+#[derive(Debug, Clone)]
+pub struct TextSeparators {
+    /// The separator between the prefix and the timestamp.
+    pub after_prefix: String,
+    /// The separator between the timestamp and the level.
+    pub after_time: String,
+    /// The separator between the level and the module path.
+    pub after_level: String,
+    /// The separator between the module path and the location.
+    pub after_module: String,
+    /// The separator between the location and the message.
+    pub after_location: String,
+}
+
+impl Default for TextSeparators {
+    fn default() -> Self {
+        Self {
+            after_prefix: " ".to_string(),
+            after_time: " ".to_string(),
+            after_level: " ".to_string(),
+            after_module: " ".to_string(),
+            after_location: " ".to_string(),
+        }
+    }
 }
 
 /// Customize the color of each log level.
@@ -72,8 +237,70 @@ impl Default for LevelColor {
     }
 }
 
+/// A built-in palette of level colors matching a common terminal theme.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Theme {
+    /// The [Solarized](https://ethanschoonover.com/solarized/) accent colors.
+    Solarized,
+    /// The [Dracula](https://draculatheme.com/) colors.
+    Dracula,
+    /// The Monokai colors.
+    Monokai,
+}
+
+impl LevelColor {
+    /// The level colors of a built-in [`Theme`], as 24-bit colors.
+    ///
+    /// Terminals without truecolor support approximate them.
+    pub fn theme(theme: Theme) -> Self {
+        let [error, warn, info, debug, trace] = match theme {
+            Theme::Solarized => [0xdc322f, 0xb58900, 0x859900, 0x268bd2, 0x6c71c4],
+            Theme::Dracula => [0xff5555, 0xf1fa8c, 0x50fa7b, 0x8be9fd, 0xbd93f9],
+            Theme::Monokai => [0xf92672, 0xfd971f, 0xa6e22e, 0x66d9ef, 0xae81ff],
+        }
+        .map(rgb);
+        Self {
+            error,
+            warn,
+            info,
+            debug,
+            trace,
+        }
+    }
+}
+
+fn rgb(hex: u32) -> Color {
+    let [_, r, g, b] = hex.to_be_bytes();
+    Color::TrueColor { r, g, b }
+}
+
 impl TextLayout {
-    pub(crate) fn format<F>(&self, record: &log::Record, f: &F) -> anyhow::Result<()>
+    fn level_label(&self, level: Level) -> &str {
+        if let Some(label) = self.level_labels.get(&level) {
+            return label;
+        }
+        match self.level_style {
+            LevelStyle::Word => level.as_str(),
+            LevelStyle::Numeric => match level {
+                Level::Error => "3",
+                Level::Warn => "4",
+                Level::Info => "6",
+                Level::Debug | Level::Trace => "7",
+            },
+            LevelStyle::Char => &level.as_str()[..1],
+        }
+    }
+
+    /// The width to pad level labels to, so that the columns after the level always align.
+    fn level_width(&self) -> usize {
+        Level::iter()
+            .map(|level| self.level_label(level).chars().count())
+            .max()
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn format<F>(&self, record: &log::Record, now: &Zoned, f: &F) -> anyhow::Result<()>
     where
         F: Fn(Arguments) -> anyhow::Result<()>,
     {
@@ -86,25 +313,668 @@ impl TextLayout {
         };
 
         let time = match self.tz.clone() {
-            Some(tz) => Zoned::now().with_time_zone(tz),
-            None => Zoned::now(),
+            Some(tz) => now.with_time_zone(tz),
+            None => now.clone(),
+        };
+        let time = match (self.time_style, self.time_offset) {
+            (TimeStyle::TimeOfDay, _) => time.strftime("%H:%M:%S.%3f"),
+            (TimeStyle::Full, TimeOffset::Zulu) if time.offset() == jiff::tz::Offset::UTC => {
+                time.strftime("%Y-%m-%dT%H:%M:%S.%6fZ")
+            }
+            (TimeStyle::Full, _) => time.strftime("%Y-%m-%dT%H:%M:%S.%6f%:z"),
+        };
+        let time = (!self.omit_timestamp).then_some(time);
+        let colorize = self.color_mode.colorize();
+        let paint = |color| Paint {
+            color: colorize.then_some(color),
+        };
+        let prefix = match &self.prefix {
+            Some(prefix) => {
+                let prefix = Paint {
+                    color: self.prefix_color.filter(|_| colorize),
+                }
+                .wrap(prefix);
+                format!("{prefix}{}", self.separators.after_prefix)
+            }
+            None => String::new(),
+        };
+        // the padding aligns the level after the timestamp, but would only indent a leading level
+        let level_width = match time.is_none() || self.level_first {
+            true => 0,
+            false => self.level_width(),
+        };
+        let level = format!("{:>level_width$}", self.level_label(record.level()));
+        let level = paint(color).wrap(level);
+        let module: Cow<str> = match self.module_source {
+            ModuleSource::ModulePath => record.module_path().unwrap_or_default().into(),
+            ModuleSource::Target => record.target().into(),
+            ModuleSource::Both => match record.module_path() {
+                Some(module_path) if module_path != record.target() => {
+                    format!("{} ({module_path})", record.target()).into()
+                }
+                _ => record.target().into(),
+            },
+        };
+        // omit the message segment along with the separator before it if the message is empty,
+        // so that the key-values don't follow a dangling space
+        let message = message(record.args());
+        let separator = |separator| if message.is_empty() { "" } else { separator };
+        let file = record.file().filter(|_| !self.omit_location);
+        let location = Location {
+            file,
+            line: record.line(),
+            column: file.and(record.line()).and_then(|_| column(record)),
+            separator: separator(&self.separators.after_location),
+            hyperlink: self.hyperlink.as_deref().filter(|_| colorize),
+        };
+        let without_column = WithoutKey {
+            source: record.key_values(),
+            keys: &[COLUMN_KEY],
+        };
+        let kvs = match location.column {
+            Some(_) => KvDisplay::new(&without_column),
+            None => KvDisplay::new(record.key_values()),
         }
-        .strftime("%Y-%m-%dT%H:%M:%S.%6f%:z");
-        let level = ColoredString::from(record.level().to_string()).color(color);
-        let module = record.module_path().unwrap_or_default();
-        let file = record.file().unwrap_or_default();
-        let line = record.line().unwrap_or_default();
-        let message = record.args();
-        let kvs = KvDisplay::new(record.key_values());
+        .null_policy(self.kv_null_policy)
+        .float_precision(self.kv_float_precision)
+        .quote_policy(self.kv_quote_policy)
+        .escape(self.kv_escape.unwrap_or('\\'))
+        .max_kvs(self.max_kvs)
+        .color(self.kv_level_color.then_some(color).filter(|_| colorize));
+        let backtrace = match backtrace(record, self.capture_backtrace_at) {
+            Some(backtrace) => format!("\n{backtrace}"),
+            None => String::new(),
+        };
 
+        let after_module = match location.file {
+            Some(_) => &self.separators.after_module,
+            None => separator(&self.separators.after_module),
+        };
+        let module = match self.omit_module {
+            true => String::new(),
+            false => format!("{module}:{after_module}"),
+        };
+        // the separator after the leading segments is omitted if nothing follows them
+        let rest_follows = !self.omit_module || location.file.is_some() || !message.is_empty();
+        let version_tag = match &self.version_tag {
+            Some(tag) if rest_follows => format!("{tag}{}", self.separators.after_level),
+            Some(tag) => tag.clone(),
+            None => String::new(),
+        };
+        let followed = rest_follows || self.version_tag.is_some();
+        let trailing = |separator| if followed { separator } else { "" };
+        let head = match (time, self.level_first) {
+            (Some(time), false) => format!(
+                "{time}{}{level}{}",
+                self.separators.after_time,
+                trailing(&self.separators.after_level)
+            ),
+            (Some(time), true) => format!(
+                "{level}{}{time}{}",
+                self.separators.after_level,
+                trailing(&self.separators.after_time)
+            ),
+            (None, _) => format!("{level}{}", trailing(&self.separators.after_level)),
+        };
         f(format_args!(
-            "{time} {level:>5} {module}: {file}:{line} {message}{kvs}"
+            "{prefix}{head}{version_tag}{module}{location}{message}{kvs}{backtrace}"
         ))
     }
 }
 
+/// The `file:line` segment followed by its separator, omitted if the record has no location.
+struct Location<'a> {
+    file: Option<&'a str>,
+    line: Option<u32>,
+    column: Option<u32>,
+    separator: &'a str,
+    /// The URL template of the OSC-8 hyperlink wrapping the segment.
+    hyperlink: Option<&'a str>,
+}
+
+impl fmt::Display for Location<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Some(file) = self.file else {
+            return Ok(());
+        };
+        if let Some(hyperlink) = self.hyperlink {
+            let line = self.line.map(|line| line.to_string()).unwrap_or_default();
+            let url = hyperlink.replace("{file}", file).replace("{line}", &line);
+            write!(f, "\x1b]8;;{url}\x1b\\")?;
+        }
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, "{file}:{line}:{column}")?,
+            (Some(line), None) => write!(f, "{file}:{line}")?,
+            (None, _) => write!(f, "{file}")?,
+        }
+        if self.hyperlink.is_some() {
+            write!(f, "\x1b]8;;\x1b\\")?;
+        }
+        write!(f, "{}", self.separator)
+    }
+}
+
 impl From<TextLayout> for Layout {
     fn from(layout: TextLayout) -> Self {
         Layout::Text(layout)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::str::FromStr;
+
+    use log::Record;
+
+    use super::*;
+
+    fn format(layout: &TextLayout, record: &Record) -> String {
+        format_at(layout, record, &Zoned::now())
+    }
+
+    fn format_at(layout: &TextLayout, record: &Record, now: &Zoned) -> String {
+        let output = RefCell::new(String::new());
+        layout
+            .format(record, now, &|args| {
+                *output.borrow_mut() = args.to_string();
+                Ok(())
+            })
+            .unwrap();
+        output.into_inner()
+    }
+
+    #[test]
+    fn test_custom_separators() {
+        let layout = TextLayout {
+            separators: TextSeparators {
+                after_prefix: "\t".to_string(),
+                after_time: "\t".to_string(),
+                after_level: "\t".to_string(),
+                after_module: "\t".to_string(),
+                after_location: "\t".to_string(),
+            },
+            ..Default::default()
+        };
+        let record = Record::builder()
+            .module_path(Some("app"))
+            .file(Some("src/main.rs"))
+            .line(Some(42))
+            .args(format_args!("hello"))
+            .build();
+
+        let output = format(&layout, &record);
+        assert_eq!(output.matches('\t').count(), 4, "{output:?}");
+        assert!(
+            output.ends_with("\tapp:\tsrc/main.rs:42\thello"),
+            "{output:?}"
+        );
+    }
+
+    #[test]
+    fn test_timestamp_from_dispatch() {
+        let now = Zoned::from_str("2024-08-10T23:59:59.999999+08[+08]").unwrap();
+        let record = Record::builder().args(format_args!("hello")).build();
+
+        let output = format_at(&TextLayout::default(), &record, &now);
+        assert!(
+            output.starts_with("2024-08-10T23:59:59.999999+08:00 "),
+            "{output:?}"
+        );
+
+        let layout = TextLayout {
+            tz: Some(TimeZone::UTC),
+            ..Default::default()
+        };
+        let output = format_at(&layout, &record, &now);
+        assert!(
+            output.starts_with("2024-08-10T15:59:59.999999+00:00 "),
+            "{output:?}"
+        );
+    }
+
+    #[test]
+    fn test_time_offset() {
+        let now = Zoned::from_str("2024-08-10T23:59:59.999999+08[+08]").unwrap();
+        let record = Record::builder().args(format_args!("hello")).build();
+        let format_with = |tz, time_offset| {
+            let layout = TextLayout {
+                tz: Some(tz),
+                time_offset,
+                ..Default::default()
+            };
+            format_at(&layout, &record, &now)
+        };
+
+        let shanghai = TimeZone::fixed(jiff::tz::offset(8));
+        let output = format_with(shanghai.clone(), TimeOffset::Numeric);
+        assert!(
+            output.starts_with("2024-08-10T23:59:59.999999+08:00 "),
+            "{output:?}"
+        );
+        let output = format_with(shanghai, TimeOffset::Zulu);
+        assert!(
+            output.starts_with("2024-08-10T23:59:59.999999+08:00 "),
+            "{output:?}"
+        );
+        let output = format_with(TimeZone::UTC, TimeOffset::Zulu);
+        assert!(
+            output.starts_with("2024-08-10T15:59:59.999999Z "),
+            "{output:?}"
+        );
+    }
+
+    #[test]
+    fn test_module_source() {
+        let record = Record::builder()
+            .target("audit")
+            .module_path(Some("app::auth"))
+            .args(format_args!("login"))
+            .build();
+        let format_with = |module_source| {
+            let layout = TextLayout {
+                module_source,
+                ..Default::default()
+            };
+            format(&layout, &record)
+        };
+
+        assert!(format_with(ModuleSource::ModulePath).contains(" app::auth: "));
+        assert!(format_with(ModuleSource::Target).contains(" audit: "));
+        assert!(format_with(ModuleSource::Both).contains(" audit (app::auth): "));
+    }
+
+    #[test]
+    fn test_absent_location() {
+        let record = Record::builder()
+            .module_path(Some("app"))
+            .args(format_args!("hello"))
+            .build();
+        let output = format(&TextLayout::default(), &record);
+        assert!(output.ends_with(" app: hello"), "{output:?}");
+
+        let record = Record::builder()
+            .module_path(Some("app"))
+            .file(Some("src/main.rs"))
+            .args(format_args!("hello"))
+            .build();
+        let output = format(&TextLayout::default(), &record);
+        assert!(output.ends_with(" app: src/main.rs hello"), "{output:?}");
+    }
+
+    #[test]
+    fn test_omit_segments() {
+        let layout = TextLayout {
+            omit_timestamp: true,
+            omit_module: true,
+            omit_location: true,
+            ..Default::default()
+        };
+        let record = |message| {
+            format(
+                &layout,
+                &Record::builder()
+                    .level(Level::Error)
+                    .module_path(Some("app"))
+                    .file(Some("src/main.rs"))
+                    .line(Some(42))
+                    .args(format_args!("{message}"))
+                    .build(),
+            )
+        };
+        assert_eq!(record("hello"), "ERROR hello");
+        assert_eq!(record(""), "ERROR");
+
+        // the level leads the line, so it isn't padded
+        let output = format(
+            &layout,
+            &Record::builder()
+                .level(Level::Info)
+                .args(format_args!("hello"))
+                .build(),
+        );
+        assert_eq!(output, "INFO hello");
+
+        let layout = TextLayout {
+            omit_location: false,
+            ..layout
+        };
+        let output = format(
+            &layout,
+            &Record::builder()
+                .level(Level::Error)
+                .module_path(Some("app"))
+                .file(Some("src/main.rs"))
+                .line(Some(42))
+                .args(format_args!("hello"))
+                .build(),
+        );
+        assert_eq!(output, "ERROR src/main.rs:42 hello");
+    }
+
+    #[test]
+    fn test_column() {
+        let kvs = [
+            ("column", log::kv::Value::from(7)),
+            ("user", log::kv::Value::from("alice")),
+        ];
+        let output = format(
+            &TextLayout::default(),
+            &Record::builder()
+                .module_path(Some("app"))
+                .file(Some("src/main.rs"))
+                .line(Some(42))
+                .args(format_args!("hello"))
+                .key_values(&kvs)
+                .build(),
+        );
+        assert!(
+            output.ends_with(" app: src/main.rs:42:7 hello user=alice"),
+            "{output:?}"
+        );
+
+        // the column is kept as a key-value without a location
+        let output = format(
+            &TextLayout::default(),
+            &Record::builder()
+                .module_path(Some("app"))
+                .args(format_args!("hello"))
+                .key_values(&kvs)
+                .build(),
+        );
+        assert!(
+            output.ends_with(" app: hello column=7 user=alice"),
+            "{output:?}"
+        );
+    }
+
+    #[test]
+    fn test_empty_message() {
+        let kvs = [("event_type", log::kv::Value::from("login"))];
+        let record = Record::builder()
+            .module_path(Some("app"))
+            .args(format_args!(""))
+            .key_values(&kvs)
+            .build();
+        let output = format(&TextLayout::default(), &record);
+        assert!(output.ends_with(" app: event_type=login"), "{output:?}");
+
+        let record = Record::builder()
+            .module_path(Some("app"))
+            .file(Some("src/main.rs"))
+            .line(Some(42))
+            .args(format_args!(""))
+            .key_values(&kvs)
+            .build();
+        let output = format(&TextLayout::default(), &record);
+        assert!(
+            output.ends_with(" app: src/main.rs:42 event_type=login"),
+            "{output:?}"
+        );
+
+        let empty = String::new();
+        let output = format(
+            &TextLayout::default(),
+            &Record::builder()
+                .module_path(Some("app"))
+                .args(format_args!("{empty}"))
+                .build(),
+        );
+        assert!(output.ends_with(" app:"), "{output:?}");
+    }
+
+    #[test]
+    fn test_hyperlink() {
+        let location = |hyperlink| {
+            Location {
+                file: Some("src/main.rs"),
+                line: Some(42),
+                column: None,
+                separator: " ",
+                hyperlink,
+            }
+            .to_string()
+        };
+        assert_eq!(
+            location(Some("vscode://file/{file}:{line}")),
+            "\x1b]8;;vscode://file/src/main.rs:42\x1b\\src/main.rs:42\x1b]8;;\x1b\\ "
+        );
+        assert_eq!(location(None), "src/main.rs:42 ");
+
+        // omitted along with the colors
+        let layout = TextLayout {
+            hyperlink: Some("file://{file}".to_string()),
+            color_mode: ColorMode::Never,
+            ..Default::default()
+        };
+        let record = Record::builder()
+            .file(Some("src/main.rs"))
+            .line(Some(42))
+            .args(format_args!("hello"))
+            .build();
+        let output = format(&layout, &record);
+        assert!(!output.contains("\x1b]8"), "{output:?}");
+    }
+
+    #[test]
+    fn test_prefix() {
+        let layout = TextLayout {
+            prefix: Some("[api]".to_string()),
+            ..Default::default()
+        };
+        let record = Record::builder().args(format_args!("hello")).build();
+
+        let output = format(&layout, &record);
+        assert!(output.starts_with("[api] "), "{output:?}");
+        assert!(output.ends_with(" hello"), "{output:?}");
+    }
+
+    #[test]
+    fn test_version_tag() {
+        let layout = TextLayout {
+            version_tag: Some("3f2a9c1".to_string()),
+            omit_timestamp: true,
+            ..Default::default()
+        };
+        let record = |message| {
+            format(
+                &layout,
+                &Record::builder()
+                    .level(Level::Info)
+                    .module_path(Some("app"))
+                    .args(format_args!("{message}"))
+                    .build(),
+            )
+        };
+        assert_eq!(record("hello"), "INFO 3f2a9c1 app: hello");
+
+        let layout = TextLayout {
+            omit_module: true,
+            ..layout
+        };
+        let output = format(&layout, &Record::builder().args(format_args!("")).build());
+        assert_eq!(output, "INFO 3f2a9c1");
+    }
+
+    #[test]
+    fn test_compact() {
+        let layout = TextLayout {
+            level_style: LevelStyle::Char,
+            time_style: TimeStyle::TimeOfDay,
+            level_first: true,
+            omit_module: true,
+            omit_location: true,
+            ..Default::default()
+        };
+        let now = Zoned::from_str("2024-08-10T12:00:00.123456+08[+08]").unwrap();
+        let output = format_at(
+            &layout,
+            &Record::builder()
+                .level(Level::Error)
+                .module_path(Some("app"))
+                .file(Some("src/main.rs"))
+                .line(Some(42))
+                .args(format_args!("msg"))
+                .build(),
+            &now,
+        );
+        assert_eq!(output, "E 12:00:00.123 msg");
+
+        let output = format_at(
+            &layout,
+            &Record::builder()
+                .level(Level::Error)
+                .args(format_args!(""))
+                .build(),
+            &now,
+        );
+        assert_eq!(output, "E 12:00:00.123");
+    }
+
+    #[test]
+    fn test_numeric_level() {
+        let layout = TextLayout {
+            level_style: LevelStyle::Numeric,
+            ..Default::default()
+        };
+        let now = Zoned::now();
+        let time = now.strftime("%Y-%m-%dT%H:%M:%S.%6f%:z").to_string();
+        for (level, severity) in [
+            (Level::Error, 3),
+            (Level::Warn, 4),
+            (Level::Info, 6),
+            (Level::Debug, 7),
+            (Level::Trace, 7),
+        ] {
+            let record = Record::builder()
+                .level(level)
+                .module_path(Some("app"))
+                .args(format_args!("hello"))
+                .build();
+            let output = format_at(&layout, &record, &now);
+            let output = output.strip_prefix(&time).unwrap();
+            assert!(output.contains(&format!(" {severity}")), "{output:?}");
+            assert!(output.ends_with(" app: hello"), "{output:?}");
+        }
+    }
+
+    #[test]
+    fn test_level_alignment() {
+        let layout = TextLayout::default();
+        assert_eq!(layout.level_width(), "ERROR".len());
+
+        let now = Zoned::now();
+        let offsets = Level::iter()
+            .map(|level| {
+                let record = Record::builder()
+                    .level(level)
+                    .module_path(Some("app"))
+                    .args(format_args!("hello"))
+                    .build();
+                format_at(&layout, &record, &now).find(" app: ").unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert!(offsets.windows(2).all(|w| w[0] == w[1]), "{offsets:?}");
+    }
+
+    #[test]
+    fn test_level_labels() {
+        let layout = TextLayout {
+            level_labels: HashMap::from([
+                (Level::Warn, "CAUTION".to_string()),
+                (Level::Trace, "VERBOSE".to_string()),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(layout.level_width(), "CAUTION".len());
+
+        let now = Zoned::now();
+        let lines = Level::iter()
+            .map(|level| {
+                let record = Record::builder()
+                    .level(level)
+                    .module_path(Some("app"))
+                    .args(format_args!("hello"))
+                    .build();
+                format_at(&layout, &record, &now)
+            })
+            .collect::<Vec<_>>();
+        assert!(lines[1].contains(" CAUTION app: "), "{lines:?}");
+        assert!(lines[2].contains("    INFO app: "), "{lines:?}");
+        assert!(lines[4].contains(" VERBOSE app: "), "{lines:?}");
+        let offsets = lines
+            .iter()
+            .map(|line| line.find(" app: ").unwrap())
+            .collect::<Vec<_>>();
+        assert!(offsets.windows(2).all(|w| w[0] == w[1]), "{offsets:?}");
+    }
+
+    #[test]
+    fn test_theme() {
+        let colors = LevelColor::theme(Theme::Dracula);
+        let rgb = |color| match color {
+            Color::TrueColor { r, g, b } => (r, g, b),
+            color => panic!("not a truecolor: {color:?}"),
+        };
+        assert_eq!(rgb(colors.error), (0xff, 0x55, 0x55));
+        assert_eq!(rgb(colors.warn), (0xf1, 0xfa, 0x8c));
+        assert_eq!(rgb(colors.info), (0x50, 0xfa, 0x7b));
+        assert_eq!(rgb(colors.debug), (0x8b, 0xe9, 0xfd));
+        assert_eq!(rgb(colors.trace), (0xbd, 0x93, 0xf9));
+    }
+
+    #[test]
+    fn test_kv_level_color() {
+        let kvs = [("user", "alice")];
+        let record = Record::builder()
+            .level(Level::Warn)
+            .args(format_args!("hello"))
+            .key_values(&kvs)
+            .build();
+        let format_with = |color_mode| {
+            let layout = TextLayout {
+                color_mode,
+                kv_level_color: true,
+                ..Default::default()
+            };
+            format(&layout, &record)
+        };
+
+        let output = format_with(ColorMode::Always);
+        assert!(
+            output.ends_with(" hello \x1b[2;33muser\x1b[0m=\x1b[33malice\x1b[0m"),
+            "{output:?}"
+        );
+        let output = format_with(ColorMode::Never);
+        assert!(output.ends_with(" hello user=alice"), "{output:?}");
+    }
+
+    #[test]
+    fn test_color_mode() {
+        let now = Zoned::from_str("2024-08-10T12:00:00.123456+08[+08]").unwrap();
+        let record = Record::builder()
+            .level(Level::Info)
+            .module_path(Some("app"))
+            .args(format_args!("hello"))
+            .build();
+        let format_with = |color_mode| {
+            let layout = TextLayout {
+                prefix: Some("[api]".to_string()),
+                prefix_color: Some(Color::Blue),
+                color_mode,
+                ..Default::default()
+            };
+            format_at(&layout, &record, &now)
+        };
+
+        // decided by the layout, whether or not the test output is a TTY
+        assert_eq!(
+            format_with(ColorMode::Always),
+            "\x1b[34m[api]\x1b[0m 2024-08-10T12:00:00.123456+08:00 \x1b[32m INFO\x1b[0m app: hello"
+        );
+        assert_eq!(
+            format_with(ColorMode::Never),
+            "[api] 2024-08-10T12:00:00.123456+08:00  INFO app: hello"
+        );
+    }
+}
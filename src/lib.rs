@@ -39,7 +39,7 @@
 //!         Dispatch::new()
 //!             .filter(LevelFilter::Trace)
 //!             .layout(TextLayout::default())
-//!             .append(append::Stdout),
+//!             .append(append::Stdout::default()),
 //!     )
 //!     .apply()
 //!     .unwrap();
@@ -54,12 +54,21 @@
 //! Read more demos under the [examples](https://github.com/cratesland/logforth/tree/main/examples) directory.
 
 pub mod append;
+#[cfg(feature = "config")]
+mod config;
 pub mod filter;
 pub mod layout;
 mod logger;
+mod preset;
+mod scope;
 
 pub use append::Append;
 pub use filter::Filter;
 pub use layout::Layout;
 pub use logger::Dispatch;
 pub use logger::Logger;
+pub use logger::LoggerHandle;
+pub use preset::dev;
+#[cfg(all(feature = "json", feature = "rolling_file"))]
+pub use preset::prod;
+pub use scope::TimedScope;
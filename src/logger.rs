@@ -12,8 +12,30 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::backtrace::BacktraceStatus;
+use std::cell::Cell;
+use std::collections::HashMap;
 use std::io::Write;
+use std::ops::RangeInclusive;
+use std::panic::Location;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Once;
+use std::sync::OnceLock;
+use std::sync::PoisonError;
+use std::sync::RwLock;
+use std::sync::RwLockReadGuard;
+use std::time::Duration;
+use std::time::Instant;
 
+use jiff::tz::TimeZone;
+use jiff::Timestamp;
+use jiff::Zoned;
+use log::Level;
 use log::LevelFilter;
 use log::Metadata;
 use log::Record;
@@ -22,6 +44,8 @@ use crate::append::Append;
 use crate::filter::Filter;
 use crate::filter::FilterResult;
 use crate::layout::Layout;
+use crate::layout::LayoutErrorPolicy;
+use crate::layout::WithoutKey;
 
 /// A grouped set of appenders, filters, and optional layout.
 ///
@@ -31,11 +55,39 @@ use crate::layout::Layout;
 /// `filters` are used to determine whether a log record should be passed to the appenders.
 /// `appends` are used to write log records to a destination. Each appender has its own
 /// default layout. If the [`Dispatch`] has a layout, it will be used instead of the default layout.
+/// An appender added by [`Dispatch::append_with_layout`] uses its own layout instead of both.
+/// `kvs` are static key-value pairs attached to every log record passed to the appenders.
 #[derive(Debug)]
 pub struct Dispatch<const LAYOUT: bool = true, const APPEND: bool = true> {
-    filters: Vec<Filter>,
-    appends: Vec<Box<dyn Append>>,
-    layout: Option<Layout>,
+    // shared with the snapshots of the logger, see `Dispatch::share`
+    filters: Vec<Arc<Filter>>,
+    appends: Vec<Arc<Appender>>,
+    layout: Option<Arc<Layout>>,
+    kvs: Vec<(String, String)>,
+}
+
+/// An [`Append`] of a [`Dispatch`], optionally named so that it can be addressed individually,
+/// and with an optional layout of its own.
+#[derive(Debug)]
+struct Appender {
+    name: Option<String>,
+    append: Box<dyn Append>,
+    layout: Option<Arc<Layout>>,
+    // toggled by name through the logger handle, without taking the write lock
+    enabled: AtomicBool,
+    // set once replaced by the logger handle, so that the appender is drained when the last
+    // snapshot holding it is done
+    retired: AtomicBool,
+    // set once a layout failure fell back to text, so that it's reported only once
+    fell_back: AtomicBool,
+}
+
+impl Drop for Appender {
+    fn drop(&mut self) {
+        if self.retired.load(Ordering::Relaxed) {
+            self.append.flush();
+        }
+    }
 }
 
 impl Default for Dispatch<false, false> {
@@ -53,43 +105,130 @@ impl Dispatch<false, false> {
             filters: vec![],
             appends: vec![],
             layout: None,
+            kvs: vec![],
         }
     }
 
     /// Add a [`Filter`] to the [`Dispatch`].
     pub fn filter(mut self, filter: impl Into<Filter>) -> Dispatch<false, false> {
-        self.filters.push(filter.into());
+        self.filters.push(Arc::new(filter.into()));
         self
     }
 
     /// Add the preferred [`Layout`] to the [`Dispatch`]. At most one layout can be added to a
     /// [`Dispatch`].
     pub fn layout(self, layout: impl Into<Layout>) -> Dispatch<true, false> {
+        self.shared_layout(Arc::new(layout.into()))
+    }
+
+    /// Add the preferred [`Layout`] to the [`Dispatch`], shared with other dispatches, so that
+    /// they format records with the very same configuration. At most one layout can be added to
+    /// a [`Dispatch`].
+    pub fn shared_layout(self, layout: Arc<Layout>) -> Dispatch<true, false> {
         Dispatch {
             filters: self.filters,
             appends: self.appends,
-            layout: Some(layout.into()),
+            layout: Some(layout),
+            kvs: self.kvs,
         }
     }
 }
 
 impl<const LAYOUT: bool, const APPEND: bool> Dispatch<LAYOUT, APPEND> {
     /// Add an [`Append`] to the [`Dispatch`].
-    pub fn append(mut self, append: impl Append) -> Dispatch<true, true> {
-        self.appends.push(Box::new(append));
+    pub fn append(self, append: impl Append) -> Dispatch<true, true> {
+        self.push_append(None, append, None)
+    }
+
+    /// Add an [`Append`] to the [`Dispatch`] with its own [`Layout`], which is used instead of the
+    /// layout of the [`Dispatch`], e.g., to render timestamps in a different timezone per file.
+    pub fn append_with_layout(
+        self,
+        append: impl Append,
+        layout: impl Into<Layout>,
+    ) -> Dispatch<true, true> {
+        self.push_append(None, append, Some(Arc::new(layout.into())))
+    }
+
+    /// Add an [`Append`] to the [`Dispatch`] with a name, so that it can be flushed individually
+    /// by [`Logger::flush_appender`].
+    pub fn append_named(
+        self,
+        name: impl Into<String>,
+        append: impl Append,
+    ) -> Dispatch<true, true> {
+        self.push_append(Some(name.into()), append, None)
+    }
+
+    fn push_append(
+        mut self,
+        name: Option<String>,
+        append: impl Append,
+        layout: Option<Arc<Layout>>,
+    ) -> Dispatch<true, true> {
+        self.appends.push(Arc::new(Appender {
+            name,
+            append: Box::new(append),
+            layout,
+            enabled: AtomicBool::new(true),
+            retired: AtomicBool::new(false),
+            fell_back: AtomicBool::new(false),
+        }));
 
         Dispatch {
             filters: self.filters,
             appends: self.appends,
             layout: self.layout,
+            kvs: self.kvs,
         }
     }
+
+    /// Attach a static key-value pair to every log record dispatched by the [`Dispatch`], e.g.,
+    /// `service=api`.
+    ///
+    /// Static key-values are rendered before the record's own key-values. If the record has a
+    /// key-value of the same key, the record's value wins and the static one is skipped.
+    pub fn kv(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Dispatch<LAYOUT, APPEND> {
+        self.kvs.push((key.into(), value.into()));
+        self
+    }
 }
 
 impl Dispatch {
+    /// A copy of the dispatch sharing its filters, appenders, and layout, so that the handle can
+    /// update the dispatches while the records of an older snapshot are still being appended.
+    fn share(&self) -> Dispatch {
+        Dispatch {
+            filters: self.filters.clone(),
+            appends: self.appends.clone(),
+            layout: self.layout.clone(),
+            kvs: self.kvs.clone(),
+        }
+    }
+
     fn enabled(&self, metadata: &Metadata) -> bool {
-        for filter in &self.filters {
-            match filter.filter(metadata) {
+        self.check(metadata, |filter| filter.filter(metadata))
+    }
+
+    fn enabled_record(&self, record: &Record) -> bool {
+        self.check(record.metadata(), |filter| filter.filter_record(record))
+    }
+
+    fn check(&self, metadata: &Metadata, filter: impl Fn(&Filter) -> FilterResult) -> bool {
+        if !self
+            .appends
+            .iter()
+            .any(|appender| appender.append.enabled(metadata))
+        {
+            return false;
+        }
+
+        for f in &self.filters {
+            match filter(f) {
                 FilterResult::Reject => return false,
                 FilterResult::Accept => return true,
                 FilterResult::Neutral => {}
@@ -99,23 +238,153 @@ impl Dispatch {
         true
     }
 
-    fn log(&self, record: &Record) -> anyhow::Result<()> {
-        let layout = self.layout.as_ref();
-        for append in &self.appends {
-            match layout {
-                Some(layout) => layout.format(record, &|record| append.append(record))?,
-                None => append
-                    .default_layout()
-                    .format(record, &|record| append.append(record))?,
+    fn log(&self, record: &Record, now: &Zoned, policy: LayoutErrorPolicy) -> anyhow::Result<()> {
+        if !self.kvs.is_empty() {
+            let kvs = StaticKvs {
+                kvs: &self.kvs,
+                record: record.key_values(),
+            };
+            return self.log_record(&record.to_builder().key_values(&kvs).build(), now, policy);
+        }
+        self.log_record(record, now, policy)
+    }
+
+    fn log_record(
+        &self,
+        record: &Record,
+        now: &Zoned,
+        policy: LayoutErrorPolicy,
+    ) -> anyhow::Result<()> {
+        // reported once all the appenders have the record
+        let mut fallback_error = None;
+        for appender in &self.appends {
+            let Appender {
+                append,
+                layout,
+                enabled,
+                fell_back,
+                ..
+            } = &**appender;
+            if !enabled.load(Ordering::Relaxed) || !append.enabled(record.metadata()) {
+                continue;
+            }
+            // tell the errors of the appender apart from the ones of the layout, and whether the
+            // layout appended anything before failing
+            let appended = Cell::new(false);
+            let append_failed = Cell::new(false);
+            let append_record = |record: &Record| {
+                appended.set(true);
+                append.append(record).map_err(|err| {
+                    append_failed.set(true);
+                    err
+                })
+            };
+            let result = match layout.as_deref().or(self.layout.as_deref()) {
+                Some(layout) => layout.format(record, now, &append_record),
+                None => append.default_layout().format(record, now, &append_record),
+            };
+            match result {
+                Err(err) if append_failed.get() => return Err(err),
+                Err(err) => match policy {
+                    LayoutErrorPolicy::DropRecord => {}
+                    LayoutErrorPolicy::FallbackToText => {
+                        if !appended.get() {
+                            append.append(
+                                &record
+                                    .to_builder()
+                                    .args(format_args!(
+                                        "{} {}: {}",
+                                        record.level(),
+                                        record.target(),
+                                        record.args()
+                                    ))
+                                    .build(),
+                            )?;
+                        }
+                        if !fell_back.swap(true, Ordering::Relaxed) {
+                            fallback_error = Some(err.context("layout failed, fell back to text"));
+                        }
+                    }
+                    LayoutErrorPolicy::PropagateToErrorHandler => return Err(err),
+                },
+                Ok(()) => {}
             }
         }
-        Ok(())
+        fallback_error.map_or(Ok(()), Err)
     }
 
     fn flush(&self) {
-        for append in &self.appends {
-            append.flush();
+        for appender in &self.appends {
+            appender.append.flush();
+        }
+    }
+
+    fn flush_appender(&self, name: &str) -> bool {
+        let mut found = false;
+        for appender in &self.appends {
+            if appender.name.as_deref() == Some(name) {
+                appender.append.flush();
+                found = true;
+            }
         }
+        found
+    }
+
+    fn set_appender_enabled(&self, name: &str, enabled: bool) -> bool {
+        let mut found = false;
+        for appender in &self.appends {
+            if appender.name.as_deref() == Some(name) {
+                appender.enabled.store(enabled, Ordering::Relaxed);
+                found = true;
+            }
+        }
+        found
+    }
+}
+
+/// The static key-values of a [`Dispatch`] merged with the key-values of a record.
+struct StaticKvs<'a> {
+    kvs: &'a [(String, String)],
+    record: &'a dyn log::kv::Source,
+}
+
+impl log::kv::Source for StaticKvs<'_> {
+    fn visit<'kvs>(
+        &'kvs self,
+        visitor: &mut dyn log::kv::VisitSource<'kvs>,
+    ) -> Result<(), log::kv::Error> {
+        for (key, value) in self.kvs {
+            let key = log::kv::Key::from_str(key);
+            if self.record.get(key.clone()).is_none() {
+                visitor.visit_pair(key, log::kv::Value::from(value.as_str()))?;
+            }
+        }
+        self.record.visit(visitor)
+    }
+}
+
+/// The key of the sequence number of a record, see [`Logger::sequence_numbers`].
+const SEQUENCE_KEY: &str = "seq";
+
+/// The sequence number of the next record, shared by all the loggers of the process.
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// The sequence number of a record followed by the key-values of the record.
+struct SequenceKvs<'a> {
+    seq: u64,
+    record: WithoutKey<'a>,
+}
+
+impl log::kv::Source for SequenceKvs<'_> {
+    fn visit<'kvs>(
+        &'kvs self,
+        visitor: &mut dyn log::kv::VisitSource<'kvs>,
+    ) -> Result<(), log::kv::Error> {
+        visitor.visit_pair(
+            log::kv::Key::from_str(SEQUENCE_KEY),
+            log::kv::Value::from(self.seq),
+        )?;
+        self.record.visit(visitor)
     }
 }
 
@@ -125,7 +394,132 @@ impl Dispatch {
 /// with the [`log`] crate.
 #[derive(Debug)]
 pub struct Logger {
-    dispatches: Vec<Dispatch>,
+    // shared with the handles, so that the dispatches can be reconfigured once applied
+    dispatches: Arc<RwLock<Arc<Vec<Dispatch>>>>,
+    routes: Vec<Route>,
+    level_routes: Vec<LevelRoute>,
+    capture_panics: bool,
+    startup_banner: bool,
+    filter_cache: Option<Arc<FilterCache>>,
+    error_handler: Option<ErrorHandler>,
+    latency_recorder: Option<LatencyRecorder>,
+    /// The time zone resolved by [`Logger::fallback_time_zone`].
+    time_zone: Option<TimeZone>,
+    layout_error_policy: LayoutErrorPolicy,
+    sequence_numbers: bool,
+    enabled: Arc<AtomicBool>,
+}
+
+/// A handle to reconfigure a [`Logger`] after it's applied, without registering it with [`log`]
+/// again.
+///
+/// The handle is returned by [`Logger::apply`]. It updates the dispatches added by
+/// [`Logger::dispatch`], which are addressed by the order they're added in; the routes are
+/// fixed once the logger is applied. Each update is atomic: a record is dispatched either before
+/// or after it, never in between.
+///
+/// ```rust
+/// use logforth::append;
+/// use logforth::Dispatch;
+/// use logforth::Logger;
+///
+/// let handle = Logger::new()
+///     .dispatch(Dispatch::new().append(append::Stdout::default()))
+///     .apply()
+///     .unwrap();
+///
+/// // after reading the configuration files
+/// handle.add_appender(0, append::Stderr::default());
+/// ```
+#[derive(Debug, Clone)]
+pub struct LoggerHandle {
+    dispatches: Arc<RwLock<Arc<Vec<Dispatch>>>>,
+    filter_cache: Option<Arc<FilterCache>>,
+    enabled: Arc<AtomicBool>,
+}
+
+/// Handles the errors of logforth itself, e.g., an appender failing to write.
+struct ErrorHandler(Box<dyn Fn(&anyhow::Error) + Send + Sync + 'static>);
+
+impl std::fmt::Debug for ErrorHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ErrorHandler {{ ... }}")
+    }
+}
+
+/// Records the time spent dispatching each log record.
+struct LatencyRecorder(Box<LatencyFn>);
+
+type LatencyFn = dyn Fn(&Record, Duration) + Send + Sync + 'static;
+
+impl std::fmt::Debug for LatencyRecorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "LatencyRecorder {{ ... }}")
+    }
+}
+
+/// A [`Dispatch`] that only receives log records of a specific target.
+#[derive(Debug)]
+struct Route {
+    target: String,
+    dispatch: Dispatch,
+}
+
+impl Route {
+    fn matches(&self, target: &str) -> bool {
+        match target.strip_prefix(self.target.as_str()) {
+            Some(rest) => rest.is_empty() || rest.starts_with("::"),
+            None => false,
+        }
+    }
+}
+
+/// A [`Dispatch`] that only receives log records of a range of levels.
+#[derive(Debug)]
+struct LevelRoute {
+    levels: RangeInclusive<Level>,
+    dispatch: Dispatch,
+}
+
+/// A bounded memoization of [`Logger::enabled`] keyed on the target and level of the metadata.
+#[derive(Debug)]
+struct FilterCache {
+    capacity: usize,
+    // one map per level, indexed by `level as usize - 1`
+    entries: RwLock<[HashMap<String, bool>; 5]>,
+}
+
+impl FilterCache {
+    fn new(capacity: usize) -> FilterCache {
+        FilterCache {
+            capacity,
+            entries: RwLock::new(Default::default()),
+        }
+    }
+
+    fn clear(&self) {
+        let mut entries = self.entries.write().unwrap_or_else(PoisonError::into_inner);
+        entries.iter_mut().for_each(HashMap::clear);
+    }
+
+    fn get_or_insert_with(&self, metadata: &Metadata, f: impl FnOnce() -> bool) -> bool {
+        let index = metadata.level() as usize - 1;
+        let entries = self.entries.read().unwrap_or_else(PoisonError::into_inner);
+        if let Some(enabled) = entries[index].get(metadata.target()) {
+            return *enabled;
+        }
+        drop(entries);
+
+        let enabled = f();
+        let mut entries = self.entries.write().unwrap_or_else(PoisonError::into_inner);
+        if entries.iter().map(HashMap::len).sum::<usize>() >= self.capacity {
+            entries.iter_mut().for_each(HashMap::clear);
+        }
+        if self.capacity > 0 {
+            entries[index].insert(metadata.target().to_string(), enabled);
+        }
+        enabled
+    }
 }
 
 impl Default for Logger {
@@ -137,54 +531,617 @@ impl Default for Logger {
 impl Logger {
     /// Create a new [`Logger`] instance.
     pub fn new() -> Logger {
-        Self { dispatches: vec![] }
+        Self {
+            dispatches: Arc::new(RwLock::new(Arc::new(vec![]))),
+            routes: vec![],
+            level_routes: vec![],
+            capture_panics: false,
+            startup_banner: false,
+            filter_cache: None,
+            error_handler: None,
+            latency_recorder: None,
+            time_zone: None,
+            layout_error_policy: LayoutErrorPolicy::default(),
+            sequence_numbers: false,
+            enabled: Arc::new(AtomicBool::new(true)),
+        }
     }
 }
 
 impl Logger {
     /// Add a [`Dispatch`] to the [`Logger`].
-    pub fn dispatch(mut self, dispatch: Dispatch) -> Logger {
-        self.dispatches.push(dispatch);
+    pub fn dispatch(self, dispatch: Dispatch) -> Logger {
+        update(&self.dispatches, |dispatches| dispatches.push(dispatch));
         self
     }
 
+    /// Add a [`Dispatch`] to the [`Logger`] that only receives log records of the given target.
+    ///
+    /// A record's target matches if it equals `target` or is nested under it, e.g., `sql` matches
+    /// both `sql` and `sql::query`. Records that match at least one route are dispatched to the
+    /// matching routes only, and the other records are dispatched to the dispatches added by
+    /// [`Logger::dispatch`]. Dispatches of non-matching routes are skipped without evaluating
+    /// their filters.
+    pub fn route(mut self, target: impl Into<String>, dispatch: Dispatch) -> Logger {
+        self.routes.push(Route {
+            target: target.into(),
+            dispatch,
+        });
+        self
+    }
+
+    /// Add a [`Dispatch`] to the [`Logger`] that only receives log records of the given range of
+    /// levels, e.g., `Level::Error..=Level::Warn` for an alerting sink.
+    ///
+    /// Records whose level falls in at least one level route are dispatched to the matching level
+    /// routes only, and the other records are dispatched to the dispatches added by
+    /// [`Logger::dispatch`]. The level is compared once per route, so the dispatches of
+    /// non-matching routes are skipped without evaluating their filters. Routes by target added
+    /// by [`Logger::route`] take precedence over routes by level.
+    ///
+    /// The bounds can be given in either order, e.g., `Level::Warn..=Level::Error` is the same as
+    /// `Level::Error..=Level::Warn`.
+    pub fn route_levels(mut self, levels: RangeInclusive<Level>, dispatch: Dispatch) -> Logger {
+        let (start, end) = levels.into_inner();
+        let levels = start.min(end)..=start.max(end);
+        self.level_routes.push(LevelRoute { levels, dispatch });
+        self
+    }
+
+    /// Log panics through the [`Logger`] once it's applied.
+    ///
+    /// A panic hook is installed by [`Logger::apply`] that logs the panic message, location, and
+    /// backtrace (if captured) at `Error` level with the target `panic`, flushes all the appenders,
+    /// and then calls the previously registered panic hook.
+    pub fn capture_panics(mut self) -> Logger {
+        self.capture_panics = true;
+        self
+    }
+
+    /// Log a startup banner once the [`Logger`] is applied.
+    ///
+    /// The banner is an `Info` record with the target `logforth` and the message
+    /// `logforth initialized`, carrying the `version`, `pid`, and `host` (if it can be resolved)
+    /// key-values. It's dispatched through the normal pipeline, so it delimits runs in every
+    /// appender.
+    pub fn log_startup_banner(mut self) -> Logger {
+        self.startup_banner = true;
+        self
+    }
+
+    /// Tag each dispatched record with a `seq` key-value, a process-global number that increases
+    /// by one per record, so that lost or reordered records can be detected downstream.
+    ///
+    /// A record passed to several appenders carries the same number in all of them. A `seq`
+    /// key-value of the record itself is replaced.
+    pub fn sequence_numbers(mut self) -> Logger {
+        self.sequence_numbers = true;
+        self
+    }
+
+    /// Memoize whether records are enabled per (target, level) pair, for at most `capacity` pairs.
+    ///
+    /// The filters are evaluated on the metadata once per pair, and then the cached result decides
+    /// whether the records of the pair are enabled. Once the cache is full, it's cleared and
+    /// refilled. The filters that need the whole record are still evaluated for each record.
+    ///
+    /// A cache lookup costs about as much as a few cheap filters, so the cache pays off with many
+    /// dispatches or costly filters, see the `filter_cache` benchmark.
+    ///
+    /// Only enable the cache if the metadata result of all the filters and appenders depends
+    /// solely on the target and level and doesn't change over time. Among the built-in filters,
+    /// [`LevelFilter`], [`TargetLevelFilter`], and [`EnvGateFilter`] decide on the level and
+    /// target, and the others are neutral on the metadata. A [`CustomFilter`] that inspects other
+    /// metadata or changes its answer over time would be bypassed.
+    ///
+    /// [`CustomFilter`]: crate::filter::CustomFilter
+    /// [`EnvGateFilter`]: crate::filter::EnvGateFilter
+    /// [`LevelFilter`]: crate::filter::LevelFilter
+    /// [`TargetLevelFilter`]: crate::filter::TargetLevelFilter
+    pub fn cache_filters(mut self, capacity: usize) -> Logger {
+        self.filter_cache = Some(Arc::new(FilterCache::new(capacity)));
+        self
+    }
+
+    /// Flush only the appenders added with the given name by [`Dispatch::append_named`], e.g., to
+    /// force-flush a file appender without round-tripping a network appender.
+    ///
+    /// Returns `false` if no appender has the given name.
+    pub fn flush_appender(&self, name: &str) -> bool {
+        let mut found = false;
+        for dispatch in snapshot(&self.dispatches).iter() {
+            found |= dispatch.flush_appender(name);
+        }
+        for route in &self.routes {
+            found |= route.dispatch.flush_appender(name);
+        }
+        for route in &self.level_routes {
+            found |= route.dispatch.flush_appender(name);
+        }
+        found
+    }
+
+    /// Handle the errors of logforth itself, e.g., an appender failing to write, with the given
+    /// function instead of printing them to stderr.
+    ///
+    /// This allows routing the errors to syslog, a file, or a metrics counter in daemons without
+    /// stderr.
+    ///
+    /// The errors of a [`RollingFile`] are written off-thread, so they're passed to the handler
+    /// along with the next record it appends. The errors after the last record, i.e., of flushing
+    /// the appender or dropping its [`WorkerGuard`], are still printed to stderr.
+    ///
+    /// [`RollingFile`]: crate::append::rolling_file::RollingFile
+    /// [`WorkerGuard`]: crate::append::rolling_file::WorkerGuard
+    pub fn error_handler(
+        mut self,
+        handler: impl Fn(&anyhow::Error) + Send + Sync + 'static,
+    ) -> Logger {
+        self.error_handler = Some(ErrorHandler(Box::new(handler)));
+        self
+    }
+
+    /// Set what to do with a record when its layout fails to format it. Defaults to
+    /// [`LayoutErrorPolicy::FallbackToText`].
+    pub fn layout_error_policy(mut self, policy: LayoutErrorPolicy) -> Logger {
+        self.layout_error_policy = policy;
+        self
+    }
+
+    /// Measure the time spent dispatching each log record to the appenders, and report it to the
+    /// given function, e.g., to feed a metrics histogram.
+    ///
+    /// This allows detecting when logging itself becomes a bottleneck. The clock is read only if
+    /// a recorder is set, so there's no overhead otherwise.
+    pub fn latency_recorder(
+        mut self,
+        recorder: impl Fn(&Record, Duration) + Send + Sync + 'static,
+    ) -> Logger {
+        self.latency_recorder = Some(LatencyRecorder(Box::new(recorder)));
+        self
+    }
+
+    /// Use the given time zone for the timestamps of log records if the system time zone can't be
+    /// determined, e.g., in a container without `/etc/localtime` or `TZ`, instead of falling
+    /// back to UTC.
+    ///
+    /// This allows operators to pin the offset they know their hosts run at. The system time zone
+    /// is resolved once here rather than for every record, and a warning is printed to stderr if
+    /// the fallback is used. Layouts with a time zone of their own aren't affected.
+    pub fn fallback_time_zone(mut self, tz: TimeZone) -> Logger {
+        self.time_zone = Some(system_time_zone(TimeZone::try_system(), &tz));
+        self
+    }
+
+    /// Turn the [`Logger`] back on after [`Logger::disable`].
+    pub fn enable(&self) {
+        self.enabled.store(true, Ordering::Relaxed);
+    }
+
+    /// Turn the [`Logger`] off, so that all log records are dropped before evaluating any filter
+    /// or appender, e.g., as an ops kill switch. Unlike setting all levels to `Off`, this costs a
+    /// single atomic load per record.
+    pub fn disable(&self) {
+        self.enabled.store(false, Ordering::Relaxed);
+    }
+
     /// Set up the global logger with the [`Logger`] instance.
     ///
+    /// Returns a [`LoggerHandle`] to reconfigure the logger later, e.g., after reading the
+    /// configuration files.
+    ///
     /// # Errors
     ///
     /// An error is returned if the global logger has already been set.
-    pub fn apply(self) -> Result<(), log::SetLoggerError> {
+    pub fn apply(self) -> Result<LoggerHandle, log::SetLoggerError> {
+        let capture_panics = self.capture_panics;
+        let startup_banner = self.startup_banner;
+        let handle = self.handle();
         log::set_boxed_logger(Box::new(self))?;
         log::set_max_level(LevelFilter::Trace);
-        Ok(())
+        if capture_panics {
+            install_panic_hook(log::logger());
+        }
+        if startup_banner {
+            log_startup_banner(log::logger());
+        }
+        Ok(handle)
+    }
+}
+
+impl Logger {
+    fn handle(&self) -> LoggerHandle {
+        LoggerHandle {
+            dispatches: self.dispatches.clone(),
+            filter_cache: self.filter_cache.clone(),
+            enabled: self.enabled.clone(),
+        }
+    }
+
+    fn dispatches<'a>(
+        &'a self,
+        dispatches: &'a [Dispatch],
+        metadata: &'a Metadata,
+    ) -> impl Iterator<Item = &'a Dispatch> + 'a {
+        let target = metadata.target();
+        let level = metadata.level();
+        let routed = self.routes.iter().any(|route| route.matches(target));
+        let level_routed = !routed
+            && self
+                .level_routes
+                .iter()
+                .any(|route| route.levels.contains(&level));
+        let routes = self
+            .routes
+            .iter()
+            .filter(move |route| routed && route.matches(target))
+            .map(|route| &route.dispatch);
+        let level_routes = self
+            .level_routes
+            .iter()
+            .filter(move |route| level_routed && route.levels.contains(&level))
+            .map(|route| &route.dispatch);
+        let dispatches = dispatches.iter().filter(move |_| !routed && !level_routed);
+        routes.chain(level_routes).chain(dispatches)
+    }
+}
+
+impl LoggerHandle {
+    /// Add a [`Dispatch`] to the logger.
+    pub fn add_dispatch(&self, dispatch: Dispatch) {
+        self.update(|dispatches| dispatches.push(dispatch));
+    }
+
+    /// Add an [`Append`] to the `index`-th dispatch of the logger.
+    ///
+    /// Returns `false` if the logger has no such dispatch.
+    pub fn add_appender(&self, index: usize, append: impl Append) -> bool {
+        self.update(|dispatches| match dispatches.get_mut(index) {
+            Some(dispatch) => {
+                dispatch.appends.push(Arc::new(Appender {
+                    name: None,
+                    append: Box::new(append),
+                    layout: None,
+                    enabled: AtomicBool::new(true),
+                    retired: AtomicBool::new(false),
+                    fell_back: AtomicBool::new(false),
+                }));
+                true
+            }
+            None => false,
+        })
+    }
+
+    /// Replace the [`Append`] named `name` by [`Dispatch::append_named`] in the `index`-th
+    /// dispatch of the logger, e.g., to move a file appender to another directory. The new
+    /// appender keeps the name and the layout of the old one.
+    ///
+    /// The old appender is flushed once the new one has taken over and the records dispatched to
+    /// it before are appended, so that the records queued by an asynchronous appender are written
+    /// to the old target rather than dropped. Logging doesn't block while it's drained.
+    ///
+    /// Returns `false` if the logger has no such dispatch or appender.
+    pub fn replace_appender(&self, index: usize, name: &str, append: impl Append) -> bool {
+        let old = self.update(|dispatches| {
+            let appender = dispatches
+                .get_mut(index)?
+                .appends
+                .iter_mut()
+                .find(|appender| appender.name.as_deref() == Some(name))?;
+            let new = Arc::new(Appender {
+                name: appender.name.clone(),
+                append: Box::new(append),
+                layout: appender.layout.clone(),
+                enabled: AtomicBool::new(appender.enabled.load(Ordering::Relaxed)),
+                retired: AtomicBool::new(false),
+                fell_back: AtomicBool::new(false),
+            });
+            Some(std::mem::replace(appender, new))
+        });
+        // flushed on drop, outside the lock, by the last snapshot still appending to it
+        old.map(|old| old.retired.store(true, Ordering::Relaxed))
+            .is_some()
+    }
+
+    /// Replace the filters of the `index`-th dispatch of the logger.
+    ///
+    /// Returns `false` if the logger has no such dispatch.
+    pub fn set_filters(&self, index: usize, filters: Vec<Filter>) -> bool {
+        self.update(|dispatches| match dispatches.get_mut(index) {
+            Some(dispatch) => {
+                dispatch.filters = filters.into_iter().map(Arc::new).collect();
+                true
+            }
+            None => false,
+        })
+    }
+
+    /// Turn the appenders named `name` by [`Dispatch::append_named`] back on after
+    /// [`LoggerHandle::disable_appender`].
+    ///
+    /// Returns `false` if no appender of the dispatches has the given name.
+    pub fn enable_appender(&self, name: &str) -> bool {
+        self.set_appender_enabled(name, true)
+    }
+
+    /// Stop passing records to the appenders named `name` by [`Dispatch::append_named`], e.g., to
+    /// compare log destinations, while the other appenders keep receiving them. The appenders of
+    /// the routes are fixed once the logger is applied and can't be disabled.
+    ///
+    /// Returns `false` if no appender of the dispatches has the given name.
+    pub fn disable_appender(&self, name: &str) -> bool {
+        self.set_appender_enabled(name, false)
+    }
+
+    /// Turn the logger back on after [`LoggerHandle::disable`].
+    pub fn enable(&self) {
+        self.enabled.store(true, Ordering::Relaxed);
+    }
+
+    /// Turn the logger off, see [`Logger::disable`].
+    pub fn disable(&self) {
+        self.enabled.store(false, Ordering::Relaxed);
+    }
+
+    fn set_appender_enabled(&self, name: &str, enabled: bool) -> bool {
+        // the flags are atomic, so the dispatches are only read
+        let mut found = false;
+        for dispatch in snapshot(&self.dispatches).iter() {
+            found |= dispatch.set_appender_enabled(name, enabled);
+        }
+        found
+    }
+
+    fn update<R>(&self, f: impl FnOnce(&mut Vec<Dispatch>) -> R) -> R {
+        update(&self.dispatches, |dispatches| {
+            let result = f(dispatches);
+            // clear the cache while holding the lock, so that no result of the old dispatches is
+            // cached afterward
+            if let Some(cache) = &self.filter_cache {
+                cache.clear();
+            }
+            result
+        })
+    }
+}
+
+#[cfg(feature = "rolling_file")]
+thread_local! {
+    static DISPATCH_TIME: std::cell::RefCell<Option<Zoned>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+/// The timestamp of the record being dispatched on the current thread, which the layouts render,
+/// so that an appender, e.g., a rolling file, can decide by the same instant.
+#[cfg(feature = "rolling_file")]
+pub(crate) fn dispatch_time() -> Option<Zoned> {
+    DISPATCH_TIME.with(|time| time.borrow().clone())
+}
+
+/// Sets the dispatch time of the current thread until dropped, restoring the time of an outer
+/// record for a record logged while dispatching it.
+#[cfg(feature = "rolling_file")]
+struct DispatchTimeGuard(Option<Zoned>);
+
+#[cfg(feature = "rolling_file")]
+impl DispatchTimeGuard {
+    fn new(now: &Zoned) -> DispatchTimeGuard {
+        DispatchTimeGuard(DISPATCH_TIME.with(|time| time.replace(Some(now.clone()))))
     }
 }
 
+#[cfg(feature = "rolling_file")]
+impl Drop for DispatchTimeGuard {
+    fn drop(&mut self) {
+        DISPATCH_TIME.with(|time| *time.borrow_mut() = self.0.take());
+    }
+}
+
+fn read(dispatches: &RwLock<Arc<Vec<Dispatch>>>) -> RwLockReadGuard<'_, Arc<Vec<Dispatch>>> {
+    dispatches.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The current dispatches, taken without holding the lock while the records are appended, so
+/// that an appender or a layout logging again, e.g., through the panic hook, can't deadlock with
+/// an update waiting for the lock.
+fn snapshot(dispatches: &RwLock<Arc<Vec<Dispatch>>>) -> Arc<Vec<Dispatch>> {
+    read(dispatches).clone()
+}
+
+/// Publishes an updated copy of the dispatches, leaving the snapshots taken before intact.
+fn update<R>(
+    dispatches: &RwLock<Arc<Vec<Dispatch>>>,
+    f: impl FnOnce(&mut Vec<Dispatch>) -> R,
+) -> R {
+    let mut dispatches = dispatches.write().unwrap_or_else(PoisonError::into_inner);
+    let mut updated = dispatches.iter().map(Dispatch::share).collect();
+    let result = f(&mut updated);
+    *dispatches = Arc::new(updated);
+    result
+}
+
 impl log::Log for Logger {
     fn enabled(&self, metadata: &Metadata) -> bool {
-        self.dispatches
-            .iter()
-            .any(|dispatch| dispatch.enabled(metadata))
+        if !self.enabled.load(Ordering::Relaxed) {
+            return false;
+        }
+
+        // hold the lock while caching, see `LoggerHandle::update`
+        let dispatches = read(&self.dispatches);
+        let enabled = || {
+            self.dispatches(&dispatches, metadata)
+                .any(|dispatch| dispatch.enabled(metadata))
+        };
+        match &self.filter_cache {
+            Some(cache) => cache.get_or_insert_with(metadata, enabled),
+            None => enabled(),
+        }
     }
 
     fn log(&self, record: &Record) {
-        for dispatch in &self.dispatches {
-            if dispatch.enabled(record.metadata()) {
-                if let Err(err) = dispatch.log(record) {
-                    handle_error(record, err);
+        if !self.enabled.load(Ordering::Relaxed) {
+            return;
+        }
+        if self.filter_cache.is_some() && !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let start = self.latency_recorder.as_ref().map(|_| Instant::now());
+
+        // capture the timestamp once so that all the dispatches render the same instant
+        let now = match &self.time_zone {
+            Some(tz) => Zoned::new(Timestamp::now(), tz.clone()),
+            None => Zoned::now(),
+        };
+        #[cfg(feature = "rolling_file")]
+        let _dispatch_time = DispatchTimeGuard::new(&now);
+        // numbered once dispatched, so that a gap means a lost record rather than a filtered one
+        let mut seq = None;
+        let dispatches = snapshot(&self.dispatches);
+        for dispatch in self.dispatches(&dispatches, record.metadata()) {
+            if dispatch.enabled_record(record) {
+                let result = match self.sequence_numbers {
+                    true => {
+                        let seq =
+                            *seq.get_or_insert_with(|| SEQUENCE.fetch_add(1, Ordering::Relaxed));
+                        let kvs = SequenceKvs {
+                            seq,
+                            record: WithoutKey {
+                                source: record.key_values(),
+                                keys: &[SEQUENCE_KEY],
+                            },
+                        };
+                        let record = record.to_builder().key_values(&kvs).build();
+                        dispatch.log(&record, &now, self.layout_error_policy)
+                    }
+                    false => dispatch.log(record, &now, self.layout_error_policy),
+                };
+                if let Err(err) = result {
+                    match &self.error_handler {
+                        Some(handler) => (handler.0)(&err),
+                        None => handle_error(record, err),
+                    }
                 }
             }
         }
+        drop(dispatches);
+
+        if let (Some(recorder), Some(start)) = (&self.latency_recorder, start) {
+            (recorder.0)(record, start.elapsed());
+        }
     }
 
     fn flush(&self) {
-        for dispatch in &self.dispatches {
+        for dispatch in snapshot(&self.dispatches).iter() {
             dispatch.flush();
         }
+        for route in &self.routes {
+            route.dispatch.flush();
+        }
+        for route in &self.level_routes {
+            route.dispatch.flush();
+        }
     }
 }
 
-// TODO(tisonkun): logback and log4j2 support custom error handling (status listener).
+fn log_startup_banner(logger: &dyn log::Log) {
+    let mut kvs = vec![
+        ("version", log::kv::Value::from(env!("CARGO_PKG_VERSION"))),
+        ("pid", log::kv::Value::from(pid())),
+    ];
+    if let Some(host) = hostname() {
+        kvs.push(("host", log::kv::Value::from(host)));
+    }
+
+    logger.log(
+        &Record::builder()
+            .level(log::Level::Info)
+            .target("logforth")
+            .args(format_args!("logforth initialized"))
+            .key_values(&kvs)
+            .build(),
+    );
+}
+
+/// The process ID, resolved once and then cached.
+fn pid() -> u32 {
+    static PID: OnceLock<u32> = OnceLock::new();
+    *PID.get_or_init(std::process::id)
+}
+
+/// The hostname, resolved once and then cached.
+fn hostname() -> Option<&'static str> {
+    static HOSTNAME: OnceLock<Option<String>> = OnceLock::new();
+    cached(&HOSTNAME, resolve_hostname)
+}
+
+fn cached(
+    cell: &OnceLock<Option<String>>,
+    resolve: impl FnOnce() -> Option<String>,
+) -> Option<&str> {
+    cell.get_or_init(resolve).as_deref()
+}
+
+fn resolve_hostname() -> Option<String> {
+    let hostname = std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())?;
+    let hostname = hostname.trim();
+    (!hostname.is_empty()).then(|| hostname.to_string())
+}
+
+fn install_panic_hook(logger: &'static dyn log::Log) {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        log_panic(logger, info.payload(), info.location());
+        previous(info);
+    }));
+}
+
+pub(crate) fn log_panic(
+    logger: &dyn log::Log,
+    payload: &(dyn Any + Send),
+    location: Option<&Location>,
+) {
+    let message = if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.as_str()
+    } else {
+        "Box<dyn Any>"
+    };
+
+    let backtrace = Backtrace::capture();
+    let mut builder = Record::builder();
+    builder
+        .level(log::Level::Error)
+        .target("panic")
+        .file(location.map(|location| location.file()))
+        .line(location.map(|location| location.line()));
+    if backtrace.status() == BacktraceStatus::Captured {
+        logger.log(
+            &builder
+                .args(format_args!("panicked: {message}\n{backtrace}"))
+                .build(),
+        );
+    } else {
+        logger.log(&builder.args(format_args!("panicked: {message}")).build());
+    }
+    logger.flush();
+}
+
+/// The system time zone, or the fallback if it can't be determined.
+fn system_time_zone(system: Result<TimeZone, jiff::Error>, fallback: &TimeZone) -> TimeZone {
+    static WARN: Once = Once::new();
+    system.unwrap_or_else(|err| {
+        WARN.call_once(|| {
+            eprintln!("failed to get the system time zone, falling back to {fallback:?}: {err}");
+        });
+        fallback.clone()
+    })
+}
+
 fn handle_error(record: &Record, error: anyhow::Error) {
     let Err(fallback_error) = write!(
         std::io::stderr(),
@@ -215,3 +1172,716 @@ Error performing stderr logging after error occurred during regular logging.
         fallback_error = fallback_error,
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Arguments;
+    use std::fs;
+    use std::fs::File;
+    use std::io::Write;
+    use std::path::Path;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Mutex;
+
+    use log::Log;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::filter::CustomFilter;
+    use crate::filter::FilePathFilter;
+    use crate::layout::KvDisplay;
+
+    #[derive(Debug, Default, Clone)]
+    struct CollectAppend(Arc<Mutex<Vec<String>>>);
+
+    impl CollectAppend {
+        fn records(&self) -> Vec<String> {
+            self.0.lock().unwrap().clone()
+        }
+    }
+
+    impl Append for CollectAppend {
+        fn append(&self, record: &Record) -> anyhow::Result<()> {
+            let mut records = self.0.lock().unwrap();
+            let kvs = KvDisplay::new(record.key_values());
+            records.push(format!("{} {}{kvs}", record.level(), record.args()));
+            Ok(())
+        }
+    }
+
+    #[derive(Debug)]
+    struct FileAppend(Mutex<File>);
+
+    impl FileAppend {
+        fn new(path: &Path) -> FileAppend {
+            FileAppend(Mutex::new(File::create(path).unwrap()))
+        }
+    }
+
+    impl Append for FileAppend {
+        fn append(&self, record: &Record) -> anyhow::Result<()> {
+            writeln!(self.0.lock().unwrap(), "{}", record.args())?;
+            Ok(())
+        }
+    }
+
+    fn log(logger: &Logger, target: &str, message: &str) {
+        logger.log(
+            &Record::builder()
+                .target(target)
+                .level(log::Level::Info)
+                .args(format_args!("{message}"))
+                .build(),
+        );
+    }
+
+    #[test]
+    fn test_route_by_target() {
+        let temp_dir = TempDir::new().expect("failed to create a temporary directory");
+        let sql_path = temp_dir.path().join("sql.log");
+        let app_path = temp_dir.path().join("app.log");
+
+        let logger = Logger::new()
+            .route("sql", Dispatch::new().append(FileAppend::new(&sql_path)))
+            .dispatch(Dispatch::new().append(FileAppend::new(&app_path)));
+
+        log(&logger, "sql", "select");
+        log(&logger, "sql::query", "insert");
+        log(&logger, "sqlx", "connect");
+        log(&logger, "app", "start");
+
+        assert_eq!(fs::read_to_string(&sql_path).unwrap(), "select\ninsert\n");
+        assert_eq!(fs::read_to_string(&app_path).unwrap(), "connect\nstart\n");
+    }
+
+    #[test]
+    fn test_capture_panics() {
+        let append = CollectAppend::default();
+        let logger: &'static Logger = Box::leak(Box::new(
+            Logger::new().dispatch(Dispatch::new().append(append.clone())),
+        ));
+        // the hook is process-wide, so restore the one of the test harness afterward
+        let original = std::panic::take_hook();
+        install_panic_hook(logger);
+
+        let result = std::panic::catch_unwind(|| panic!("boom"));
+        drop(std::panic::take_hook());
+        std::panic::set_hook(original);
+        assert!(result.is_err());
+
+        let records = append.records();
+        assert!(
+            records
+                .iter()
+                .any(|record| record.starts_with("ERROR panicked: boom")),
+            "{records:?}"
+        );
+    }
+
+    #[test]
+    fn test_startup_banner() {
+        let append = CollectAppend::default();
+        let logger = Logger::new().dispatch(Dispatch::new().append(append.clone()));
+        log_startup_banner(&logger);
+
+        let records = append.records();
+        assert_eq!(records.len(), 1);
+        let expected = format!(
+            "INFO logforth initialized version={} pid={}",
+            env!("CARGO_PKG_VERSION"),
+            std::process::id()
+        );
+        assert!(records[0].starts_with(&expected), "{records:?}");
+    }
+
+    #[test]
+    fn test_static_kvs() {
+        let append = CollectAppend::default();
+        let logger = Logger::new().dispatch(
+            Dispatch::new()
+                .append(append.clone())
+                .kv("service", "api")
+                .kv("env", "prod"),
+        );
+
+        logger.log(&Record::builder().args(format_args!("hello")).build());
+        let kvs = [("env", log::kv::Value::from("staging"))];
+        logger.log(
+            &Record::builder()
+                .args(format_args!("world"))
+                .key_values(&kvs)
+                .build(),
+        );
+
+        assert_eq!(
+            append.records(),
+            [
+                "INFO hello service=api env=prod",
+                "INFO world service=api env=staging"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_filter_by_file_path() {
+        let append = CollectAppend::default();
+        let logger = Logger::new().dispatch(
+            Dispatch::new()
+                .filter(FilePathFilter::new(["src/generated/"]))
+                .append(append.clone()),
+        );
+
+        for file in ["src/generated/api.rs", "src/main.rs"] {
+            logger.log(
+                &Record::builder()
+                    .file(Some(file))
+                    .args(format_args!("{file}"))
+                    .build(),
+            );
+        }
+        assert_eq!(append.records(), ["INFO src/main.rs"]);
+    }
+
+    #[test]
+    fn test_filter_cache() {
+        let evaluated = Arc::new(AtomicUsize::new(0));
+        let filter = {
+            let evaluated = evaluated.clone();
+            CustomFilter::new(move |metadata| {
+                evaluated.fetch_add(1, Ordering::SeqCst);
+                if metadata.level() <= log::Level::Info {
+                    FilterResult::Neutral
+                } else {
+                    FilterResult::Reject
+                }
+            })
+        };
+        let append = CollectAppend::default();
+        let logger = Logger::new()
+            .cache_filters(2)
+            .dispatch(Dispatch::new().filter(filter).append(append.clone()));
+
+        let debug = |target| {
+            Metadata::builder()
+                .level(log::Level::Debug)
+                .target(target)
+                .build()
+        };
+        assert!(!logger.enabled(&debug("a")));
+        assert!(!logger.enabled(&debug("a")));
+        assert_eq!(evaluated.load(Ordering::SeqCst), 1);
+
+        // the cache is full, so it's cleared before caching the new pair
+        assert!(!logger.enabled(&debug("b")));
+        assert!(!logger.enabled(&debug("c")));
+        assert!(!logger.enabled(&debug("a")));
+        assert_eq!(evaluated.load(Ordering::SeqCst), 4);
+
+        log(&logger, "a", "hello");
+        log(&logger, "a", "world");
+        assert_eq!(append.records(), ["INFO hello", "INFO world"]);
+
+        // a newly configured logger doesn't reuse the cached results
+        let logger = Logger::new()
+            .cache_filters(2)
+            .dispatch(Dispatch::new().append(append.clone()));
+        assert!(logger.enabled(&debug("a")));
+    }
+
+    #[test]
+    fn test_hostname_resolved_once() {
+        let append = CollectAppend::default();
+        let logger = Logger::new().dispatch(Dispatch::new().append(append.clone()));
+        for _ in 0..100 {
+            log_startup_banner(&logger);
+        }
+
+        // every record renders the values cached by the first one
+        let mut expected = format!(" pid={}", std::process::id());
+        if let Some(host) = hostname() {
+            assert!(std::ptr::eq(host, hostname().unwrap()));
+            expected.push_str(&format!(" host={host}"));
+        }
+        let records = append.records();
+        assert_eq!(records.len(), 100);
+        assert!(
+            records.iter().all(|record| record.ends_with(&expected)),
+            "{records:?}"
+        );
+    }
+
+    #[test]
+    fn test_flush_appender() {
+        #[derive(Debug, Default, Clone)]
+        struct FlushCount(Arc<AtomicUsize>);
+
+        impl Append for FlushCount {
+            fn append(&self, _: &Record) -> anyhow::Result<()> {
+                Ok(())
+            }
+
+            fn flush(&self) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let file = FlushCount::default();
+        let http = FlushCount::default();
+        let logger = Logger::new().dispatch(
+            Dispatch::new()
+                .append_named("file", file.clone())
+                .append_named("http", http.clone()),
+        );
+
+        assert!(logger.flush_appender("file"));
+        assert!(!logger.flush_appender("missing"));
+        assert_eq!(file.0.load(Ordering::SeqCst), 1);
+        assert_eq!(http.0.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_disable_appender() {
+        let file = CollectAppend::default();
+        let http = CollectAppend::default();
+        let logger = Logger::new().dispatch(
+            Dispatch::new()
+                .append_named("file", file.clone())
+                .append_named("http", http.clone()),
+        );
+        let handle = logger.handle();
+
+        log(&logger, "app", "both");
+        assert!(handle.disable_appender("http"));
+        assert!(!handle.disable_appender("missing"));
+        log(&logger, "app", "file only");
+        assert!(handle.enable_appender("http"));
+        log(&logger, "app", "both again");
+
+        assert_eq!(
+            file.records(),
+            vec!["INFO both", "INFO file only", "INFO both again"]
+        );
+        assert_eq!(http.records(), vec!["INFO both", "INFO both again"]);
+    }
+
+    #[test]
+    fn test_layout_error_policy() {
+        let failing = || {
+            crate::layout::CustomLayout::new(|record: &Record, f: &dyn Fn(Arguments) -> _| {
+                if record.args().to_string() == "bad" {
+                    anyhow::bail!("bad time format");
+                }
+                f(format_args!("{}", record.args()))
+            })
+        };
+
+        let policies = [
+            (LayoutErrorPolicy::DropRecord, vec!["INFO good"], 0),
+            // the first failure of each appender is reported
+            (
+                LayoutErrorPolicy::FallbackToText,
+                vec!["INFO good", "INFO INFO app: bad", "INFO INFO app: bad"],
+                1,
+            ),
+            (
+                LayoutErrorPolicy::PropagateToErrorHandler,
+                vec!["INFO good"],
+                2,
+            ),
+        ];
+        for (policy, expected, errors) in policies {
+            let append = CollectAppend::default();
+            let handled = Arc::new(AtomicUsize::new(0));
+            let logger = Logger::new()
+                .dispatch(Dispatch::new().layout(failing()).append(append.clone()))
+                .layout_error_policy(policy)
+                .error_handler({
+                    let handled = handled.clone();
+                    move |_| {
+                        handled.fetch_add(1, Ordering::SeqCst);
+                    }
+                });
+
+            log(&logger, "app", "good");
+            log(&logger, "app", "bad");
+            log(&logger, "app", "bad");
+            assert_eq!(append.records(), expected, "{policy:?}");
+            assert_eq!(handled.load(Ordering::SeqCst), errors, "{policy:?}");
+        }
+    }
+
+    #[test]
+    fn test_layout_error_after_append() {
+        let append = CollectAppend::default();
+        let logger = Logger::new().dispatch(
+            Dispatch::new()
+                .layout(crate::layout::CustomLayout::new(
+                    |record: &Record, f: &dyn Fn(Arguments) -> _| {
+                        f(format_args!("{}", record.args()))?;
+                        anyhow::bail!("failed after appending")
+                    },
+                ))
+                .append(append.clone()),
+        );
+
+        // the record is already appended, so there's nothing to fall back for
+        log(&logger, "app", "hello");
+        assert_eq!(append.records(), ["INFO hello"]);
+    }
+
+    #[test]
+    fn test_sequence_numbers() {
+        let file = CollectAppend::default();
+        let http = CollectAppend::default();
+        let logger = Logger::new()
+            .dispatch(
+                Dispatch::new()
+                    .filter(LevelFilter::Info)
+                    .append(file.clone())
+                    .append(http.clone()),
+            )
+            .sequence_numbers();
+
+        for n in 0..5 {
+            log(&logger, "app", &n.to_string());
+            // filtered out, so not numbered
+            logger.log(&Record::builder().level(Level::Debug).build());
+        }
+
+        let seqs = |append: &CollectAppend| {
+            let records = append.records();
+            records
+                .iter()
+                .map(|record| record.split("seq=").nth(1).unwrap().parse::<u64>().unwrap())
+                .collect::<Vec<_>>()
+        };
+        let seqs_file = seqs(&file);
+        assert_eq!(seqs_file.len(), 5);
+        assert!(
+            seqs_file.windows(2).all(|w| w[1] == w[0] + 1),
+            "{seqs_file:?}"
+        );
+        assert_eq!(seqs_file, seqs(&http));
+    }
+
+    #[test]
+    #[cfg(feature = "rolling_file")]
+    fn test_dispatch_time() {
+        use crate::layout::TextLayout;
+
+        type Timed = Vec<(String, Option<Zoned>)>;
+
+        /// Renders the record along with the dispatch time seen by the appender.
+        #[derive(Debug, Default, Clone)]
+        struct TimedAppend(Arc<Mutex<Timed>>);
+
+        impl Append for TimedAppend {
+            fn append(&self, record: &Record) -> anyhow::Result<()> {
+                let line = record.args().to_string();
+                self.0.lock().unwrap().push((line, dispatch_time()));
+                Ok(())
+            }
+        }
+
+        let append = TimedAppend::default();
+        let logger = Logger::new().dispatch(
+            Dispatch::new()
+                .layout(TextLayout {
+                    omit_module: true,
+                    ..Default::default()
+                })
+                .append(append.clone()),
+        );
+        log(&logger, "app", "hello");
+        assert_eq!(dispatch_time(), None);
+
+        let records = append.0.lock().unwrap();
+        let (line, time) = &records[0];
+        let time = time.as_ref().unwrap();
+        let rendered = time.strftime("%Y-%m-%dT%H:%M:%S.%6f%:z").to_string();
+        assert!(line.starts_with(&rendered), "{line:?} {rendered:?}");
+    }
+
+    #[test]
+    fn test_shared_layout() {
+        let layout = Arc::new(Layout::from(crate::layout::CustomLayout::new(
+            |record, f| f(format_args!("[{}] {}", record.target(), record.args())),
+        )));
+        let stdout = CollectAppend::default();
+        let file = CollectAppend::default();
+        let logger = Logger::new()
+            .dispatch(
+                Dispatch::new()
+                    .shared_layout(layout.clone())
+                    .append(stdout.clone()),
+            )
+            .dispatch(
+                Dispatch::new()
+                    .shared_layout(layout.clone())
+                    .append(file.clone()),
+            );
+        assert_eq!(Arc::strong_count(&layout), 3);
+
+        log(&logger, "app", "hello");
+        assert_eq!(stdout.records(), ["INFO [app] hello"]);
+        assert_eq!(stdout.records(), file.records());
+    }
+
+    #[test]
+    fn test_error_handler() {
+        #[derive(Debug)]
+        struct FailingAppend;
+
+        impl Append for FailingAppend {
+            fn append(&self, _: &Record) -> anyhow::Result<()> {
+                Err(anyhow::anyhow!("disk full"))
+            }
+        }
+
+        let errors = Arc::new(Mutex::new(vec![]));
+        let logger = Logger::new()
+            .error_handler({
+                let errors = errors.clone();
+                move |err| errors.lock().unwrap().push(err.to_string())
+            })
+            .dispatch(Dispatch::new().append(FailingAppend));
+
+        log(&logger, "app", "hello");
+        assert_eq!(*errors.lock().unwrap(), ["disk full"]);
+    }
+
+    #[test]
+    fn test_latency_recorder() {
+        #[derive(Debug)]
+        struct SlowAppend;
+
+        impl Append for SlowAppend {
+            fn append(&self, _: &Record) -> anyhow::Result<()> {
+                std::thread::sleep(Duration::from_millis(10));
+                Ok(())
+            }
+        }
+
+        let samples = Arc::new(Mutex::new(vec![]));
+        let logger = Logger::new()
+            .latency_recorder({
+                let samples = samples.clone();
+                move |record, latency| {
+                    samples
+                        .lock()
+                        .unwrap()
+                        .push((record.args().to_string(), latency))
+                }
+            })
+            .dispatch(Dispatch::new().append(SlowAppend));
+
+        log(&logger, "app", "hello");
+        let samples = samples.lock().unwrap();
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].0, "hello");
+        assert!(samples[0].1 >= Duration::from_millis(10));
+    }
+
+    #[test]
+    fn test_fallback_time_zone() {
+        let fallback = TimeZone::fixed(jiff::tz::offset(8));
+        let unknown = TimeZone::get("Unknown/Zone");
+        assert_eq!(system_time_zone(unknown, &fallback), fallback);
+
+        let system = TimeZone::UTC;
+        assert_eq!(system_time_zone(Ok(system.clone()), &fallback), system);
+
+        // resolved once when the logger is built, not for every record
+        let logger = Logger::new().fallback_time_zone(fallback.clone());
+        let resolved = system_time_zone(TimeZone::try_system(), &fallback);
+        assert_eq!(logger.time_zone, Some(resolved));
+    }
+
+    #[test]
+    fn test_disable() {
+        let append = CollectAppend::default();
+        let logger = Logger::new().dispatch(Dispatch::new().append(append.clone()));
+
+        logger.disable();
+        assert!(!logger.enabled(&Metadata::builder().build()));
+        log(&logger, "app", "dropped");
+        assert!(append.records().is_empty());
+
+        logger.enable();
+        log(&logger, "app", "hello");
+        assert_eq!(append.records(), ["INFO hello"]);
+    }
+
+    #[test]
+    fn test_handle() {
+        let first = CollectAppend::default();
+        let second = CollectAppend::default();
+        let logger = Logger::new()
+            .cache_filters(16)
+            .dispatch(Dispatch::new().append(first.clone()));
+        let handle = logger.handle();
+
+        log(&logger, "app", "hello");
+        assert!(handle.add_appender(0, second.clone()));
+        assert!(!handle.add_appender(1, CollectAppend::default()));
+        log(&logger, "app", "world");
+        assert_eq!(first.records(), ["INFO hello", "INFO world"]);
+        assert_eq!(second.records(), ["INFO world"]);
+
+        // the cached result of the old filters is invalidated
+        assert!(handle.set_filters(0, vec![LevelFilter::Warn.into()]));
+        log(&logger, "app", "dropped");
+        assert_eq!(second.records(), ["INFO world"]);
+    }
+
+    #[test]
+    fn test_replace_appender() {
+        /// Queues the records until flushed, like an asynchronous appender.
+        #[derive(Debug, Default, Clone)]
+        struct QueuedAppend {
+            queued: Arc<Mutex<Vec<usize>>>,
+            written: Arc<Mutex<Vec<usize>>>,
+        }
+
+        impl Append for QueuedAppend {
+            fn append(&self, record: &Record) -> anyhow::Result<()> {
+                let n = record.args().to_string().parse()?;
+                self.queued.lock().unwrap().push(n);
+                Ok(())
+            }
+
+            fn flush(&self) {
+                let mut queued = self.queued.lock().unwrap();
+                self.written.lock().unwrap().append(&mut queued);
+            }
+        }
+
+        let old = QueuedAppend::default();
+        let new = QueuedAppend::default();
+        let logger = Logger::new().dispatch(Dispatch::new().append_named("file", old.clone()));
+        let handle = logger.handle();
+
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                for n in 0..1000 {
+                    log(&logger, "app", &n.to_string());
+                }
+            });
+            assert!(handle.replace_appender(0, "file", new.clone()));
+        });
+        assert!(!handle.replace_appender(0, "unknown", QueuedAppend::default()));
+        assert!(!handle.replace_appender(1, "file", QueuedAppend::default()));
+        logger.flush();
+
+        // the old appender is drained, and all the records after the swap go to the new one
+        assert!(old.queued.lock().unwrap().is_empty());
+        let mut records = old.written.lock().unwrap().clone();
+        records.extend(new.written.lock().unwrap().iter());
+        assert_eq!(records, (0..1000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_update_while_appending() {
+        /// Adds an appender through the handle while appending, like a panic hook logging again.
+        #[derive(Debug, Default, Clone)]
+        struct UpdateAppend {
+            handle: Arc<OnceLock<LoggerHandle>>,
+            added: CollectAppend,
+        }
+
+        impl Append for UpdateAppend {
+            fn append(&self, _: &Record) -> anyhow::Result<()> {
+                if let Some(handle) = self.handle.get() {
+                    assert!(handle.add_appender(0, self.added.clone()));
+                }
+                Ok(())
+            }
+        }
+
+        let append = UpdateAppend::default();
+        let logger = Logger::new().dispatch(Dispatch::new().append(append.clone()));
+        append.handle.set(logger.handle()).unwrap();
+
+        // the dispatches aren't locked while the records are appended
+        log(&logger, "app", "hello");
+        log(&logger, "app", "world");
+        assert_eq!(append.added.records(), ["INFO world"]);
+    }
+
+    #[test]
+    fn test_append_with_layout() {
+        use crate::layout::TextLayout;
+
+        let utc = CollectAppend::default();
+        let shanghai = CollectAppend::default();
+        let logger = Logger::new().dispatch(
+            Dispatch::new()
+                .append_with_layout(
+                    utc.clone(),
+                    TextLayout {
+                        tz: Some(jiff::tz::TimeZone::UTC),
+                        ..Default::default()
+                    },
+                )
+                .append_with_layout(
+                    shanghai.clone(),
+                    TextLayout {
+                        tz: Some(jiff::tz::TimeZone::fixed(jiff::tz::offset(8))),
+                        ..Default::default()
+                    },
+                ),
+        );
+
+        log(&logger, "app", "hello");
+        let offset = |records: Vec<String>| {
+            let timestamp = records[0].split(' ').nth(1).unwrap().to_string();
+            timestamp[timestamp.len() - 6..].to_string()
+        };
+        assert_eq!(offset(utc.records()), "+00:00");
+        assert_eq!(offset(shanghai.records()), "+08:00");
+    }
+
+    #[test]
+    fn test_route_by_level() {
+        let alerts = CollectAppend::default();
+        let bulk = CollectAppend::default();
+        let logger = Logger::new()
+            .route_levels(
+                Level::Error..=Level::Warn,
+                Dispatch::new().append(alerts.clone()),
+            )
+            .dispatch(Dispatch::new().append(bulk.clone()));
+
+        for level in Level::iter() {
+            logger.log(
+                &Record::builder()
+                    .level(level)
+                    .args(format_args!("hello"))
+                    .build(),
+            );
+        }
+        assert_eq!(alerts.records(), ["ERROR hello", "WARN hello"]);
+        assert_eq!(bulk.records(), ["INFO hello", "DEBUG hello", "TRACE hello"]);
+    }
+
+    #[test]
+    fn test_route_by_reversed_levels() {
+        let alerts = CollectAppend::default();
+        let logger = Logger::new()
+            .route_levels(
+                Level::Warn..=Level::Error,
+                Dispatch::new().append(alerts.clone()),
+            )
+            .dispatch(Dispatch::new().append(CollectAppend::default()));
+
+        for level in Level::iter() {
+            logger.log(
+                &Record::builder()
+                    .level(level)
+                    .args(format_args!("hello"))
+                    .build(),
+            );
+        }
+        assert_eq!(alerts.records(), ["ERROR hello", "WARN hello"]);
+    }
+}
@@ -0,0 +1,145 @@
+// Copyright 2024 CratesLand Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(all(feature = "json", feature = "rolling_file"))]
+use std::path::Path;
+
+use log::LevelFilter;
+
+use crate::append;
+#[cfg(all(feature = "json", feature = "rolling_file"))]
+use crate::append::rolling_file;
+use crate::append::Append;
+#[cfg(all(feature = "json", feature = "rolling_file"))]
+use crate::layout::JsonLayout;
+use crate::layout::TextLayout;
+use crate::Dispatch;
+use crate::Logger;
+
+/// A [`Logger`] with the recommended defaults for local development: colored text logs of
+/// `Debug` level and above to stderr.
+///
+/// ```rust
+/// logforth::dev().apply().unwrap();
+/// ```
+pub fn dev() -> Logger {
+    dev_to(append::Stderr::default())
+}
+
+fn dev_to(append: impl Append) -> Logger {
+    Logger::new().dispatch(
+        Dispatch::new()
+            .filter(LevelFilter::Debug)
+            .layout(TextLayout::default())
+            .append(append),
+    )
+}
+
+/// A [`Logger`] with the recommended defaults for production: JSON logs of `Info` level and above
+/// to daily rolling files under `dir`, named `{prefix}.{date}.{n}.log`, of which the latest 7
+/// files are kept.
+///
+/// The returned [`WorkerGuard`](rolling_file::WorkerGuard) must be held until the program exits,
+/// so that the buffered records are flushed.
+///
+/// ```rust,no_run
+/// let (logger, _guard) = logforth::prod("logs", "app").unwrap();
+/// logger.apply().unwrap();
+/// ```
+#[cfg(all(feature = "json", feature = "rolling_file"))]
+pub fn prod(
+    dir: impl AsRef<Path>,
+    prefix: impl Into<String>,
+) -> anyhow::Result<(Logger, rolling_file::WorkerGuard)> {
+    let rolling = rolling_file::RollingFileWriter::builder()
+        .rotation(rolling_file::Rotation::Daily)
+        .filename_prefix(prefix)
+        .filename_suffix("log")
+        .max_log_files(7)
+        .build(dir)?;
+    let (writer, guard) = rolling_file::NonBlockingBuilder::default().finish(rolling);
+
+    let logger = Logger::new().dispatch(
+        Dispatch::new()
+            .filter(LevelFilter::Info)
+            .layout(JsonLayout::default())
+            .append(rolling_file::RollingFile::new(writer)),
+    );
+    Ok((logger, guard))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    use log::Log;
+    use log::Metadata;
+
+    use super::*;
+
+    fn enabled(logger: &Logger, level: log::Level) -> bool {
+        logger.enabled(&Metadata::builder().level(level).build())
+    }
+
+    #[test]
+    fn test_dev() {
+        let logger = dev();
+        assert!(enabled(&logger, log::Level::Debug));
+        assert!(!enabled(&logger, log::Level::Trace));
+
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let logger = dev_to(append::Writer::new(buffer.clone()));
+        for level in [log::Level::Debug, log::Level::Trace] {
+            logger.log(
+                &log::Record::builder()
+                    .level(level)
+                    .args(format_args!("hello"))
+                    .build(),
+            );
+        }
+        logger.flush();
+        // only the debug record, as text, e.g., `2024-08-10T17:12:52.123+08:00 DEBUG :
+        // hello`
+        let output = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert_eq!(output.lines().count(), 1, "{output:?}");
+        let (timestamp, line) = output.split_once(' ').unwrap();
+        assert!(timestamp.parse::<jiff::Timestamp>().is_ok(), "{output:?}");
+        assert!(line.contains("DEBUG"), "{output:?}");
+        assert!(line.ends_with(" hello\n"), "{output:?}");
+    }
+
+    #[cfg(all(feature = "json", feature = "rolling_file"))]
+    #[test]
+    fn test_prod() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let (logger, guard) = prod(&dir, "app").unwrap();
+        assert!(enabled(&logger, log::Level::Info));
+        assert!(!enabled(&logger, log::Level::Debug));
+
+        logger.log(&log::Record::builder().args(format_args!("hello")).build());
+        drop(guard);
+
+        let files = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        assert_eq!(files.len(), 1, "{files:?}");
+        let name = files[0].file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("app.") && name.ends_with(".log"), "{name}");
+        let line: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&files[0]).unwrap()).unwrap();
+        assert_eq!(line["message"], "hello");
+    }
+}
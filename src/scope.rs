@@ -0,0 +1,186 @@
+// Copyright 2024 CratesLand Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Instant;
+
+use log::Level;
+use log::Log;
+use log::Record;
+
+/// A guard that logs how long a scope takes once it's dropped.
+///
+/// On drop, the guard logs `{name} finished` with an `elapsed_ms` key-value at the configured
+/// level, `Trace` by default. The duration is measured with a monotonic clock, so it's not affected
+/// by wall clock adjustments.
+///
+/// ```rust
+/// use logforth::TimedScope;
+///
+/// fn compact() {
+///     let _scope = TimedScope::new("compact")
+///         .level(log::Level::Debug)
+///         .log_start();
+///     // ...
+/// }
+/// ```
+pub struct TimedScope {
+    name: String,
+    target: String,
+    level: Level,
+    start: Instant,
+    clock: MonotonicClock,
+    logger: &'static dyn Log,
+}
+
+impl std::fmt::Debug for TimedScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TimedScope")
+            .field("name", &self.name)
+            .field("target", &self.target)
+            .field("level", &self.level)
+            .field("start", &self.start)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TimedScope {
+    /// Start timing a scope named `name`. The records are logged with the target `scope`.
+    pub fn new(name: impl Into<String>) -> TimedScope {
+        let clock = MonotonicClock::Std;
+        TimedScope {
+            name: name.into(),
+            target: "scope".to_string(),
+            level: Level::Trace,
+            start: clock.now(),
+            clock,
+            logger: log::logger(),
+        }
+    }
+
+    /// Set the level of the records.
+    pub fn level(mut self, level: Level) -> TimedScope {
+        self.level = level;
+        self
+    }
+
+    /// Set the target of the records.
+    pub fn target(mut self, target: impl Into<String>) -> TimedScope {
+        self.target = target.into();
+        self
+    }
+
+    /// Log `{name} started` and restart timing from now.
+    pub fn log_start(mut self) -> TimedScope {
+        self.log(format_args!("{} started", self.name), &[]);
+        self.start = self.clock.now();
+        self
+    }
+
+    fn log(&self, args: std::fmt::Arguments, kvs: &[(&str, log::kv::Value)]) {
+        if self.level <= log::max_level() {
+            self.logger.log(
+                &Record::builder()
+                    .level(self.level)
+                    .target(&self.target)
+                    .args(args)
+                    .key_values(&kvs)
+                    .build(),
+            );
+        }
+    }
+}
+
+impl Drop for TimedScope {
+    fn drop(&mut self) {
+        let elapsed = self.clock.now().saturating_duration_since(self.start);
+        let elapsed_ms = elapsed.as_millis() as u64;
+        self.log(
+            format_args!("{} finished", self.name),
+            &[("elapsed_ms", log::kv::Value::from(elapsed_ms))],
+        );
+    }
+}
+
+#[derive(Debug)]
+enum MonotonicClock {
+    Std,
+    #[cfg(test)]
+    Manual(std::sync::Arc<std::sync::Mutex<Instant>>),
+}
+
+impl MonotonicClock {
+    fn now(&self) -> Instant {
+        match self {
+            MonotonicClock::Std => Instant::now(),
+            #[cfg(test)]
+            MonotonicClock::Manual(now) => *now.lock().unwrap(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use log::Metadata;
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct CollectLog(Mutex<Vec<String>>);
+
+    impl Log for CollectLog {
+        fn enabled(&self, _: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            let elapsed_ms = record.key_values().get("elapsed_ms".into());
+            self.0.lock().unwrap().push(format!(
+                "{} {} {} {:?}",
+                record.level(),
+                record.target(),
+                record.args(),
+                elapsed_ms.and_then(|value| value.to_u64())
+            ));
+        }
+
+        fn flush(&self) {}
+    }
+
+    #[test]
+    fn test_timed_scope() {
+        log::set_max_level(log::LevelFilter::Trace);
+        let logger: &'static CollectLog = Box::leak(Box::default());
+        let now = Arc::new(Mutex::new(Instant::now()));
+
+        let mut scope = TimedScope::new("compact").level(Level::Debug).target("db");
+        scope.clock = MonotonicClock::Manual(now.clone());
+        scope.logger = logger;
+        let scope = scope.log_start();
+
+        *now.lock().unwrap() += Duration::from_millis(42);
+        drop(scope);
+
+        assert_eq!(
+            *logger.0.lock().unwrap(),
+            [
+                "DEBUG db compact started None",
+                "DEBUG db compact finished Some(42)"
+            ]
+        );
+    }
+}